@@ -0,0 +1,46 @@
+use crate::nodes::Type;
+use crate::x86::{Reg, Xmm};
+
+// the System V AMD64 calling convention, which hj functions follow so they can call and be called by C code
+// floats are passed in the first eight SSE registers, everything else (ints, bools, chars and pointers to strings) in the
+// first six general purpose registers, as 64 bit values of which only the bits of the C type are defined (the lowest byte
+// of a _Bool, 32 bits of a uint32_t for a char, 8 to 32 bits of the narrow ints), so hj extends narrow arguments at the
+// start of a function and narrow results after a call, and passes its own ones extended like they are kept everywhere
+// the arguments that don't fit into registers are pushed onto the stack from right to left, rsp has to be 16 byte aligned
+// at the call, so the first one ends up right above the return address
+// results are returned in rax or in xmm0 for floats
+// rbx, rbp and r12 to r15 have to be preserved by the called function, everything else may be changed
+
+pub const INT_ARGS: [Reg; 6] = [Reg::Rdi, Reg::Rsi, Reg::Rdx, Reg::Rcx, Reg::R8, Reg::R9];
+pub const FLOAT_ARGS: [Xmm; 8] = [Xmm::Xmm0, Xmm::Xmm1, Xmm::Xmm2, Xmm::Xmm3, Xmm::Xmm4, Xmm::Xmm5, Xmm::Xmm6, Xmm::Xmm7];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArgLocation {
+    Reg(Reg),
+    Xmm(Xmm),
+    // the index of the 8 byte slot on the stack, the first one is at rsp when the function gets called (rbp + 16 inside it)
+    Stack(usize),
+}
+
+// where the arguments of a call with these types go
+pub fn classify(types: &[Type]) -> Vec<ArgLocation> {
+    let mut int_count = 0;
+    let mut float_count = 0;
+    let mut stack_count = 0;
+    types.iter().map(|t| {
+        if *t == Type::Float && float_count < FLOAT_ARGS.len() {
+            float_count += 1;
+            ArgLocation::Xmm(FLOAT_ARGS[float_count - 1])
+        } else if *t != Type::Float && int_count < INT_ARGS.len() {
+            int_count += 1;
+            ArgLocation::Reg(INT_ARGS[int_count - 1])
+        } else {
+            stack_count += 1;
+            ArgLocation::Stack(stack_count - 1)
+        }
+    }).collect()
+}
+
+pub fn stack_arg_count(locations: &[ArgLocation]) -> usize {
+    locations.iter().filter(|location| matches!(location, ArgLocation::Stack(_))).count()
+}
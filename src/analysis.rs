@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+
+use crate::ir::*;
+
+// facts about the control flow and the registers of a function, which the SSA construction and the optimizations need
+
+// a block dominates another one if every path from the entry block to the other block goes through it
+// computed with "A Simple, Fast Dominance Algorithm" by Cooper, Harvey and Kennedy
+pub struct Dominators {
+    // the immediate dominator of each block, None for the entry block and unreachable blocks
+    pub idom: Vec<Option<BlockId>>,
+    // the blocks each block immediately dominates (the children in the dominator tree)
+    pub children: Vec<Vec<BlockId>>,
+    // where the dominance of each block ends: the blocks it doesn't dominate, but one of their predecessors
+    pub frontiers: Vec<Vec<BlockId>>,
+}
+
+impl Dominators {
+    pub fn new(function: &Function) -> Dominators {
+        let block_count = function.blocks.len();
+        let order = function.reverse_postorder();
+        let mut order_index = vec!(usize::MAX; block_count);
+        for (i, block) in order.iter().enumerate() {
+            order_index[block.0] = i;
+        }
+        let predecessors = function.predecessors();
+
+        let mut idom: Vec<Option<BlockId>> = vec!(None; block_count);
+        idom[0] = Some(BlockId(0));
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in order.iter().skip(1) {
+                let mut new_idom: Option<BlockId> = None;
+                for pred in &predecessors[block.0] {
+                    if idom[pred.0].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => *pred,
+                        Some(other) => Dominators::intersect(&idom, &order_index, *pred, other),
+                    });
+                }
+                if new_idom.is_some() && idom[block.0] != new_idom {
+                    idom[block.0] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        // the entry block has no immediate dominator, it only pointed to itself for the algorithm
+        idom[0] = None;
+
+        let mut children = vec!(vec!(); block_count);
+        for block in &order {
+            if let Some(parent) = idom[block.0] {
+                children[parent.0].push(*block);
+            }
+        }
+
+        let mut frontiers: Vec<Vec<BlockId>> = vec!(vec!(); block_count);
+        for block in &order {
+            let preds = &predecessors[block.0];
+            if preds.len() < 2 {
+                continue;
+            }
+            for pred in preds {
+                if order_index[pred.0] == usize::MAX {
+                    continue;
+                }
+                let mut runner = Some(*pred);
+                while let Some(current) = runner {
+                    if Some(current) == idom[block.0] {
+                        break;
+                    }
+                    if !frontiers[current.0].contains(block) {
+                        frontiers[current.0].push(*block);
+                    }
+                    runner = idom[current.0];
+                }
+            }
+        }
+
+        Dominators { idom, children, frontiers }
+    }
+
+    // walks up the tree from both blocks until they meet (the entry block points to itself during the computation)
+    fn intersect(idom: &[Option<BlockId>], order_index: &[usize], mut a: BlockId, mut b: BlockId) -> BlockId {
+        while a != b {
+            while order_index[a.0] > order_index[b.0] {
+                a = idom[a.0].unwrap();
+            }
+            while order_index[b.0] > order_index[a.0] {
+                b = idom[b.0].unwrap();
+            }
+        }
+        a
+    }
+
+    pub fn dominates(&self, a: BlockId, mut b: BlockId) -> bool {
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.idom[b.0] {
+                Some(parent) => b = parent,
+                None => return false,
+            }
+        }
+    }
+}
+
+// which registers still get read later at the start and at the end of each block
+// the operands of a phi are read at the end of the block they come from, not in the block of the phi
+pub struct Liveness {
+    pub live_in: Vec<HashSet<VReg>>,
+    pub live_out: Vec<HashSet<VReg>>,
+}
+
+impl Liveness {
+    pub fn new(function: &Function) -> Liveness {
+        let block_count = function.blocks.len();
+        // the registers a block reads before writing them, and the ones it writes
+        let mut uses: Vec<HashSet<VReg>> = vec!(HashSet::new(); block_count);
+        let mut defs: Vec<HashSet<VReg>> = vec!(HashSet::new(); block_count);
+        // the registers phis in the successors read at the end of the block
+        let mut phi_uses: Vec<HashSet<VReg>> = vec!(HashSet::new(); block_count);
+        for (i, block) in function.blocks.iter().enumerate() {
+            for inst in &block.insts {
+                if let Inst::Phi { incoming, .. } = inst {
+                    for (pred, operand) in incoming {
+                        if let Operand::Reg(reg) = operand {
+                            phi_uses[pred.0].insert(*reg);
+                        }
+                    }
+                } else {
+                    for operand in inst.operands() {
+                        if let Operand::Reg(reg) = operand {
+                            if !defs[i].contains(reg) {
+                                uses[i].insert(*reg);
+                            }
+                        }
+                    }
+                }
+                if let Some(dest) = inst.dest() {
+                    defs[i].insert(dest);
+                }
+            }
+            for operand in block.terminator.operands() {
+                if let Operand::Reg(reg) = operand {
+                    if !defs[i].contains(reg) {
+                        uses[i].insert(*reg);
+                    }
+                }
+            }
+        }
+
+        let mut live_in: Vec<HashSet<VReg>> = vec!(HashSet::new(); block_count);
+        let mut live_out: Vec<HashSet<VReg>> = vec!(HashSet::new(); block_count);
+        // going through the blocks backwards makes the information flow faster
+        let order: Vec<BlockId> = function.reverse_postorder().into_iter().rev().collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in &order {
+                let mut out = phi_uses[block.0].clone();
+                for successor in function.block(*block).terminator.successors() {
+                    out.extend(live_in[successor.0].iter());
+                }
+                let mut new_in = uses[block.0].clone();
+                new_in.extend(out.iter().filter(|reg| !defs[block.0].contains(reg)));
+                if new_in != live_in[block.0] || out != live_out[block.0] {
+                    live_in[block.0] = new_in;
+                    live_out[block.0] = out;
+                    changed = true;
+                }
+            }
+        }
+
+        Liveness { live_in, live_out }
+    }
+}
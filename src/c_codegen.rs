@@ -0,0 +1,479 @@
+use std::collections::HashSet;
+
+use crate::integer;
+use crate::ir::{self, BinaryOp, BlockId, Constant, Function, Inst, Module, Operand, Terminator, UnaryOp, VReg};
+use crate::nodes::Type;
+use crate::ssa;
+
+// turns the IR into C99 source, as a second backend that works with any C compiler and as a cross-check for the x86 one
+// every register of the IR becomes a local variable (rn for %n), every block a label that's jumped to with goto
+// ints are int64_t (and the other int types the matching stdint.h types), floats double, bools bool, chars uint32_t (the
+// code point) and strings hj_str, a length and a pointer to the UTF-8 bytes
+// the output has to behave exactly like the native executable: ints wrap around (signed overflow isn't defined in C, so
+// the arithmetic goes through helper functions), dividing by zero raises SIGFPE and print formats values like the runtime
+// user-defined functions are called fn_name, so they don't collide with C keywords or the C library
+
+// the helpers every program is compiled with, they're static inline so the C compiler doesn't warn about unused ones
+const RUNTIME: &str = r#"#include <inttypes.h>
+#include <math.h>
+#include <signal.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+// the length of the string in bytes and its UTF-8 bytes (not terminated by a null byte)
+typedef struct {
+    int64_t length;
+    const char *bytes;
+} hj_str;
+
+// dividing by zero (or the smallest int by -1) crashes like the idiv instruction of x86
+static inline void hj_trap(void) {
+    fflush(stdout);
+    raise(SIGFPE);
+    abort();
+}
+
+static inline int64_t hj_add(int64_t left, int64_t right) {
+    return (int64_t)((uint64_t)left + (uint64_t)right);
+}
+
+static inline int64_t hj_sub(int64_t left, int64_t right) {
+    return (int64_t)((uint64_t)left - (uint64_t)right);
+}
+
+static inline int64_t hj_mul(int64_t left, int64_t right) {
+    return (int64_t)((uint64_t)left * (uint64_t)right);
+}
+
+static inline int64_t hj_div(int64_t left, int64_t right) {
+    if (right == 0 || (left == INT64_MIN && right == -1)) {
+        hj_trap();
+    }
+    return left / right;
+}
+
+static inline int64_t hj_rem(int64_t left, int64_t right) {
+    if (right == 0 || (left == INT64_MIN && right == -1)) {
+        hj_trap();
+    }
+    return left % right;
+}
+
+static inline uint64_t hj_udiv(uint64_t left, uint64_t right) {
+    if (right == 0) {
+        hj_trap();
+    }
+    return left / right;
+}
+
+static inline uint64_t hj_urem(uint64_t left, uint64_t right) {
+    if (right == 0) {
+        hj_trap();
+    }
+    return left % right;
+}
+
+static inline int64_t hj_neg(int64_t value) {
+    return (int64_t)(0 - (uint64_t)value);
+}
+
+// the count is already taken modulo the width of the type, shifting a negative value to the left isn't defined in C
+static inline int64_t hj_shl(uint64_t value, int64_t count) {
+    return (int64_t)(value << count);
+}
+
+// >> on a negative value is implementation-defined in C, so the sign is kept by shifting the complement
+static inline int64_t hj_sar(int64_t value, int64_t count) {
+    return value < 0 ? ~(~value >> count) : value >> count;
+}
+
+static inline uint64_t hj_shr(uint64_t value, int64_t count) {
+    return value >> count;
+}
+
+// floats are rounded toward zero, values outside of the range of the type become its lowest or highest value and NaN
+// becomes 0 (converting them directly isn't defined in C)
+static inline int64_t hj_ftoi(double value, int64_t min, int64_t max) {
+    if (isnan(value)) {
+        return 0;
+    }
+    if (value <= (double)min) {
+        return min;
+    }
+    // max + 1 is a power of two, which this gives exactly (for 64 bits, max is rounded up to it already)
+    if (value >= (double)max + 1.0) {
+        return max;
+    }
+    return (int64_t)value;
+}
+
+static inline uint64_t hj_ftou(double value, uint64_t max) {
+    // also true for NaN
+    if (!(value > 0.0)) {
+        return 0;
+    }
+    if (value >= (double)max + 1.0) {
+        return max;
+    }
+    return (uint64_t)value;
+}
+
+// values that aren't code points become the replacement character U+FFFD, a negative int is above 0x10ffff here
+static inline uint32_t hj_to_char(uint64_t value) {
+    if (value > 0x10ffff || (value >= 0xd800 && value <= 0xdfff)) {
+        return 0xfffd;
+    }
+    return (uint32_t)value;
+}
+
+static inline void hj_print_int(int64_t value) {
+    printf("%" PRId64 "\n", value);
+}
+
+static inline void hj_print_uint(uint64_t value) {
+    printf("%" PRIu64 "\n", value);
+}
+
+// 6 decimals, with an exponent from 1e12 on (see format_float in interpreter.rs)
+static inline void hj_print_float(double value) {
+    if (isnan(value)) {
+        puts("NaN");
+        return;
+    }
+    if (value < 0.0) {
+        putchar('-');
+        value = -value;
+    }
+    if (isinf(value)) {
+        puts("inf");
+        return;
+    }
+    int exponent = 0;
+    if (value >= 1e12) {
+        while (value >= 10.0) {
+            value /= 10.0;
+            exponent++;
+        }
+    }
+    // nearbyint rounds ties to even like the runtime
+    uint64_t millionths = (uint64_t)nearbyint(value * 1e6);
+    printf("%" PRIu64 ".%06" PRIu64, millionths / 1000000, millionths % 1000000);
+    if (exponent > 0) {
+        printf("e%d", exponent);
+    }
+    putchar('\n');
+}
+
+static inline void hj_print_bool(bool value) {
+    puts(value ? "true" : "false");
+}
+
+static inline void hj_print_char(uint32_t value) {
+    char bytes[4];
+    int length;
+    if (value < 0x80) {
+        bytes[0] = (char)value;
+        length = 1;
+    } else if (value < 0x800) {
+        bytes[0] = (char)(0xc0 | (value >> 6));
+        bytes[1] = (char)(0x80 | (value & 0x3f));
+        length = 2;
+    } else if (value < 0x10000) {
+        bytes[0] = (char)(0xe0 | (value >> 12));
+        bytes[1] = (char)(0x80 | ((value >> 6) & 0x3f));
+        bytes[2] = (char)(0x80 | (value & 0x3f));
+        length = 3;
+    } else {
+        bytes[0] = (char)(0xf0 | (value >> 18));
+        bytes[1] = (char)(0x80 | ((value >> 12) & 0x3f));
+        bytes[2] = (char)(0x80 | ((value >> 6) & 0x3f));
+        bytes[3] = (char)(0x80 | (value & 0x3f));
+        length = 4;
+    }
+    fwrite(bytes, 1, (size_t)length, stdout);
+    putchar('\n');
+}
+
+static inline void hj_print_str(hj_str value) {
+    fwrite(value.bytes, 1, (size_t)value.length, stdout);
+    putchar('\n');
+}
+
+static inline void hj_exit(int64_t code) {
+    exit((int)code);
+}
+"#;
+
+const MAIN_NAME: &str = "hj_main";
+
+pub fn generate(module: &Module) -> String {
+    let mut text = String::from("// generated by hj, compile with: cc -std=c99 <file> -lm\n");
+    text.push_str(RUNTIME);
+
+    text.push('\n');
+    for function in module.all_functions() {
+        text.push_str(&format!("{};\n", signature(function)));
+    }
+    for function in module.all_functions() {
+        text.push('\n');
+        text.push_str(&generate_function(function));
+    }
+    text.push_str(&format!("\nint main(void) {{\n    {}();\n    return 0;\n}}\n", MAIN_NAME));
+    text
+}
+
+fn c_type(t: Type) -> &'static str {
+    match t {
+        Type::Int => "int64_t",
+        Type::I8 => "int8_t",
+        Type::I16 => "int16_t",
+        Type::I32 => "int32_t",
+        Type::UInt => "uint64_t",
+        Type::U8 => "uint8_t",
+        Type::U16 => "uint16_t",
+        Type::U32 => "uint32_t",
+        Type::Float => "double",
+        Type::Bool => "bool",
+        Type::Char => "uint32_t",
+        Type::Str => "hj_str",
+        Type::Error => panic!("Internal compiler error (error type in the IR)"),
+    }
+}
+
+fn function_name(name: &str) -> String {
+    if name == ir::MAIN_NAME {
+        MAIN_NAME.to_string()
+    } else {
+        format!("fn_{}", name)
+    }
+}
+
+fn signature(function: &Function) -> String {
+    let return_type = function.return_type.map_or("void", c_type);
+    let params: Vec<String> = function.params.iter().map(|param| format!("{} {}", c_type(function.reg_type(*param)), reg(*param))).collect();
+    let params = if params.is_empty() { "void".to_string() } else { params.join(", ") };
+    // the program itself can't be called from other C files
+    let storage = if function.name == ir::MAIN_NAME { "static " } else { "" };
+    format!("{}{} {}({})", storage, return_type, function_name(&function.name), params)
+}
+
+fn reg(reg: VReg) -> String {
+    format!("r{}", reg.0)
+}
+
+fn operand(operand: &Operand) -> String {
+    match operand {
+        Operand::Reg(r) => reg(*r),
+        Operand::Const(constant) => literal(constant),
+    }
+}
+
+// negative constants get parentheses, so a minus in front of them doesn't turn into a decrement
+fn negated(src: &Operand) -> String {
+    let text = operand(src);
+    if text.starts_with('-') { format!("-({})", text) } else { format!("-{}", text) }
+}
+
+fn literal(constant: &Constant) -> String {
+    match constant {
+        // the smallest int can't be written as a literal, 9223372036854775808 is too big before it's negated
+        Constant::Int(i64::MIN, Type::Int) => "INT64_MIN".to_string(),
+        // without the suffix, the biggest uints would be too big for any signed C type
+        Constant::Int(value, t @ (Type::U32 | Type::UInt)) => format!("{}u", integer::to_string(*value, *t)),
+        Constant::Int(value, t) => integer::to_string(*value, *t),
+        Constant::Float(value) if value.is_nan() => "NAN".to_string(),
+        Constant::Float(value) if value.is_infinite() => if *value > 0.0 { "INFINITY".to_string() } else { "-INFINITY".to_string() },
+        // debug formatting is the shortest text that gets parsed back to the same float, C reads it the same way
+        Constant::Float(value) => format!("{:?}", value),
+        Constant::Bool(value) => value.to_string(),
+        Constant::Char(value) if (value.is_ascii_graphic() && *value != '\'' && *value != '\\') || *value == ' ' => format!("'{}'", value),
+        Constant::Char(value) => format!("0x{:x}", *value as u32),
+        Constant::Str(value) => format!("(hj_str){{{}, \"{}\"}}", value.len(), escape(value)),
+    }
+}
+
+// everything except printable ASCII is written as an octal escape (which is never longer than three digits, unlike \x)
+// question marks are escaped because of trigraphs like ??=
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'"' | b'\\' | b'?' => {
+                escaped.push('\\');
+                escaped.push(byte as char);
+            }
+            0x20..=0x7e => escaped.push(byte as char),
+            _ => escaped.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    escaped
+}
+
+fn generate_function(function: &Function) -> String {
+    let mut function = function.clone();
+    ssa::destruct(&mut function);
+    let order = function.reverse_postorder();
+
+    // the statements of every block, a block only gets a label if something jumps to it with goto
+    let mut blocks: Vec<(BlockId, Vec<String>)> = vec!();
+    let mut labels = HashSet::new();
+    let mut used_regs = HashSet::new();
+    for (i, id) in order.iter().enumerate() {
+        let block = function.block(*id);
+        let mut lines = vec!();
+        for inst in &block.insts {
+            used_regs.extend(inst.dest());
+            used_regs.extend(inst.operands().into_iter().filter_map(|op| if let Operand::Reg(r) = op { Some(*r) } else { None }));
+            lines.push(generate_inst(&function, inst));
+        }
+        used_regs.extend(block.terminator.operands().into_iter().filter_map(|op| if let Operand::Reg(r) = op { Some(*r) } else { None }));
+
+        let next = order.get(i + 1).copied();
+        let mut goto = |target: BlockId| {
+            labels.insert(target);
+            format!("goto bb{};", target.0)
+        };
+        match &block.terminator {
+            Terminator::Jump(target) => {
+                if Some(*target) != next {
+                    lines.push(goto(*target));
+                }
+            }
+            Terminator::Branch { cond, then_block, else_block } => {
+                if Some(*then_block) == next {
+                    lines.push(format!("if (!{}) {}", operand(cond), goto(*else_block)));
+                } else {
+                    lines.push(format!("if ({}) {}", operand(cond), goto(*then_block)));
+                    if Some(*else_block) != next {
+                        lines.push(goto(*else_block));
+                    }
+                }
+            }
+            Terminator::Return(Some(value)) => lines.push(format!("return {};", operand(value))),
+            Terminator::Return(None) => lines.push("return;".to_string()),
+            Terminator::Unreachable => lines.push("abort();".to_string()),
+        }
+        blocks.push((*id, lines));
+    }
+
+    let mut text = format!("{} {{\n", signature(&function));
+    // the local variables, grouped by type
+    for t in Type::INTEGERS.into_iter().chain([Type::Float, Type::Bool, Type::Char, Type::Str]) {
+        let mut regs: Vec<VReg> = used_regs.iter().copied().filter(|r| function.reg_type(*r) == t && !function.params.contains(r)).collect();
+        regs.sort();
+        if !regs.is_empty() {
+            let names: Vec<String> = regs.into_iter().map(reg).collect();
+            text.push_str(&format!("    {} {};\n", c_type(t), names.join(", ")));
+        }
+    }
+    for (id, lines) in blocks {
+        if labels.contains(&id) {
+            // a label has to be followed by a statement, the empty one is enough
+            text.push_str(&format!("bb{}:;\n", id.0));
+        }
+        for line in lines {
+            text.push_str(&format!("    {}\n", line));
+        }
+    }
+    text.push_str("}\n");
+    text
+}
+
+fn generate_inst(function: &Function, inst: &Inst) -> String {
+    match inst {
+        Inst::Copy { dest, src } => format!("{} = {};", reg(*dest), operand(src)),
+        Inst::Binary { dest, op, left, right } => {
+            let t = function.operand_type(left);
+            let value = match op {
+                // the helpers calculate with 64 bits, the result is cut down to the type again
+                BinaryOp::Div | BinaryOp::Rem if t.is_integer() && !t.is_signed() => {
+                    let helper = if *op == BinaryOp::Div { "hj_udiv" } else { "hj_urem" };
+                    int_result(t, Type::UInt, format!("{}({}, {})", helper, operand(left), operand(right)))
+                }
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem if t.is_integer() => {
+                    int_result(t, Type::Int, format!("hj_{}({}, {})", op.to_str(), operand(left), operand(right)))
+                }
+                BinaryOp::Shl | BinaryOp::Shr => {
+                    let (helper, helper_type) = match op {
+                        BinaryOp::Shl => ("hj_shl", Type::Int),
+                        _ if t.is_signed() => ("hj_sar", Type::Int),
+                        _ => ("hj_shr", Type::UInt),
+                    };
+                    int_result(t, helper_type, format!("{}({}, {} & {})", helper, operand(left), operand(right), t.bits() - 1))
+                }
+                BinaryOp::Rem => format!("fmod({}, {})", operand(left), operand(right)),
+                _ => format!("{} {} {}", operand(left), c_operator(*op), operand(right)),
+            };
+            format!("{} = {};", reg(*dest), value)
+        }
+        Inst::Unary { dest, op: UnaryOp::Neg, src } if function.operand_type(src).is_integer() => {
+            format!("{} = {};", reg(*dest), int_result(function.operand_type(src), Type::Int, format!("hj_neg({})", operand(src))))
+        }
+        Inst::Unary { dest, op: UnaryOp::Neg, src } => format!("{} = {};", reg(*dest), negated(src)),
+        Inst::Unary { dest, op: UnaryOp::Not, src } => format!("{} = !{};", reg(*dest), operand(src)),
+        Inst::Convert { dest, src } => {
+            let (from, to) = (function.operand_type(src), function.reg_type(*dest));
+            let value = match (from, to) {
+                (Type::Float, to) => {
+                    let (min, max) = integer::range(to);
+                    let (min, max) = (literal(&Constant::Int(min as i64, to)), literal(&Constant::Int(max as i64, to)));
+                    if to.is_signed() {
+                        int_result(to, Type::Int, format!("hj_ftoi({}, {}, {})", operand(src), min, max))
+                    } else {
+                        int_result(to, Type::UInt, format!("hj_ftou({}, {})", operand(src), max))
+                    }
+                }
+                (_, Type::Char) => format!("hj_to_char({})", operand(src)),
+                (_, to) => format!("({}){}", c_type(to), operand(src)),
+            };
+            format!("{} = {};", reg(*dest), value)
+        }
+        Inst::Call { function: name, args, .. } if name == "print" => {
+            let helper = match function.operand_type(&args[0]) {
+                Type::UInt => "hj_print_uint",
+                t if t.is_integer() => "hj_print_int",
+                Type::Float => "hj_print_float",
+                Type::Bool => "hj_print_bool",
+                Type::Char => "hj_print_char",
+                Type::Str => "hj_print_str",
+                _ => panic!("Internal compiler error (untyped print argument)"),
+            };
+            format!("{}({});", helper, operand(&args[0]))
+        }
+        Inst::Call { function: name, args, .. } if name == "exit" => format!("hj_exit({});", operand(&args[0])),
+        Inst::Call { dest, function: name, args } => {
+            let arg_strs: Vec<String> = args.iter().map(operand).collect();
+            let call = format!("{}({});", function_name(name), arg_strs.join(", "));
+            match dest {
+                Some(dest) => format!("{} = {}", reg(*dest), call),
+                None => call,
+            }
+        }
+        Inst::Phi { .. } => panic!("Internal compiler error (phi after SSA destruction)"),
+    }
+}
+
+// the call of a helper that returns the C type of helper_type, cast to the type of the result if that's a different one
+fn int_result(t: Type, helper_type: Type, call: String) -> String {
+    if t == helper_type { call } else { format!("({}){}", c_type(t), call) }
+}
+
+fn c_operator(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::Rem => "%",
+        BinaryOp::Shl => "<<",
+        BinaryOp::Shr => ">>",
+        BinaryOp::Eq => "==",
+        BinaryOp::Ne => "!=",
+        BinaryOp::Lt => "<",
+        BinaryOp::Le => "<=",
+        BinaryOp::Gt => ">",
+        BinaryOp::Ge => ">=",
+    }
+}
@@ -0,0 +1,962 @@
+use crate::abi::{self, ArgLocation};
+use crate::integer;
+use crate::ir::{self, BinaryOp, Block, BlockId, Constant, Function, Module, Terminator, UnaryOp, VReg};
+use crate::nodes::Type;
+use crate::regalloc::{self, Location};
+use crate::runtime;
+use crate::ssa;
+use crate::x86::{Cond, Inst, Operand, Program, Reg, Xmm};
+
+// turns the IR into x86-64 code for linux
+// the phis are turned back into copies first, then every register of the IR gets a location (see regalloc.rs)
+// rax, rdx, r11, xmm14 and xmm15 are never allocated, they're used for divisions and for moving values between locations
+// floats live in SSE registers, strings are pointers to their length followed by the bytes (in the data section)
+// ints of every width are kept in 64 bits (sign-extended or zero-extended, see the integer module), so after operations that
+// can leave the range of a narrow type the result gets extended again
+// the main function is called from _start, which exits with 0 when it returns
+// functions follow the System V calling convention (see abi.rs) and keep their names, so C code can call them
+pub struct CodeGenerator {
+    text: Vec<Inst>,
+    data: Vec<(String, Vec<u8>)>,
+    globals: Vec<String>,
+    // without register allocation (-O0) every value gets its own stack slot
+    allocate_registers: bool,
+    label_count: usize,
+    // the function that's being generated
+    locations: Vec<Location>,
+    reg_types: Vec<Type>,
+    block_labels: Vec<String>,
+    // where a jump to each block ends up, blocks that only jump somewhere else are skipped
+    jump_targets: Vec<BlockId>,
+    callee_saved: Vec<Reg>,
+}
+
+const MAIN_LABEL: &str = "hj_main";
+
+// where a value for a move comes from
+#[derive(Clone, PartialEq, Debug)]
+enum Source {
+    At(Location),
+    Const(Constant),
+}
+
+// the symbol of a function, the names of the runtime routines and of the entry point can't be used
+fn function_label(name: &str) -> String {
+    if name == ir::MAIN_NAME {
+        MAIN_LABEL.to_string()
+    } else if name.starts_with("hj_") || name == "_start" {
+        format!("hj_fn_{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+impl CodeGenerator {
+    pub fn new(allocate_registers: bool) -> CodeGenerator {
+        CodeGenerator {
+            text: vec!(),
+            data: vec!(),
+            globals: vec!(),
+            allocate_registers,
+            label_count: 0,
+            locations: vec!(),
+            reg_types: vec!(),
+            block_labels: vec!(),
+            jump_targets: vec!(),
+            callee_saved: vec!(),
+        }
+    }
+
+    pub fn generate(mut self, module: &Module) -> Program {
+        // rbp is 0 in the outermost frame, so debuggers know where the stack ends
+        self.emit(Inst::Label("_start".to_string()));
+        self.emit(Inst::Xor(Operand::Reg(Reg::Rbp), Operand::Reg(Reg::Rbp)));
+        self.emit(Inst::Call(MAIN_LABEL.to_string()));
+        self.emit(Inst::Xor(Operand::Reg(Reg::Rdi), Operand::Reg(Reg::Rdi)));
+        self.emit(Inst::Call(runtime::EXIT.to_string()));
+
+        for function in module.all_functions() {
+            self.generate_function(function);
+        }
+
+        self.text.append(&mut runtime::routines());
+        self.data.append(&mut runtime::data());
+        Program { text: self.text, data: self.data, globals: self.globals }
+    }
+
+    fn emit(&mut self, inst: Inst) {
+        self.text.push(inst);
+    }
+
+    fn new_label(&mut self) -> String {
+        self.label_count += 1;
+        format!(".L{}", self.label_count)
+    }
+
+    fn generate_function(&mut self, function: &Function) {
+        let mut function = function.clone();
+        ssa::destruct(&mut function);
+        let allocation = if self.allocate_registers {
+            regalloc::allocate(&function)
+        } else {
+            regalloc::allocate_naive(&function)
+        };
+        self.locations = allocation.locations;
+        self.reg_types = function.reg_types.clone();
+        self.callee_saved = allocation.callee_saved;
+        self.block_labels = (0..function.blocks.len()).map(|_| self.new_label()).collect();
+        // splitting edges for the phis leaves empty blocks behind when the copies in them got the same register on both sides
+        self.jump_targets = (0..function.blocks.len()).map(|i| {
+            let mut target = BlockId(i);
+            for _ in 0..function.blocks.len() {
+                match function.block(target) {
+                    Block { insts, terminator: Terminator::Jump(next) } if insts.iter().all(|inst| self.is_nop(inst)) => target = *next,
+                    _ => break,
+                }
+            }
+            target
+        }).collect();
+        let entry = allocation.order[0];
+        let order: Vec<BlockId> = allocation.order.iter().copied()
+            .filter(|block_id| *block_id == entry || self.jump_targets[block_id.0] == *block_id)
+            .collect();
+
+        // how often each register is read and written, a comparison that's only read by the branch after it doesn't need a result
+        let mut reads = vec!(0; function.reg_types.len());
+        let mut writes = vec!(0; function.reg_types.len());
+        for block in &function.blocks {
+            for inst in &block.insts {
+                for operand in inst.operands() {
+                    if let ir::Operand::Reg(reg) = operand {
+                        reads[reg.0] += 1;
+                    }
+                }
+                if let Some(dest) = inst.dest() {
+                    writes[dest.0] += 1;
+                }
+            }
+            for operand in block.terminator.operands() {
+                if let ir::Operand::Reg(reg) = operand {
+                    reads[reg.0] += 1;
+                }
+            }
+        }
+
+        // the stack slots are below rbp and the saved registers below them, rsp has to stay 16 byte aligned
+        let saved_size = self.callee_saved.len() as i32 * 8;
+        let frame_size = (allocation.slot_count * 8 + saved_size + 15) / 16 * 16 - saved_size;
+        let label = function_label(&function.name);
+        if function.name != ir::MAIN_NAME {
+            self.globals.push(label.clone());
+        }
+        self.emit(Inst::Label(label));
+        self.emit(Inst::Push(Reg::Rbp));
+        self.emit(Inst::Mov(Operand::Reg(Reg::Rbp), Operand::Reg(Reg::Rsp)));
+        if frame_size > 0 {
+            self.emit(Inst::Sub(Operand::Reg(Reg::Rsp), Operand::Imm(frame_size)));
+        }
+        for reg in self.callee_saved.clone() {
+            self.emit(Inst::Push(reg));
+        }
+        // the parameters are moved from where the caller put them to where they're needed
+        let param_types: Vec<Type> = function.params.iter().map(|param| function.reg_type(*param)).collect();
+        let moves = function.params.iter().zip(abi::classify(&param_types)).map(|(param, arg_location)| {
+            let src = match arg_location {
+                ArgLocation::Reg(reg) => Location::Reg(reg),
+                ArgLocation::Xmm(xmm) => Location::Xmm(xmm),
+                // above the saved rbp and the return address
+                ArgLocation::Stack(index) => Location::Stack(16 + 8 * index as i32),
+            };
+            (self.locations[param.0], Source::At(src))
+        }).collect();
+        self.parallel_move(moves);
+        // a C caller only sets the bits of the declared type (the lowest byte of a _Bool, the lowest 32 bits of a uint32_t)
+        for (param, t) in function.params.iter().zip(param_types) {
+            match self.locations[param.0] {
+                Location::Reg(reg) => self.extend_passed(reg, t),
+                Location::Stack(offset) if Self::passed_bits(t) < 64 => {
+                    let slot = Operand::Mem { base: Reg::Rbp, offset };
+                    self.emit(Inst::Mov(Operand::Reg(Reg::R11), slot));
+                    self.extend_passed(Reg::R11, t);
+                    self.emit(Inst::Mov(slot, Operand::Reg(Reg::R11)));
+                }
+                _ => (),
+            }
+        }
+
+        for (i, block_id) in order.iter().enumerate() {
+            let next = order.get(i + 1).copied();
+            let block = function.block(*block_id);
+            self.emit(Inst::Label(self.block_labels[block_id.0].clone()));
+
+            // the flags of the last comparison can be used by the branch directly
+            let mut fused = None;
+            for (j, inst) in block.insts.iter().enumerate() {
+                if let (ir::Inst::Binary { dest, op, left, right }, Terminator::Branch { cond: ir::Operand::Reg(cond), .. }) = (inst, &block.terminator) {
+                    let float = self.operand_type(left) == Type::Float;
+                    let fusable = op.is_comparison() && !(float && matches!(op, BinaryOp::Eq | BinaryOp::Ne));
+                    if j == block.insts.len() - 1 && dest == cond && fusable && reads[dest.0] == 1 && writes[dest.0] == 1 {
+                        fused = Some(self.compare(*op, left, right));
+                        continue;
+                    }
+                }
+                self.generate_inst(inst);
+            }
+            self.generate_terminator(&block.terminator, fused, next);
+        }
+    }
+
+    fn generate_inst(&mut self, inst: &ir::Inst) {
+        match inst {
+            ir::Inst::Copy { .. } if self.is_nop(inst) => (),
+            ir::Inst::Copy { dest, src } => {
+                if self.reg_types[dest.0] == Type::Float {
+                    self.move_float(*dest, src);
+                } else {
+                    let dst = self.dest_operand(*dest);
+                    self.move_to(dst, src);
+                }
+            }
+
+            ir::Inst::Binary { dest, op, left, right } => {
+                if self.operand_type(left) == Type::Float {
+                    self.generate_float_binary(*dest, *op, left, right);
+                } else {
+                    self.generate_binary(*dest, *op, left, right);
+                }
+            }
+
+            ir::Inst::Unary { dest, op, src } => match op {
+                // flipping the sign bit negates a float
+                UnaryOp::Neg if self.reg_types[dest.0] == Type::Float => {
+                    self.load_float_bits(Reg::R11, src);
+                    self.emit(Inst::MovAbs(Reg::Rax, i64::MIN));
+                    self.emit(Inst::Xor(Operand::Reg(Reg::R11), Operand::Reg(Reg::Rax)));
+                    self.store_float_bits(*dest, Reg::R11);
+                }
+                _ => {
+                    let target = self.gp_target(*dest, None);
+                    self.load(target, src);
+                    match op {
+                        UnaryOp::Neg => {
+                            self.emit(Inst::Neg(Operand::Reg(target)));
+                            self.normalize(target, self.reg_types[dest.0]);
+                        }
+                        UnaryOp::Not => self.emit(Inst::Xor(Operand::Reg(target), Operand::Imm(1))),
+                    }
+                    self.store(*dest, target);
+                }
+            },
+
+            ir::Inst::Convert { dest, src } if self.operand_type(src) == Type::Float => {
+                let target = self.gp_target(*dest, None);
+                let value = self.float_register(src, Xmm::Xmm15);
+                self.convert_float(target, value, self.reg_types[dest.0]);
+                self.store(*dest, target);
+            }
+
+            ir::Inst::Convert { dest, src } if self.reg_types[dest.0] == Type::Char => {
+                let target = self.gp_target(*dest, None);
+                self.load(target, src);
+                self.convert_to_char(target);
+                self.store(*dest, target);
+            }
+
+            ir::Inst::Convert { dest, src } if self.reg_types[dest.0] != Type::Float => {
+                // a wider type can hold the value as it is (chars and bools are ints already, they only need to be cut down
+                // to a narrower type)
+                let (from, to) = (self.operand_type(src), self.reg_types[dest.0]);
+                let target = self.gp_target(*dest, None);
+                self.load(target, src);
+                if !from.widens_to(to) {
+                    self.normalize(target, to);
+                }
+                self.store(*dest, target);
+            }
+
+            ir::Inst::Convert { dest, src } => {
+                let target = match self.locations[dest.0] {
+                    Location::Xmm(xmm) => xmm,
+                    _ => Xmm::Xmm15,
+                };
+                if self.operand_type(src) == Type::UInt {
+                    self.load(Reg::R11, src);
+                    self.convert_uint(target, Reg::R11);
+                    self.store_float(*dest, target);
+                    return;
+                }
+                let src_reg = match self.operand(src, Reg::R11) {
+                    Operand::Reg(reg) => reg,
+                    other => {
+                        self.emit(Inst::Mov(Operand::Reg(Reg::R11), other));
+                        Reg::R11
+                    }
+                };
+                self.emit(Inst::Cvtsi2sd(target, src_reg));
+                self.store_float(*dest, target);
+            }
+
+            ir::Inst::Call { dest, function, args } if function != "print" && function != "exit" => {
+                self.generate_call(*dest, function, args);
+            }
+
+            ir::Inst::Call { function, args, .. } => {
+                // the inbuilt functions are routines in the runtime, print has one for each type
+                let routine = match function.as_str() {
+                    "print" => match self.operand_type(&args[0]) {
+                        Type::UInt => runtime::PRINT_UINT,
+                        t if t.is_integer() => runtime::PRINT_INT,
+                        Type::Float => runtime::PRINT_FLOAT,
+                        Type::Bool => runtime::PRINT_BOOL,
+                        Type::Char => runtime::PRINT_CHAR,
+                        Type::Str => runtime::PRINT_STR,
+                        _ => panic!("Internal compiler error (untyped print argument)"),
+                    },
+                    _ => runtime::EXIT,
+                };
+                if self.operand_type(&args[0]) == Type::Float {
+                    self.load_float_bits(Reg::Rdi, &args[0]);
+                } else {
+                    self.load(Reg::Rdi, &args[0]);
+                }
+                self.emit(Inst::Call(routine.to_string()));
+            }
+
+            ir::Inst::Phi { .. } => panic!("Internal compiler error (phi in code generation)"),
+        }
+    }
+
+    fn generate_terminator(&mut self, terminator: &Terminator, fused: Option<Cond>, next: Option<BlockId>) {
+        match terminator {
+            Terminator::Jump(target) => self.jump(*target, next),
+            Terminator::Branch { cond, then_block, else_block } => {
+                let cond = match (fused, cond) {
+                    (Some(fused), _) => fused,
+                    (None, ir::Operand::Const(Constant::Bool(value))) => {
+                        self.jump(if *value { *then_block } else { *else_block }, next);
+                        return;
+                    }
+                    (None, cond) => {
+                        match self.operand(cond, Reg::R11) {
+                            Operand::Reg(reg) => self.emit(Inst::Test(Operand::Reg(reg), Operand::Reg(reg))),
+                            other => self.emit(Inst::Cmp(other, Operand::Imm(0))),
+                        }
+                        Cond::NotEqual
+                    }
+                };
+                let then_block = self.jump_targets[then_block.0];
+                let else_block = self.jump_targets[else_block.0];
+                if Some(then_block) == next {
+                    self.emit(Inst::Jcc(cond.inverse(), self.block_labels[else_block.0].clone()));
+                } else {
+                    self.emit(Inst::Jcc(cond, self.block_labels[then_block.0].clone()));
+                    self.jump(else_block, next);
+                }
+            }
+            Terminator::Return(value) => {
+                match value {
+                    Some(value) if self.operand_type(value) == Type::Float => self.load_float(Xmm::Xmm0, value),
+                    Some(value) => self.load(Reg::Rax, value),
+                    None => (),
+                }
+                for reg in self.callee_saved.clone().into_iter().rev() {
+                    self.emit(Inst::Pop(reg));
+                }
+                self.emit(Inst::Leave);
+                self.emit(Inst::Ret);
+            }
+            // control never gets here
+            Terminator::Unreachable => (),
+        }
+    }
+
+    // the next block comes right after this one, so it doesn't need a jump
+    fn jump(&mut self, target: BlockId, next: Option<BlockId>) {
+        let target = self.jump_targets[target.0];
+        if Some(target) != next {
+            self.emit(Inst::Jmp(self.block_labels[target.0].clone()));
+        }
+    }
+
+    fn generate_call(&mut self, dest: Option<VReg>, function: &str, args: &[ir::Operand]) {
+        let arg_types: Vec<Type> = args.iter().map(|arg| self.operand_type(arg)).collect();
+        let arg_locations = abi::classify(&arg_types);
+        let stack_count = abi::stack_arg_count(&arg_locations) as i32;
+
+        // the stack arguments are pushed from right to left, with padding before them if there is an odd number of them
+        let padding = stack_count % 2 * 8;
+        if padding != 0 {
+            self.emit(Inst::Sub(Operand::Reg(Reg::Rsp), Operand::Imm(padding)));
+        }
+        for (arg, arg_location) in args.iter().zip(&arg_locations).rev() {
+            if let ArgLocation::Stack(_) = arg_location {
+                let src = self.source(arg);
+                self.emit_move(Location::Reg(Reg::R11), &src);
+                self.emit(Inst::Push(Reg::R11));
+            }
+        }
+
+        let moves = args.iter().zip(&arg_locations).filter_map(|(arg, arg_location)| match arg_location {
+            ArgLocation::Reg(reg) => Some((Location::Reg(*reg), self.source(arg))),
+            ArgLocation::Xmm(xmm) => Some((Location::Xmm(*xmm), self.source(arg))),
+            ArgLocation::Stack(_) => None,
+        }).collect();
+        self.parallel_move(moves);
+        self.emit(Inst::Call(function_label(function)));
+        if stack_count != 0 {
+            self.emit(Inst::Add(Operand::Reg(Reg::Rsp), Operand::Imm(stack_count * 8 + padding)));
+        }
+
+        if let Some(dest) = dest {
+            if self.reg_types[dest.0] == Type::Float {
+                self.store_float(dest, Xmm::Xmm0);
+            } else {
+                self.extend_passed(Reg::Rax, self.reg_types[dest.0]);
+                self.store(dest, Reg::Rax);
+            }
+        }
+    }
+
+    // does all moves as if they happened at the same time: a register is only written once no other move needs its value,
+    // and if the moves go around in a circle, one of the values is moved out of the way first (into rax or xmm15)
+    fn parallel_move(&mut self, moves: Vec<(Location, Source)>) {
+        let mut pending: Vec<(Location, Source)> = moves.into_iter().filter(|(dst, src)| *src != Source::At(*dst)).collect();
+        while !pending.is_empty() {
+            let ready = pending.iter().position(|(dst, _)| !pending.iter().any(|(_, src)| *src == Source::At(*dst)));
+            match ready {
+                Some(i) => {
+                    let (dst, src) = pending.remove(i);
+                    self.emit_move(dst, &src);
+                }
+                None => {
+                    // every destination is still needed by another move, so the first one is freed
+                    let blocked = Source::At(pending[0].0);
+                    let scratch = match pending[0].0 {
+                        Location::Xmm(_) => Location::Xmm(Xmm::Xmm15),
+                        _ => Location::Reg(Reg::Rax),
+                    };
+                    self.emit_move(scratch, &blocked);
+                    for (_, src) in pending.iter_mut() {
+                        if *src == blocked {
+                            *src = Source::At(scratch);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn source(&self, operand: &ir::Operand) -> Source {
+        match operand {
+            ir::Operand::Reg(reg) => Source::At(self.locations[reg.0]),
+            ir::Operand::Const(constant) => Source::Const(constant.clone()),
+        }
+    }
+
+    // moves a value (floats as their bits) between any two locations, r11 is used when both are in memory
+    fn emit_move(&mut self, dst: Location, src: &Source) {
+        let stack = |offset: i32| Operand::Mem { base: Reg::Rbp, offset };
+        match (dst, src) {
+            (_, Source::At(location)) if *location == dst => (),
+            (Location::Reg(reg), Source::At(Location::Reg(src))) => self.emit(Inst::Mov(Operand::Reg(reg), Operand::Reg(*src))),
+            (Location::Reg(reg), Source::At(Location::Xmm(src))) => self.emit(Inst::MovqFromXmm(reg, *src)),
+            (Location::Reg(reg), Source::At(Location::Stack(offset))) => self.emit(Inst::Mov(Operand::Reg(reg), stack(*offset))),
+            (Location::Reg(reg), Source::Const(constant)) => {
+                let operand = match constant {
+                    Constant::Float(value) => ir::Operand::Const(Constant::Int(value.to_bits() as i64, Type::Int)),
+                    _ => ir::Operand::Const(constant.clone()),
+                };
+                self.load(reg, &operand);
+            }
+            (Location::Xmm(xmm), Source::At(Location::Xmm(src))) => self.emit(Inst::Movapd(xmm, *src)),
+            (Location::Xmm(xmm), Source::At(Location::Reg(src))) => self.emit(Inst::MovqToXmm(xmm, *src)),
+            (Location::Xmm(xmm), src) => {
+                self.emit_move(Location::Reg(Reg::R11), src);
+                self.emit(Inst::MovqToXmm(xmm, Reg::R11));
+            }
+            (Location::Stack(offset), Source::At(Location::Reg(src))) => self.emit(Inst::Mov(stack(offset), Operand::Reg(*src))),
+            (Location::Stack(offset), Source::Const(Constant::Int(value, _))) if i32::try_from(*value).is_ok() => {
+                self.emit(Inst::Mov(stack(offset), Operand::Imm(*value as i32)));
+            }
+            (Location::Stack(offset), src) => {
+                self.emit_move(Location::Reg(Reg::R11), src);
+                self.emit(Inst::Mov(stack(offset), Operand::Reg(Reg::R11)));
+            }
+            (location, _) => panic!("Internal compiler error (move to {:?})", location),
+        }
+    }
+
+    fn generate_binary(&mut self, dest: VReg, op: BinaryOp, left: &ir::Operand, right: &ir::Operand) {
+        let t = self.reg_types[dest.0];
+        match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
+                let target = self.gp_target(dest, Some((left, right)));
+                self.load(target, left);
+                let mut value = self.operand(right, Reg::R11);
+                match op {
+                    BinaryOp::Add => self.emit(Inst::Add(Operand::Reg(target), value)),
+                    BinaryOp::Sub => self.emit(Inst::Sub(Operand::Reg(target), value)),
+                    _ => {
+                        // imul has no form for a register and an immediate without a third operand
+                        if let Operand::Imm(_) = value {
+                            self.emit(Inst::Mov(Operand::Reg(Reg::R11), value));
+                            value = Operand::Reg(Reg::R11);
+                        }
+                        self.emit(Inst::Imul(target, value));
+                    }
+                }
+                self.normalize(target, t);
+                self.store(dest, target);
+            }
+            BinaryOp::Div | BinaryOp::Rem => {
+                // the upper half of the dividend is the sign of a signed one, and 0 for an unsigned one
+                self.load(Reg::Rax, left);
+                if t.is_signed() {
+                    self.emit(Inst::Cqo);
+                } else {
+                    self.emit(Inst::Xor(Operand::Reg(Reg::Rdx), Operand::Reg(Reg::Rdx)));
+                }
+                let mut divisor = self.operand(right, Reg::R11);
+                if let Operand::Imm(_) = divisor {
+                    self.emit(Inst::Mov(Operand::Reg(Reg::R11), divisor));
+                    divisor = Operand::Reg(Reg::R11);
+                }
+                if t.is_signed() {
+                    self.emit(Inst::Idiv(divisor));
+                } else {
+                    self.emit(Inst::Div(divisor));
+                }
+                // only the quotient can be out of range (the lowest value of a narrow type divided by -1)
+                if op == BinaryOp::Div {
+                    self.normalize(Reg::Rax, t);
+                    self.store(dest, Reg::Rax);
+                } else {
+                    self.store(dest, Reg::Rdx);
+                }
+            }
+            BinaryOp::Shl | BinaryOp::Shr => {
+                let shift = |operand: Operand| match op {
+                    BinaryOp::Shl => Inst::ShlCl(operand),
+                    _ if t.is_signed() => Inst::SarCl(operand),
+                    _ => Inst::ShrCl(operand),
+                };
+                let target = match right {
+                    // the count is taken modulo the width of the type, x86 only does that for 64 bits
+                    ir::Operand::Const(Constant::Int(count, _)) => {
+                        let target = self.gp_target(dest, Some((left, right)));
+                        let count = (*count as u8) & (t.bits() as u8 - 1);
+                        self.load(target, left);
+                        self.emit(match op {
+                            BinaryOp::Shl => Inst::Shl(Operand::Reg(target), count),
+                            _ if t.is_signed() => Inst::Sar(Operand::Reg(target), count),
+                            _ => Inst::Shr(Operand::Reg(target), count),
+                        });
+                        target
+                    }
+                    // a variable count has to be in cl, rcx is saved in rdx meanwhile
+                    _ => {
+                        self.load(Reg::R11, right);
+                        self.load(Reg::Rax, left);
+                        if t.bits() < 64 {
+                            self.emit(Inst::And(Operand::Reg(Reg::R11), Operand::Imm(t.bits() as i32 - 1)));
+                        }
+                        self.emit(Inst::Mov(Operand::Reg(Reg::Rdx), Operand::Reg(Reg::Rcx)));
+                        self.emit(Inst::Mov(Operand::Reg(Reg::Rcx), Operand::Reg(Reg::R11)));
+                        self.emit(shift(Operand::Reg(Reg::Rax)));
+                        self.emit(Inst::Mov(Operand::Reg(Reg::Rcx), Operand::Reg(Reg::Rdx)));
+                        Reg::Rax
+                    }
+                };
+                // shifting to the right can't leave the range of the type
+                if op == BinaryOp::Shl {
+                    self.normalize(target, t);
+                }
+                self.store(dest, target);
+            }
+            _ => {
+                let cond = self.compare(op, left, right);
+                let target = self.gp_target(dest, None);
+                self.emit(Inst::Set(cond, target));
+                self.emit(Inst::MovzxByte(target, target));
+                self.store(dest, target);
+            }
+        }
+    }
+
+    fn generate_float_binary(&mut self, dest: VReg, op: BinaryOp, left: &ir::Operand, right: &ir::Operand) {
+        match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                let target = match self.locations[dest.0] {
+                    Location::Xmm(xmm) if !self.is_in(right, Location::Xmm(xmm)) || left == right => xmm,
+                    _ => Xmm::Xmm15,
+                };
+                let value = self.float_register(right, Xmm::Xmm14);
+                self.load_float(target, left);
+                match op {
+                    BinaryOp::Add => self.emit(Inst::Addsd(target, value)),
+                    BinaryOp::Sub => self.emit(Inst::Subsd(target, value)),
+                    BinaryOp::Mul => self.emit(Inst::Mulsd(target, value)),
+                    _ => self.emit(Inst::Divsd(target, value)),
+                }
+                self.store_float(dest, target);
+            }
+            BinaryOp::Rem => {
+                // fprem has to be repeated until it signals that the remainder is complete (bit 10 of the status word)
+                let loop_label = self.new_label();
+                self.load_float_bits(Reg::R11, right);
+                self.emit(Inst::Push(Reg::R11));
+                self.load_float_bits(Reg::R11, left);
+                self.emit(Inst::Push(Reg::R11));
+                self.emit(Inst::Fld(Operand::Mem { base: Reg::Rsp, offset: 8 }));
+                self.emit(Inst::Fld(Operand::Mem { base: Reg::Rsp, offset: 0 }));
+                self.emit(Inst::Label(loop_label.clone()));
+                self.emit(Inst::Fprem);
+                self.emit(Inst::FnstswAx);
+                self.emit(Inst::Test(Operand::Reg(Reg::Rax), Operand::Imm(0x400)));
+                self.emit(Inst::Jcc(Cond::NotEqual, loop_label));
+                self.emit(Inst::FstpSt1);
+                self.emit(Inst::Fstp(Operand::Mem { base: Reg::Rsp, offset: 0 }));
+                self.emit(Inst::Pop(Reg::R11));
+                self.emit(Inst::Pop(Reg::Rax));
+                self.store_float_bits(dest, Reg::R11);
+            }
+            BinaryOp::Eq | BinaryOp::Ne => {
+                // comparisons with NaN are unordered (parity flag set), then only != is true
+                let cond = self.compare(op, left, right);
+                let target = self.gp_target(dest, None);
+                self.emit(Inst::Set(cond, target));
+                self.emit(Inst::Set(if op == BinaryOp::Eq { Cond::NotParity } else { Cond::Parity }, Reg::R11));
+                self.emit(Inst::MovzxByte(target, target));
+                self.emit(Inst::MovzxByte(Reg::R11, Reg::R11));
+                if op == BinaryOp::Eq {
+                    self.emit(Inst::And(Operand::Reg(target), Operand::Reg(Reg::R11)));
+                } else {
+                    self.emit(Inst::Or(Operand::Reg(target), Operand::Reg(Reg::R11)));
+                }
+                self.store(dest, target);
+            }
+            _ => self.generate_binary(dest, op, left, right),
+        }
+    }
+
+    // compares the operands and returns the condition under which the comparison is true
+    fn compare(&mut self, op: BinaryOp, left: &ir::Operand, right: &ir::Operand) -> Cond {
+        if self.operand_type(left) == Type::Float {
+            let left = self.float_register(left, Xmm::Xmm15);
+            let right = self.float_register(right, Xmm::Xmm14);
+            // "above" is false for unordered values, so a < b is written as b > a
+            let (first, second, cond) = match op {
+                BinaryOp::Eq => (left, right, Cond::Equal),
+                BinaryOp::Ne => (left, right, Cond::NotEqual),
+                BinaryOp::Lt => (right, left, Cond::Above),
+                BinaryOp::Le => (right, left, Cond::AboveEqual),
+                BinaryOp::Gt => (left, right, Cond::Above),
+                _ => (left, right, Cond::AboveEqual),
+            };
+            self.emit(Inst::Ucomisd(first, second));
+            return cond;
+        }
+
+        let mut first = self.operand(left, Reg::Rax);
+        if let Operand::Imm(_) = first {
+            self.emit(Inst::Mov(Operand::Reg(Reg::Rax), first));
+            first = Operand::Reg(Reg::Rax);
+        }
+        let mut second = self.operand(right, Reg::R11);
+        if let (Operand::Mem { .. }, Operand::Mem { .. }) = (first, second) {
+            self.emit(Inst::Mov(Operand::Reg(Reg::R11), second));
+            second = Operand::Reg(Reg::R11);
+        }
+        self.emit(Inst::Cmp(first, second));
+        let signed = self.operand_type(left).is_signed();
+        match op {
+            BinaryOp::Eq => Cond::Equal,
+            BinaryOp::Ne => Cond::NotEqual,
+            BinaryOp::Lt if signed => Cond::Less,
+            BinaryOp::Le if signed => Cond::LessEqual,
+            BinaryOp::Gt if signed => Cond::Greater,
+            BinaryOp::Ge if signed => Cond::GreaterEqual,
+            BinaryOp::Lt => Cond::Below,
+            BinaryOp::Le => Cond::BelowEqual,
+            BinaryOp::Gt => Cond::Above,
+            _ => Cond::AboveEqual,
+        }
+    }
+
+    // sign- or zero-extends the lowest bits of the register again, after an operation on a narrow int
+    fn normalize(&mut self, reg: Reg, t: Type) {
+        let inst = match t {
+            Type::I8 => Inst::MovsxByte(reg, reg),
+            Type::I16 => Inst::MovsxWord(reg, reg),
+            Type::I32 => Inst::Movsxd(reg, reg),
+            Type::U8 => Inst::MovzxByte(reg, reg),
+            Type::U16 => Inst::MovzxWord(reg, reg),
+            Type::U32 => Inst::MovDword(reg, reg),
+            _ => return,
+        };
+        self.emit(inst);
+    }
+
+    // how many of the lowest bits of an argument or result of this type are defined in the System V calling convention
+    fn passed_bits(t: Type) -> u32 {
+        match t {
+            Type::Bool => 8,
+            Type::Char => 32,
+            t if t.is_integer() => t.bits(),
+            _ => 64,
+        }
+    }
+
+    // extends an argument or a result to 64 bits, like every value is kept in hj
+    fn extend_passed(&mut self, reg: Reg, t: Type) {
+        match t {
+            Type::Bool => self.emit(Inst::MovzxByte(reg, reg)),
+            Type::Char => self.emit(Inst::MovDword(reg, reg)),
+            t => self.normalize(reg, t),
+        }
+    }
+
+    // cvtsi2sd only knows signed ints, a uint above the highest int is halved first (keeping the lowest bit, so the
+    // rounding stays right) and doubled again afterwards
+    fn convert_uint(&mut self, target: Xmm, src: Reg) {
+        let (big_label, end_label) = (self.new_label(), self.new_label());
+        self.emit(Inst::Test(Operand::Reg(src), Operand::Reg(src)));
+        self.emit(Inst::Jcc(Cond::Sign, big_label.clone()));
+        self.emit(Inst::Cvtsi2sd(target, src));
+        self.emit(Inst::Jmp(end_label.clone()));
+        self.emit(Inst::Label(big_label));
+        self.emit(Inst::Mov(Operand::Reg(Reg::Rax), Operand::Reg(src)));
+        self.emit(Inst::Shr(Operand::Reg(Reg::Rax), 1));
+        self.emit(Inst::And(Operand::Reg(src), Operand::Imm(1)));
+        self.emit(Inst::Or(Operand::Reg(Reg::Rax), Operand::Reg(src)));
+        self.emit(Inst::Cvtsi2sd(target, Reg::Rax));
+        self.emit(Inst::Addsd(target, target));
+        self.emit(Inst::Label(end_label));
+    }
+
+    // cvttsd2si rounds toward zero, but it only knows int, so values outside of the range of the type are caught before:
+    // they become the lowest or the highest value (NaN becomes 0, see integer::from_float)
+    // a uint from 2^63 on is converted after subtracting 2^63, which is added back by flipping the highest bit
+    fn convert_float(&mut self, target: Reg, src: Xmm, t: Type) {
+        let (min, max) = integer::range(t);
+        let (min_label, max_label, zero_label, end_label) = (self.new_label(), self.new_label(), self.new_label(), self.new_label());
+        self.load_float(Xmm::Xmm14, &ir::Operand::Const(Constant::Float(min as f64)));
+        self.emit(Inst::Ucomisd(src, Xmm::Xmm14));
+        self.emit(Inst::Jcc(Cond::Parity, zero_label.clone()));
+        self.emit(Inst::Jcc(Cond::BelowEqual, min_label.clone()));
+        // max + 1 is a power of two, so it's exactly a float (unlike max itself for 64 bits)
+        self.load_float(Xmm::Xmm14, &ir::Operand::Const(Constant::Float((max + 1) as f64)));
+        self.emit(Inst::Ucomisd(src, Xmm::Xmm14));
+        self.emit(Inst::Jcc(Cond::AboveEqual, max_label.clone()));
+        if t == Type::UInt {
+            let small_label = self.new_label();
+            let half = 2f64.powi(63);
+            self.load_float(Xmm::Xmm14, &ir::Operand::Const(Constant::Float(half)));
+            self.emit(Inst::Ucomisd(src, Xmm::Xmm14));
+            self.emit(Inst::Jcc(Cond::Below, small_label.clone()));
+            if src != Xmm::Xmm15 {
+                self.emit(Inst::Movapd(Xmm::Xmm15, src));
+            }
+            self.emit(Inst::Subsd(Xmm::Xmm15, Xmm::Xmm14));
+            self.emit(Inst::Cvttsd2si(target, Xmm::Xmm15));
+            self.emit(Inst::MovAbs(Reg::R11, i64::MIN));
+            self.emit(Inst::Xor(Operand::Reg(target), Operand::Reg(Reg::R11)));
+            self.emit(Inst::Jmp(end_label.clone()));
+            self.emit(Inst::Label(small_label));
+        }
+        self.emit(Inst::Cvttsd2si(target, src));
+        self.emit(Inst::Jmp(end_label.clone()));
+        self.emit(Inst::Label(min_label));
+        self.load(target, &ir::Operand::Const(Constant::Int(min as i64, t)));
+        self.emit(Inst::Jmp(end_label.clone()));
+        self.emit(Inst::Label(max_label));
+        self.load(target, &ir::Operand::Const(Constant::Int(max as i64, t)));
+        self.emit(Inst::Jmp(end_label.clone()));
+        self.emit(Inst::Label(zero_label));
+        self.emit(Inst::Xor(Operand::Reg(target), Operand::Reg(target)));
+        self.emit(Inst::Label(end_label));
+    }
+
+    // ints that aren't code points (negative ones, which are above 0x10FFFF as unsigned values, and the surrogates from
+    // 0xD800 to 0xDFFF) become the replacement character U+FFFD
+    fn convert_to_char(&mut self, reg: Reg) {
+        let (invalid_label, end_label) = (self.new_label(), self.new_label());
+        self.emit(Inst::Cmp(Operand::Reg(reg), Operand::Imm(0x10ffff)));
+        self.emit(Inst::Jcc(Cond::Above, invalid_label.clone()));
+        self.emit(Inst::Mov(Operand::Reg(Reg::R11), Operand::Reg(reg)));
+        self.emit(Inst::Sub(Operand::Reg(Reg::R11), Operand::Imm(0xd800)));
+        self.emit(Inst::Cmp(Operand::Reg(Reg::R11), Operand::Imm(0x800)));
+        self.emit(Inst::Jcc(Cond::AboveEqual, end_label.clone()));
+        self.emit(Inst::Label(invalid_label));
+        self.emit(Inst::Mov(Operand::Reg(reg), Operand::Imm(0xfffd)));
+        self.emit(Inst::Label(end_label));
+    }
+
+    // copies of a value into the location it's already in don't need any code
+    fn is_nop(&self, inst: &ir::Inst) -> bool {
+        match inst {
+            ir::Inst::Copy { dest, src: ir::Operand::Reg(src) } => self.locations[dest.0] == self.locations[src.0],
+            _ => false,
+        }
+    }
+
+    fn operand_type(&self, operand: &ir::Operand) -> Type {
+        match operand {
+            ir::Operand::Reg(reg) => self.reg_types[reg.0],
+            ir::Operand::Const(constant) => constant.t(),
+        }
+    }
+
+    fn is_in(&self, operand: &ir::Operand, location: Location) -> bool {
+        matches!(operand, ir::Operand::Reg(reg) if self.locations[reg.0] == location)
+    }
+
+    // the register a result that isn't a float gets calculated in: its own one if it has one,
+    // unless the right operand is in there (and still needed for the calculation)
+    fn gp_target(&self, dest: VReg, operands: Option<(&ir::Operand, &ir::Operand)>) -> Reg {
+        match self.locations[dest.0] {
+            Location::Reg(reg) => match operands {
+                Some((left, right)) if self.is_in(right, Location::Reg(reg)) && left != right => Reg::Rax,
+                _ => reg,
+            },
+            _ => Reg::Rax,
+        }
+    }
+
+    fn dest_operand(&self, dest: VReg) -> Operand {
+        match self.locations[dest.0] {
+            Location::Reg(reg) => Operand::Reg(reg),
+            Location::Stack(offset) => Operand::Mem { base: Reg::Rbp, offset },
+            location => panic!("Internal compiler error (value at {:?} in a general purpose register)", location),
+        }
+    }
+
+    // the x86 operand for a value that isn't a float
+    // constants that don't fit into an immediate (and strings) are loaded into the scratch register
+    fn operand(&mut self, operand: &ir::Operand, scratch: Reg) -> Operand {
+        match operand {
+            ir::Operand::Reg(reg) => self.dest_operand(*reg),
+            ir::Operand::Const(Constant::Int(value, _)) => match i32::try_from(*value) {
+                Ok(value) => Operand::Imm(value),
+                Err(_) => {
+                    self.emit(Inst::MovAbs(scratch, *value));
+                    Operand::Reg(scratch)
+                }
+            },
+            ir::Operand::Const(Constant::Bool(value)) => Operand::Imm(*value as i32),
+            ir::Operand::Const(Constant::Char(value)) => Operand::Imm(*value as i32),
+            ir::Operand::Const(Constant::Str(value)) => {
+                let label = self.string_label(value);
+                self.emit(Inst::LeaData(scratch, label));
+                Operand::Reg(scratch)
+            }
+            ir::Operand::Const(Constant::Float(_)) => panic!("Internal compiler error (float in a general purpose register)"),
+        }
+    }
+
+    fn load(&mut self, dst: Reg, operand: &ir::Operand) {
+        let value = self.operand(operand, dst);
+        if value != Operand::Reg(dst) {
+            self.emit(Inst::Mov(Operand::Reg(dst), value));
+        }
+    }
+
+    fn store(&mut self, dest: VReg, src: Reg) {
+        let dst = self.dest_operand(dest);
+        if dst != Operand::Reg(src) {
+            self.emit(Inst::Mov(dst, Operand::Reg(src)));
+        }
+    }
+
+    fn move_to(&mut self, dst: Operand, src: &ir::Operand) {
+        match dst {
+            Operand::Reg(reg) => self.load(reg, src),
+            _ => {
+                let mut value = self.operand(src, Reg::R11);
+                if let Operand::Mem { .. } = value {
+                    self.emit(Inst::Mov(Operand::Reg(Reg::R11), value));
+                    value = Operand::Reg(Reg::R11);
+                }
+                self.emit(Inst::Mov(dst, value));
+            }
+        }
+    }
+
+    // the bits of a float into a general purpose register
+    fn load_float_bits(&mut self, dst: Reg, operand: &ir::Operand) {
+        match operand {
+            ir::Operand::Const(Constant::Float(value)) => self.emit(Inst::MovAbs(dst, value.to_bits() as i64)),
+            ir::Operand::Reg(reg) => match self.locations[reg.0] {
+                Location::Xmm(xmm) => self.emit(Inst::MovqFromXmm(dst, xmm)),
+                Location::Stack(offset) => self.emit(Inst::Mov(Operand::Reg(dst), Operand::Mem { base: Reg::Rbp, offset })),
+                location => panic!("Internal compiler error (float at {:?})", location),
+            },
+            _ => panic!("Internal compiler error (float operand isn't a float)"),
+        }
+    }
+
+    fn store_float_bits(&mut self, dest: VReg, src: Reg) {
+        match self.locations[dest.0] {
+            Location::Xmm(xmm) => self.emit(Inst::MovqToXmm(xmm, src)),
+            Location::Stack(offset) => self.emit(Inst::Mov(Operand::Mem { base: Reg::Rbp, offset }, Operand::Reg(src))),
+            location => panic!("Internal compiler error (float at {:?})", location),
+        }
+    }
+
+    // the SSE register a float is in, floats that aren't in one are loaded into the scratch register
+    fn float_register(&mut self, operand: &ir::Operand, scratch: Xmm) -> Xmm {
+        if let ir::Operand::Reg(reg) = operand {
+            if let Location::Xmm(xmm) = self.locations[reg.0] {
+                return xmm;
+            }
+        }
+        self.load_float_bits(Reg::R11, operand);
+        self.emit(Inst::MovqToXmm(scratch, Reg::R11));
+        scratch
+    }
+
+    fn load_float(&mut self, dst: Xmm, operand: &ir::Operand) {
+        match operand {
+            ir::Operand::Reg(reg) if matches!(self.locations[reg.0], Location::Xmm(_)) => {
+                let src = self.float_register(operand, dst);
+                if src != dst {
+                    self.emit(Inst::Movapd(dst, src));
+                }
+            }
+            _ => {
+                self.load_float_bits(Reg::R11, operand);
+                self.emit(Inst::MovqToXmm(dst, Reg::R11));
+            }
+        }
+    }
+
+    fn store_float(&mut self, dest: VReg, src: Xmm) {
+        match self.locations[dest.0] {
+            Location::Xmm(xmm) if xmm == src => (),
+            Location::Xmm(xmm) => self.emit(Inst::Movapd(xmm, src)),
+            _ => {
+                self.emit(Inst::MovqFromXmm(Reg::R11, src));
+                self.store_float_bits(dest, Reg::R11);
+            }
+        }
+    }
+
+    fn move_float(&mut self, dest: VReg, src: &ir::Operand) {
+        match self.locations[dest.0] {
+            Location::Xmm(xmm) => self.load_float(xmm, src),
+            _ => {
+                self.load_float_bits(Reg::R11, src);
+                self.store_float_bits(dest, Reg::R11);
+            }
+        }
+    }
+
+    // puts the string into the data section
+    fn string_label(&mut self, value: &str) -> String {
+        let label = format!(".Lstr{}", self.data.len());
+        let mut bytes = (value.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        self.data.push((label.clone(), bytes));
+        label
+    }
+}
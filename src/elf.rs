@@ -0,0 +1,84 @@
+use crate::encoder::{Object, Section};
+
+// writes a static ELF64 executable for x86-64 linux, which needs neither an assembler nor a linker
+// the file has two segments: the headers and the code (readable and executable) and the data (read-only)
+
+const BASE_ADDRESS: u64 = 0x400000;
+const PAGE_SIZE: u64 = 0x1000;
+const ELF_HEADER_SIZE: u64 = 64;
+const PROGRAM_HEADER_SIZE: u64 = 56;
+const PROGRAM_HEADER_COUNT: u64 = 2;
+
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const PF_R: u32 = 4;
+
+// the symbol the program starts at
+pub const ENTRY_SYMBOL: &str = "_start";
+
+pub fn write_executable(object: &Object) -> Vec<u8> {
+    let text_offset = ELF_HEADER_SIZE + PROGRAM_HEADER_COUNT * PROGRAM_HEADER_SIZE;
+    let text_end = text_offset + object.text.len() as u64;
+    // every segment has to start on its own page
+    let data_offset = text_end.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+
+    let address_of = |symbol: &str| -> u64 {
+        let symbol = object.symbols.get(symbol).unwrap_or_else(|| panic!("Internal compiler error (undefined symbol {})", symbol));
+        match symbol.section {
+            Section::Text => BASE_ADDRESS + text_offset + symbol.offset as u64,
+            Section::Data => BASE_ADDRESS + data_offset + symbol.offset as u64,
+        }
+    };
+
+    // all relocations are relative to the end of the 4 byte field
+    let mut text = object.text.clone();
+    for relocation in &object.relocations {
+        let field_end = BASE_ADDRESS + text_offset + relocation.offset as u64 + 4;
+        let distance = address_of(&relocation.symbol) as i64 - field_end as i64;
+        let distance = i32::try_from(distance).expect("Internal compiler error (relocation out of range)");
+        text[relocation.offset..relocation.offset + 4].copy_from_slice(&distance.to_le_bytes());
+    }
+
+    let mut file = vec!();
+
+    // ELF header
+    file.extend_from_slice(&[0x7f, b'E', b'L', b'F']);
+    file.push(2); // 64 bit
+    file.push(1); // little endian
+    file.push(1); // ELF version
+    file.push(0); // System V ABI
+    file.extend_from_slice(&[0; 8]); // ABI version and padding
+    file.extend_from_slice(&2u16.to_le_bytes()); // executable file
+    file.extend_from_slice(&0x3eu16.to_le_bytes()); // x86-64
+    file.extend_from_slice(&1u32.to_le_bytes()); // ELF version
+    file.extend_from_slice(&address_of(ENTRY_SYMBOL).to_le_bytes());
+    file.extend_from_slice(&ELF_HEADER_SIZE.to_le_bytes()); // program headers right after this header
+    file.extend_from_slice(&0u64.to_le_bytes()); // no section headers
+    file.extend_from_slice(&0u32.to_le_bytes()); // flags
+    file.extend_from_slice(&(ELF_HEADER_SIZE as u16).to_le_bytes());
+    file.extend_from_slice(&(PROGRAM_HEADER_SIZE as u16).to_le_bytes());
+    file.extend_from_slice(&(PROGRAM_HEADER_COUNT as u16).to_le_bytes());
+    file.extend_from_slice(&64u16.to_le_bytes()); // section header size
+    file.extend_from_slice(&0u16.to_le_bytes()); // section header count
+    file.extend_from_slice(&0u16.to_le_bytes()); // section name table index
+
+    // the first segment also contains the headers, so it starts at the beginning of the file
+    write_program_header(&mut file, PF_R | PF_X, 0, text_end);
+    write_program_header(&mut file, PF_R, data_offset, object.data.len() as u64);
+
+    file.extend_from_slice(&text);
+    file.resize(data_offset as usize, 0);
+    file.extend_from_slice(&object.data);
+    file
+}
+
+fn write_program_header(file: &mut Vec<u8>, flags: u32, offset: u64, size: u64) {
+    file.extend_from_slice(&PT_LOAD.to_le_bytes());
+    file.extend_from_slice(&flags.to_le_bytes());
+    file.extend_from_slice(&offset.to_le_bytes());
+    file.extend_from_slice(&(BASE_ADDRESS + offset).to_le_bytes()); // virtual address
+    file.extend_from_slice(&(BASE_ADDRESS + offset).to_le_bytes()); // physical address
+    file.extend_from_slice(&size.to_le_bytes()); // size in the file
+    file.extend_from_slice(&size.to_le_bytes()); // size in memory
+    file.extend_from_slice(&PAGE_SIZE.to_le_bytes()); // alignment
+}
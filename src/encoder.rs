@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use crate::x86::{Inst, Operand, Program, Reg, Xmm};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Section {
+    Text,
+    Data,
+}
+
+// where a label ended up
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Symbol {
+    pub section: Section,
+    pub offset: usize,
+}
+
+// a 32 bit field in the code that has to be filled with the distance from the end of the field to a symbol
+// (all references are relative to the instruction pointer, so this is the only kind of relocation needed)
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Relocation {
+    pub offset: usize,
+    pub symbol: String,
+}
+
+// encoded machine code, before it got a place in memory
+pub struct Object {
+    pub text: Vec<u8>,
+    pub data: Vec<u8>,
+    pub symbols: HashMap<String, Symbol>,
+    pub relocations: Vec<Relocation>,
+}
+
+// the operation numbers of the arithmetic instructions that share their encoding (add, or, and, sub, xor, cmp)
+const ALU_ADD: u8 = 0;
+const ALU_OR: u8 = 1;
+const ALU_AND: u8 = 4;
+const ALU_SUB: u8 = 5;
+const ALU_XOR: u8 = 6;
+const ALU_CMP: u8 = 7;
+
+struct Encoder {
+    code: Vec<u8>,
+    relocations: Vec<Relocation>,
+}
+
+pub fn encode(program: &Program) -> Object {
+    let mut encoder = Encoder { code: vec!(), relocations: vec!() };
+    let mut symbols = HashMap::new();
+    for inst in &program.text {
+        if let Inst::Label(name) = inst {
+            symbols.insert(name.clone(), Symbol { section: Section::Text, offset: encoder.code.len() });
+        } else {
+            encoder.encode(inst);
+        }
+    }
+
+    let mut data = vec!();
+    for (label, bytes) in &program.data {
+        symbols.insert(label.clone(), Symbol { section: Section::Data, offset: data.len() });
+        data.extend_from_slice(bytes);
+    }
+
+    Object { text: encoder.code, data, symbols, relocations: encoder.relocations }
+}
+
+impl Encoder {
+    fn byte(&mut self, byte: u8) {
+        self.code.push(byte);
+    }
+
+    fn imm32(&mut self, value: i32) {
+        self.code.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // a rel32 field that gets filled in once all symbols have an address
+    fn relocation(&mut self, symbol: &str) {
+        self.relocations.push(Relocation { offset: self.code.len(), symbol: symbol.to_string() });
+        self.imm32(0);
+    }
+
+    // the REX prefix, w selects 64 bit operands, reg and rm are the registers in the ModRM byte
+    // force is needed for the byte registers spl, bpl, sil and dil
+    fn rex(&mut self, w: bool, reg: u8, rm: u8, force: bool) {
+        let rex = 0x40 | ((w as u8) << 3) | ((reg >> 3) << 2) | (rm >> 3);
+        if rex != 0x40 || force {
+            self.byte(rex);
+        }
+    }
+
+    // the ModRM byte (and SIB byte and displacement) for a register or memory operand
+    fn modrm(&mut self, reg: u8, rm: Operand) {
+        let reg = (reg & 7) << 3;
+        match rm {
+            Operand::Reg(rm_reg) => self.byte(0xc0 | reg | (rm_reg.number() & 7)),
+            Operand::Mem { base, offset } => {
+                let base_num = base.number() & 7;
+                // rbp and r13 can't be used without a displacement, rsp and r12 need a SIB byte
+                let mode = if offset == 0 && base_num != 5 {
+                    0x00
+                } else if i8::try_from(offset).is_ok() {
+                    0x40
+                } else {
+                    0x80
+                };
+                self.byte(mode | reg | base_num);
+                if base_num == 4 {
+                    self.byte(0x24);
+                }
+                match mode {
+                    0x40 => self.byte(offset as i8 as u8),
+                    0x80 => self.imm32(offset),
+                    _ => (),
+                }
+            }
+            Operand::Imm(_) => panic!("Internal compiler error (immediate used as ModRM operand)"),
+        }
+    }
+
+    fn rm_number(operand: Operand) -> u8 {
+        match operand {
+            Operand::Reg(reg) | Operand::Mem { base: reg, .. } => reg.number(),
+            Operand::Imm(_) => 0,
+        }
+    }
+
+    // an instruction with a register in the reg field and a register or memory operand in the rm field
+    fn reg_rm(&mut self, opcode: &[u8], reg: Reg, rm: Operand) {
+        self.rex(true, reg.number(), Encoder::rm_number(rm), false);
+        self.code.extend_from_slice(opcode);
+        self.modrm(reg.number(), rm);
+    }
+
+    // an instruction with an opcode extension in the reg field (written as /n in the manual)
+    fn ext_rm(&mut self, opcode: u8, ext: u8, rm: Operand) {
+        self.rex(true, 0, Encoder::rm_number(rm), false);
+        self.byte(opcode);
+        self.modrm(ext, rm);
+    }
+
+    // an SSE instruction, the mandatory prefix has to come before the REX prefix
+    fn sse(&mut self, prefix: u8, w: bool, opcode: u8, reg: u8, rm: u8) {
+        self.byte(prefix);
+        self.rex(w, reg, rm, false);
+        self.byte(0x0f);
+        self.byte(opcode);
+        self.byte(0xc0 | ((reg & 7) << 3) | (rm & 7));
+    }
+
+    fn sse_xmm(&mut self, prefix: u8, opcode: u8, reg: Xmm, rm: Xmm) {
+        self.sse(prefix, false, opcode, reg.number(), rm.number());
+    }
+
+    // an x87 instruction with a memory operand
+    fn x87_mem(&mut self, opcode: u8, ext: u8, operand: Operand) {
+        self.rex(false, 0, Encoder::rm_number(operand), false);
+        self.byte(opcode);
+        self.modrm(ext, operand);
+    }
+
+    fn alu(&mut self, op: u8, dst: Operand, src: Operand) {
+        match (dst, src) {
+            (_, Operand::Imm(value)) => {
+                match i8::try_from(value) {
+                    Ok(small) => {
+                        self.ext_rm(0x83, op, dst);
+                        self.byte(small as u8);
+                    }
+                    Err(_) => {
+                        self.ext_rm(0x81, op, dst);
+                        self.imm32(value);
+                    }
+                }
+            }
+            (_, Operand::Reg(src_reg)) => self.reg_rm(&[op * 8 + 1], src_reg, dst),
+            (Operand::Reg(dst_reg), Operand::Mem { .. }) => self.reg_rm(&[op * 8 + 3], dst_reg, src),
+            _ => panic!("Internal compiler error (invalid operands for {:?})", op),
+        }
+    }
+
+    fn encode(&mut self, inst: &Inst) {
+        match inst {
+            Inst::Label(_) => (),
+            Inst::Mov(dst, src) => {
+                match (*dst, *src) {
+                    (_, Operand::Imm(value)) => {
+                        self.ext_rm(0xc7, 0, *dst);
+                        self.imm32(value);
+                    }
+                    (_, Operand::Reg(src_reg)) => self.reg_rm(&[0x89], src_reg, *dst),
+                    (Operand::Reg(dst_reg), Operand::Mem { .. }) => self.reg_rm(&[0x8b], dst_reg, *src),
+                    _ => panic!("Internal compiler error (mov between two memory operands)"),
+                }
+            }
+            Inst::MovAbs(dst, value) => {
+                self.rex(true, 0, dst.number(), false);
+                self.byte(0xb8 + (dst.number() & 7));
+                self.code.extend_from_slice(&value.to_le_bytes());
+            }
+            Inst::MovByte(dst, src) => {
+                match *src {
+                    Operand::Imm(value) => {
+                        self.rex(false, 0, Encoder::rm_number(*dst), false);
+                        self.byte(0xc6);
+                        self.modrm(0, *dst);
+                        self.byte(value as u8);
+                    }
+                    Operand::Reg(src_reg) => {
+                        self.rex(false, src_reg.number(), Encoder::rm_number(*dst), src_reg.number() >= 4);
+                        self.byte(0x88);
+                        self.modrm(src_reg.number(), *dst);
+                    }
+                    Operand::Mem { .. } => panic!("Internal compiler error (byte mov between two memory operands)"),
+                }
+            }
+            Inst::MovzxByte(dst, src) => self.reg_rm(&[0x0f, 0xb6], *dst, Operand::Reg(*src)),
+            Inst::MovzxWord(dst, src) => self.reg_rm(&[0x0f, 0xb7], *dst, Operand::Reg(*src)),
+            Inst::MovsxByte(dst, src) => self.reg_rm(&[0x0f, 0xbe], *dst, Operand::Reg(*src)),
+            Inst::MovsxWord(dst, src) => self.reg_rm(&[0x0f, 0xbf], *dst, Operand::Reg(*src)),
+            Inst::Movsxd(dst, src) => self.reg_rm(&[0x63], *dst, Operand::Reg(*src)),
+            Inst::MovDword(dst, src) => {
+                self.rex(false, src.number(), dst.number(), false);
+                self.byte(0x89);
+                self.modrm(src.number(), Operand::Reg(*dst));
+            }
+            Inst::Lea(dst, src) => self.reg_rm(&[0x8d], *dst, *src),
+            Inst::LeaData(dst, symbol) => {
+                // rip-relative addressing: mod 00 with rm 101
+                self.rex(true, dst.number(), 0, false);
+                self.byte(0x8d);
+                self.byte(((dst.number() & 7) << 3) | 0x05);
+                self.relocation(symbol);
+            }
+            Inst::Add(dst, src) => self.alu(ALU_ADD, *dst, *src),
+            Inst::Or(dst, src) => self.alu(ALU_OR, *dst, *src),
+            Inst::And(dst, src) => self.alu(ALU_AND, *dst, *src),
+            Inst::Sub(dst, src) => self.alu(ALU_SUB, *dst, *src),
+            Inst::Xor(dst, src) => self.alu(ALU_XOR, *dst, *src),
+            Inst::Cmp(dst, src) => self.alu(ALU_CMP, *dst, *src),
+            Inst::Test(left, right) => {
+                match *right {
+                    Operand::Imm(value) => {
+                        self.ext_rm(0xf7, 0, *left);
+                        self.imm32(value);
+                    }
+                    Operand::Reg(right_reg) => self.reg_rm(&[0x85], right_reg, *left),
+                    Operand::Mem { .. } => panic!("Internal compiler error (test with a memory operand on the right)"),
+                }
+            }
+            Inst::Imul(dst, src) => self.reg_rm(&[0x0f, 0xaf], *dst, *src),
+            Inst::Neg(operand) => self.ext_rm(0xf7, 3, *operand),
+            Inst::Div(operand) => self.ext_rm(0xf7, 6, *operand),
+            Inst::Idiv(operand) => self.ext_rm(0xf7, 7, *operand),
+            Inst::Inc(operand) => self.ext_rm(0xff, 0, *operand),
+            Inst::Dec(operand) => self.ext_rm(0xff, 1, *operand),
+            Inst::Shl(operand, count) => {
+                self.ext_rm(0xc1, 4, *operand);
+                self.byte(*count);
+            }
+            Inst::Shr(operand, count) => {
+                self.ext_rm(0xc1, 5, *operand);
+                self.byte(*count);
+            }
+            Inst::Sar(operand, count) => {
+                self.ext_rm(0xc1, 7, *operand);
+                self.byte(*count);
+            }
+            Inst::ShlCl(operand) => self.ext_rm(0xd3, 4, *operand),
+            Inst::ShrCl(operand) => self.ext_rm(0xd3, 5, *operand),
+            Inst::SarCl(operand) => self.ext_rm(0xd3, 7, *operand),
+            Inst::Cqo => {
+                self.byte(0x48);
+                self.byte(0x99);
+            }
+            Inst::Set(cond, reg) => {
+                self.rex(false, 0, reg.number(), reg.number() >= 4);
+                self.byte(0x0f);
+                self.byte(0x90 + cond.number());
+                self.modrm(0, Operand::Reg(*reg));
+            }
+            Inst::Push(reg) => {
+                self.rex(false, 0, reg.number(), false);
+                self.byte(0x50 + (reg.number() & 7));
+            }
+            Inst::Pop(reg) => {
+                self.rex(false, 0, reg.number(), false);
+                self.byte(0x58 + (reg.number() & 7));
+            }
+            Inst::Jmp(label) => {
+                self.byte(0xe9);
+                self.relocation(label);
+            }
+            Inst::Jcc(cond, label) => {
+                self.byte(0x0f);
+                self.byte(0x80 + cond.number());
+                self.relocation(label);
+            }
+            Inst::Call(label) => {
+                self.byte(0xe8);
+                self.relocation(label);
+            }
+            Inst::Ret => self.byte(0xc3),
+            Inst::Leave => self.byte(0xc9),
+            Inst::Syscall => {
+                self.byte(0x0f);
+                self.byte(0x05);
+            }
+            Inst::MovqToXmm(dst, src) => self.sse(0x66, true, 0x6e, dst.number(), src.number()),
+            Inst::MovqFromXmm(dst, src) => self.sse(0x66, true, 0x7e, src.number(), dst.number()),
+            Inst::Movapd(dst, src) => self.sse_xmm(0x66, 0x28, *dst, *src),
+            Inst::Addsd(dst, src) => self.sse_xmm(0xf2, 0x58, *dst, *src),
+            Inst::Mulsd(dst, src) => self.sse_xmm(0xf2, 0x59, *dst, *src),
+            Inst::Subsd(dst, src) => self.sse_xmm(0xf2, 0x5c, *dst, *src),
+            Inst::Divsd(dst, src) => self.sse_xmm(0xf2, 0x5e, *dst, *src),
+            Inst::Ucomisd(left, right) => self.sse_xmm(0x66, 0x2e, *left, *right),
+            Inst::Cvtsi2sd(dst, src) => self.sse(0xf2, true, 0x2a, dst.number(), src.number()),
+            Inst::Cvtsd2si(dst, src) => self.sse(0xf2, true, 0x2d, dst.number(), src.number()),
+            Inst::Cvttsd2si(dst, src) => self.sse(0xf2, true, 0x2c, dst.number(), src.number()),
+            Inst::Fld(operand) => self.x87_mem(0xdd, 0, *operand),
+            Inst::Fstp(operand) => self.x87_mem(0xdd, 3, *operand),
+            Inst::FstpSt1 => {
+                self.byte(0xdd);
+                self.byte(0xd9);
+            }
+            Inst::Fprem => {
+                self.byte(0xd9);
+                self.byte(0xf8);
+            }
+            Inst::FnstswAx => {
+                self.byte(0xdf);
+                self.byte(0xe0);
+            }
+        }
+    }
+}
@@ -0,0 +1,757 @@
+use crate::integer;
+use crate::nodes::{Operator, Type};
+use crate::output::Diagnostic;
+use crate::span::Span;
+
+// every error has a stable code (e.g. E0003), so it can be looked up with "hj --explain E0003"
+// codes are never reused: new kinds of errors always get a new number
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexError {
+    UnterminatedChar { span: Span },
+    UnterminatedString { span: Span },
+    InvalidNumber { span: Span, found: String },
+    UnexpectedCharacter { span: Span, found: char },
+    InvalidEscape { span: Span, found: String },
+    // length is the number of chars after decoding the escape sequences
+    InvalidCharLiteral { span: Span, found: String, length: usize },
+}
+
+impl LexError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::UnterminatedChar { .. } => "E0001",
+            LexError::UnterminatedString { .. } => "E0002",
+            LexError::InvalidNumber { .. } => "E0003",
+            LexError::UnexpectedCharacter { .. } => "E0004",
+            LexError::InvalidEscape { .. } => "E0033",
+            LexError::InvalidCharLiteral { .. } => "E0034",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnterminatedChar { span }
+            | LexError::UnterminatedString { span }
+            | LexError::InvalidNumber { span, .. }
+            | LexError::UnexpectedCharacter { span, .. }
+            | LexError::InvalidEscape { span, .. }
+            | LexError::InvalidCharLiteral { span, .. } => *span,
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = match self {
+            LexError::UnterminatedChar { span } => {
+                Diagnostic::error("Unexpected EOF (you have to close the ' character literal!)".to_owned())
+                    .with_span(*span, "character literal starts here")
+            }
+            LexError::UnterminatedString { span } => {
+                Diagnostic::error("Unexpected EOF (you have to close the \" string literal!)".to_owned())
+                    .with_span(*span, "string literal starts here")
+            }
+            LexError::InvalidNumber { span, found } => {
+                Diagnostic::error(format!("Invalid number syntax \"{}\"!", found))
+                    .with_span(*span, "invalid number")
+                    .with_note("numbers are digits with an optional fraction and exponent (1.5e-3), or ints starting with 0x, 0b or 0o, ints can end with a type like u8, and _ can only be used between digits")
+            }
+            LexError::UnexpectedCharacter { span, found } => {
+                Diagnostic::error(format!("Unexpected character '{}'!", found))
+                    .with_span(*span, "unexpected character")
+            }
+            LexError::InvalidEscape { span, found } => {
+                Diagnostic::error(format!("Invalid escape sequence \"{}\"!", found))
+                    .with_span(*span, "invalid escape sequence")
+                    .with_note("valid escape sequences are \\n \\t \\r \\\\ \\\" \\' \\0, \\x00 to \\x7F and \\u{0} to \\u{10FFFF}")
+            }
+            LexError::InvalidCharLiteral { span, found, length } => {
+                let label = if *length == 0 { "empty character literal".to_string() } else { format!("contains {} characters", length) };
+                Diagnostic::error(format!("Invalid character literal {} (a character literal has to contain exactly one character)!", found))
+                    .with_span(*span, &label)
+                    .with_note("use a string literal (with \") for text")
+            }
+        };
+        diagnostic.with_code(self.code())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    // the span points right behind the last token (there is none if the file is empty)
+    UnexpectedEof { span: Option<Span>, context: &'static str, expected: &'static str },
+    UnexpectedToken { span: Span, found: String, context: &'static str, expected: &'static str },
+    InvalidUnaryOperator { span: Span, operator: String },
+    SpecialAssignmentInDefinition { span: Span, operator: String },
+    NumberOutOfRange { span: Span, found: String, t: Type },
+}
+
+impl ParseError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedEof { .. } => "E0005",
+            ParseError::UnexpectedToken { .. } => "E0006",
+            ParseError::InvalidUnaryOperator { .. } => "E0007",
+            ParseError::SpecialAssignmentInDefinition { .. } => "E0008",
+            ParseError::NumberOutOfRange { .. } => "E0035",
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::UnexpectedEof { span, .. } => *span,
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::InvalidUnaryOperator { span, .. }
+            | ParseError::SpecialAssignmentInDefinition { span, .. }
+            | ParseError::NumberOutOfRange { span, .. } => Some(*span),
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = match self {
+            ParseError::UnexpectedEof { span, context, expected } => {
+                let diagnostic = Diagnostic::error(format!("Unexpected EOF {} (expected {})", context, expected));
+                match span {
+                    Some(span) => diagnostic.with_span(*span, "file ends here"),
+                    None => diagnostic,
+                }
+            }
+            ParseError::UnexpectedToken { span, found, context, expected } => {
+                Diagnostic::error(format!("Unexpected token \"{}\" {} (expected {})", found, context, expected))
+                    .with_span(*span, &format!("expected {}", expected))
+            }
+            ParseError::InvalidUnaryOperator { span, operator } => {
+                Diagnostic::error(format!("The operator \"{}\" can't be used as a unary operator (expected value before it)", operator))
+                    .with_span(*span, "not a unary operator")
+            }
+            ParseError::SpecialAssignmentInDefinition { span, operator } => {
+                Diagnostic::error(format!("Can't use special assignment operator \"{}\" for a variable definition", operator))
+                    .with_span(*span, "expected `=`")
+                    .with_help("a new variable can only be assigned with \"=\"")
+            }
+            ParseError::NumberOutOfRange { span, found, t } => {
+                let range = match t {
+                    Type::Float => "floats can't be larger than about 1.8e308".to_string(),
+                    _ => {
+                        let (min, max) = integer::range(*t);
+                        format!("{} goes from {} to {}", t.to_str(), min, max)
+                    }
+                };
+                let diagnostic = Diagnostic::error(format!("The number literal {} is out of range for the type {} ({})", found, t.to_str(), range))
+                    .with_span(*span, &format!("doesn't fit into the type {}", t.to_str()));
+                if *t == Type::Int {
+                    diagnostic.with_help("with the suffix u64, the literal is a uint, which goes up to 18446744073709551615")
+                } else {
+                    diagnostic
+                }
+            }
+        };
+        diagnostic.with_code(self.code())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError {
+    MismatchedDefinitionType { span: Span, declared_span: Span, declared: Type, found: Type },
+    UninferableType { span: Span },
+    AssignmentToUndefined { span: Span, name: String },
+    UndefinedVariable { span: Span, name: String },
+    MismatchedAssignmentType { span: Span, definition_span: Span, expected: Type, found: Type },
+    UndefinedFunction { span: Span, name: String },
+    WrongArgumentCount { span: Span, function: String, expected: usize, found: usize },
+    InvalidUnaryOperand { span: Span, operator: Operator, found: Type },
+    InvalidBinaryOperands { span: Span, operator: Operator, left_span: Span, left: Type, right_span: Span, right: Type },
+    NonBoolCondition { span: Span, statement: &'static str, found: Type },
+    OutsideOfLoop { span: Span, keyword: &'static str },
+    // previous_span is None for inbuilt functions like print
+    DuplicateFunction { span: Span, name: String, previous_span: Option<Span> },
+    MismatchedArgumentType { span: Span, function: String, parameter_span: Option<Span>, expected: Type, found: Type },
+    VoidFunctionInExpression { span: Span, name: String },
+    ReturnOutsideFunction { span: Span },
+    MismatchedReturnType { span: Span, function: String, expected: Type, found: Type },
+    MissingReturnValue { span: Span, function: String, expected: Type },
+    UnexpectedReturnValue { span: Span, function: String },
+    MissingReturn { span: Span, function: String, expected: Type },
+    NestedFunctionDefinition { span: Span, name: String },
+    InvalidCast { span: Span, expression_span: Span, found: Type, target: Type },
+    // a warning, unless the numeric types are strict (see VariableTraverser::strict_numeric)
+    ImplicitFloatConversion { span: Span, operator: Operator, found: Type, float_span: Span },
+    DuplicateParameter { span: Span, function: String, name: String, previous_span: Span },
+}
+
+impl TypeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeError::MismatchedDefinitionType { .. } => "E0009",
+            TypeError::UninferableType { .. } => "E0010",
+            TypeError::AssignmentToUndefined { .. } => "E0011",
+            TypeError::UndefinedVariable { .. } => "E0012",
+            TypeError::MismatchedAssignmentType { .. } => "E0013",
+            TypeError::UndefinedFunction { .. } => "E0014",
+            TypeError::WrongArgumentCount { .. } => "E0015",
+            TypeError::InvalidUnaryOperand { .. } => "E0016",
+            TypeError::InvalidBinaryOperands { .. } => "E0017",
+            // E0018 (function calls in expressions) isn't emitted anymore
+            TypeError::NonBoolCondition { .. } => "E0019",
+            TypeError::OutsideOfLoop { .. } => "E0020",
+            TypeError::DuplicateFunction { .. } => "E0021",
+            TypeError::MismatchedArgumentType { .. } => "E0022",
+            TypeError::VoidFunctionInExpression { .. } => "E0023",
+            TypeError::ReturnOutsideFunction { .. } => "E0024",
+            TypeError::MismatchedReturnType { .. } => "E0025",
+            TypeError::MissingReturnValue { .. } => "E0026",
+            TypeError::UnexpectedReturnValue { .. } => "E0027",
+            TypeError::MissingReturn { .. } => "E0028",
+            TypeError::NestedFunctionDefinition { .. } => "E0029",
+            TypeError::InvalidCast { .. } => "E0036",
+            TypeError::ImplicitFloatConversion { .. } => "E0037",
+            TypeError::DuplicateParameter { .. } => "E0038",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            TypeError::MismatchedDefinitionType { span, .. }
+            | TypeError::UninferableType { span }
+            | TypeError::AssignmentToUndefined { span, .. }
+            | TypeError::UndefinedVariable { span, .. }
+            | TypeError::MismatchedAssignmentType { span, .. }
+            | TypeError::UndefinedFunction { span, .. }
+            | TypeError::WrongArgumentCount { span, .. }
+            | TypeError::InvalidUnaryOperand { span, .. }
+            | TypeError::InvalidBinaryOperands { span, .. }
+            | TypeError::NonBoolCondition { span, .. }
+            | TypeError::OutsideOfLoop { span, .. }
+            | TypeError::DuplicateFunction { span, .. }
+            | TypeError::MismatchedArgumentType { span, .. }
+            | TypeError::VoidFunctionInExpression { span, .. }
+            | TypeError::ReturnOutsideFunction { span }
+            | TypeError::MismatchedReturnType { span, .. }
+            | TypeError::MissingReturnValue { span, .. }
+            | TypeError::UnexpectedReturnValue { span, .. }
+            | TypeError::MissingReturn { span, .. }
+            | TypeError::NestedFunctionDefinition { span, .. }
+            | TypeError::InvalidCast { span, .. }
+            | TypeError::ImplicitFloatConversion { span, .. }
+            | TypeError::DuplicateParameter { span, .. } => *span,
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = match self {
+            TypeError::MismatchedDefinitionType { span, declared_span, declared, found } => {
+                Diagnostic::error(format!("Mismatching variable types in variable definition: \"{}\" (left) and \"{}\" (right)", declared.to_str(), found.to_str()))
+                    .with_span(*span, &format!("expression has type {}", found.to_str()))
+                    .with_label(*declared_span, &format!("variable is declared as {}", declared.to_str()))
+            }
+            TypeError::UninferableType { span } => {
+                Diagnostic::error("Undefined type for variable definition!".to_string())
+                    .with_span(*span, "type can't be inferred")
+            }
+            TypeError::AssignmentToUndefined { span, name } => {
+                Diagnostic::error(format!("Assigning to undefined variable \"{}\"", name))
+                    .with_span(*span, "not defined")
+                    .with_help("variables have to be defined with \"let\" before they can be assigned to")
+            }
+            TypeError::UndefinedVariable { span, name } => {
+                Diagnostic::error(format!("Usage of undefined variable \"{}\" in expression!", name))
+                    .with_span(*span, "not defined")
+            }
+            TypeError::MismatchedAssignmentType { span, definition_span, expected, found } => {
+                Diagnostic::error(format!("Cannot assign expression of type \"{}\" to variable of type \"{}\"", found.to_str(), expected.to_str()))
+                    .with_span(*span, &format!("expected {}, found {}", expected.to_str(), found.to_str()))
+                    .with_label(*definition_span, "variable defined here")
+            }
+            TypeError::UndefinedFunction { span, name } => {
+                Diagnostic::error(format!("Undefined function \"{}\"", name))
+                    .with_span(*span, "not defined")
+            }
+            TypeError::WrongArgumentCount { span, function, expected, found } => {
+                Diagnostic::error(format!("Invalid number of arguments for function \"{}\" (expected {}, found {})", function, expected, found))
+                    .with_span(*span, &format!("{} arguments supplied", found))
+            }
+            TypeError::InvalidUnaryOperand { span, operator, found } => {
+                let expected = match operator {
+                    Operator::Not => "bool",
+                    Operator::Minus => "either a signed int or float",
+                    _ => "either an int or float",
+                };
+                Diagnostic::error(format!("Invalid type \"{}\" for unary operation \"{}\" (must be {})", found.to_str(), operator.to_str(), expected))
+                    .with_span(*span, &format!("has type {}", found.to_str()))
+            }
+            TypeError::InvalidBinaryOperands { span, operator, left_span, left, right_span, right } => {
+                let note = if left.is_integer() && right.is_integer() && !operator.is_shift() && !operator.is_logical() {
+                    "two different int types can only be mixed if one of them can hold every value of the other one (a signed int can't hold an unsigned one of the same width, an unsigned int never holds a signed one)"
+                } else if operator.is_shift() {
+                    "shifts are only defined for ints"
+                } else if operator.is_arithmetic() {
+                    "arithmetic operations are only defined for numbers (ints and floats)"
+                } else if operator.is_comparison() {
+                    "comparisons are only defined for two numbers (int or float) or two chars"
+                } else if operator.is_equality() {
+                    "only values of the same type can be compared for equality (int and float can be mixed), strings can't be compared"
+                } else {
+                    "logical operations are only defined for bool"
+                };
+                Diagnostic::error(format!("Invalid types \"{}\" and \"{}\" for binary operation \"{}\"!", left.to_str(), right.to_str(), operator.to_str()))
+                    .with_span(*span, "invalid operation")
+                    .with_label(*left_span, &format!("has type {}", left.to_str()))
+                    .with_label(*right_span, &format!("has type {}", right.to_str()))
+                    .with_note(note)
+            }
+            TypeError::NonBoolCondition { span, statement, found } => {
+                Diagnostic::error(format!("The condition of {} has to be a bool, not \"{}\"", statement, found.to_str()))
+                    .with_span(*span, &format!("expected bool, found {}", found.to_str()))
+            }
+            TypeError::OutsideOfLoop { span, keyword } => {
+                Diagnostic::error(format!("\"{}\" can only be used inside of a loop", keyword))
+                    .with_span(*span, "not inside of a loop")
+            }
+            TypeError::DuplicateFunction { span, name, previous_span } => {
+                let diagnostic = Diagnostic::error(format!("Function \"{}\" is defined multiple times", name))
+                    .with_span(*span, "redefined here");
+                match previous_span {
+                    Some(previous_span) => diagnostic.with_label(*previous_span, "first defined here"),
+                    None => diagnostic.with_note(&format!("\"{}\" is an inbuilt function", name)),
+                }
+            }
+            TypeError::MismatchedArgumentType { span, function, parameter_span, expected, found } => {
+                let diagnostic = Diagnostic::error(format!("Invalid argument type \"{}\" for function \"{}\" (expected \"{}\")", found.to_str(), function, expected.to_str()))
+                    .with_span(*span, &format!("expected {}, found {}", expected.to_str(), found.to_str()));
+                match parameter_span {
+                    Some(parameter_span) => diagnostic.with_label(*parameter_span, "parameter declared here"),
+                    None => diagnostic,
+                }
+            }
+            TypeError::VoidFunctionInExpression { span, name } => {
+                Diagnostic::error(format!("Function \"{}\" doesn't return a value, so it can't be used in an expression", name))
+                    .with_span(*span, "doesn't return a value")
+            }
+            TypeError::ReturnOutsideFunction { span } => {
+                Diagnostic::error("\"return\" can only be used inside of a function".to_string())
+                    .with_span(*span, "not inside of a function")
+            }
+            TypeError::MismatchedReturnType { span, function, expected, found } => {
+                Diagnostic::error(format!("Function \"{}\" has to return \"{}\", but the returned expression has type \"{}\"", function, expected.to_str(), found.to_str()))
+                    .with_span(*span, &format!("expected {}, found {}", expected.to_str(), found.to_str()))
+            }
+            TypeError::MissingReturnValue { span, function, expected } => {
+                Diagnostic::error(format!("Function \"{}\" has to return a value of type \"{}\"", function, expected.to_str()))
+                    .with_span(*span, "return without a value")
+            }
+            TypeError::UnexpectedReturnValue { span, function } => {
+                Diagnostic::error(format!("Function \"{}\" doesn't have a return type, so it can't return a value", function))
+                    .with_span(*span, "return with a value")
+                    .with_help("add a return type to the function definition, e.g. \"-> int\"")
+            }
+            TypeError::MissingReturn { span, function, expected } => {
+                Diagnostic::error(format!("Function \"{}\" doesn't return a value on every path (expected \"{}\")", function, expected.to_str()))
+                    .with_span(*span, "function can end without returning")
+            }
+            TypeError::NestedFunctionDefinition { span, name } => {
+                Diagnostic::error(format!("Function \"{}\" is defined inside of another block, functions can only be defined at the top level", name))
+                    .with_span(*span, "not at the top level")
+            }
+            TypeError::InvalidCast { span, expression_span, found, target } => {
+                Diagnostic::error(format!("Cannot cast a value of type \"{}\" to \"{}\"", found.to_str(), target.to_str()))
+                    .with_span(*span, "invalid cast")
+                    .with_label(*expression_span, &format!("has type {}", found.to_str()))
+                    .with_note("numbers can be cast to any number type, chars and bools to ints, and ints to chars")
+            }
+            TypeError::ImplicitFloatConversion { span, operator, found, float_span } => {
+                let diagnostic = Diagnostic::error(format!("Implicit conversion from \"{}\" to \"float\" in binary operation \"{}\"", found.to_str(), operator.to_str()))
+                    .with_span(*span, &format!("{} is converted to float", found.to_str()))
+                    .with_label(*float_span, "has type float")
+                    .with_help("convert the value explicitly with \"as float\" (or write float literals with a decimal point, 2.0 instead of 2)");
+                // the narrower ints always fit exactly
+                if found.bits() == 64 {
+                    diagnostic.with_note(&format!("{}s above 2^53 can't be represented exactly as floats, they're rounded", found.to_str()))
+                } else {
+                    diagnostic
+                }
+            }
+            TypeError::DuplicateParameter { span, function, name, previous_span } => {
+                Diagnostic::error(format!("Parameter \"{}\" is declared multiple times in function \"{}\"", name, function))
+                    .with_span(*span, "declared again here")
+                    .with_label(*previous_span, "first declared here")
+            }
+        };
+        diagnostic.with_code(self.code())
+    }
+}
+
+// errors while running a program with "hj run"
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    DivisionByZero { span: Span },
+    DivisionOverflow { span: Span },
+    StackOverflow { span: Span, function: String },
+}
+
+impl RuntimeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::DivisionByZero { .. } => "E0031",
+            RuntimeError::DivisionOverflow { .. } => "E0032",
+            RuntimeError::StackOverflow { .. } => "E0039",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            RuntimeError::DivisionByZero { span }
+            | RuntimeError::DivisionOverflow { span }
+            | RuntimeError::StackOverflow { span, .. } => *span,
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = match self {
+            RuntimeError::DivisionByZero { span } => {
+                Diagnostic::error("Integer division by zero".to_string())
+                    .with_span(*span, "the right side is 0")
+            }
+            RuntimeError::DivisionOverflow { span } => {
+                Diagnostic::error("Integer division overflowed (the lowest int can't be divided by -1)".to_string())
+                    .with_span(*span, "the result doesn't fit into an int")
+            }
+            RuntimeError::StackOverflow { span, function } => {
+                Diagnostic::error("Too many nested function calls, the stack of the interpreter is full".to_string())
+                    .with_span(*span, &format!("no stack left for this call of \"{}\"", function))
+            }
+        };
+        diagnostic.with_code(self.code())
+    }
+}
+
+// long-form descriptions for "hj --explain"
+const EXPLANATIONS: [(&str, &str); 39] = [
+    ("E0001", "A character literal was opened with ' but the file ended before it was closed.
+
+Example:
+    let char c = 'a
+
+Close the literal with another ':
+    let char c = 'a';"),
+    ("E0002", "A string literal was opened with \" but the file ended before it was closed.
+
+Example:
+    let s = \"hello;
+
+Close the literal with another \":
+    let s = \"hello\";"),
+    ("E0003", "A number literal has an invalid format. These kinds of numbers exist:
+    123        decimal int
+    1.5        float, the dot has to be surrounded by digits
+    1.5e-3     float with an exponent (e or E, optionally followed by + or -)
+    0x1F       hexadecimal int (digits 0-9 and a-f or A-F)
+    0b1010     binary int
+    0o755      octal int
+    255u8      int with the type given by a suffix (i8 i16 i32 i64 u8 u16 u32 u64)
+Underscores can be used between digits to make long numbers readable (1_000_000).
+A number can't be directly followed by other letters, and floats can't have a suffix.
+
+Examples of invalid numbers:
+    1.2.3
+    5.
+    1e
+    0b102
+    1_
+    12abc
+    1.5u8"),
+    ("E0004", "The lexer found a character that can't start any token, for example $ or @
+outside of a string literal. Remove the character or put it into a string."),
+    ("E0005", "The file ended in the middle of a command. This usually means a semicolon
+or a closing parenthesis is missing at the end of the file.
+
+Example:
+    let x = 5
+
+Add the missing part:
+    let x = 5;"),
+    ("E0006", "The parser found a token it didn't expect at this position. The error message
+says what kind of token was expected instead.
+
+Example:
+    let = 5;
+
+A variable definition needs a name after \"let\" (and an optional type):
+    let x = 5;"),
+    ("E0007", "Only +, - and ! can be used in front of a value (as unary operators). Operators
+like * or / always need a value on both sides.
+
+Example:
+    let x = * 5;"),
+    ("E0008", "A variable definition with \"let\" has to use a plain \"=\". Special assignment
+operators like += need an existing value to work with.
+
+Example:
+    let x += 5;
+
+Define the variable first, then modify it:
+    let x = 0;
+    x += 5;"),
+    ("E0009", "The type written in a variable definition doesn't match the type of the
+expression assigned to it.
+
+Example:
+    let int x = \"hello\";
+
+Either change the type of the variable or the expression. Numbers can be converted
+to another number type with \"as\":
+    let int x = 2.5 as int;"),
+    ("E0010", "The type of a variable can't be determined, because it neither has an explicit
+type nor an expression to infer the type from."),
+    ("E0011", "A value is assigned to a variable that was never defined. Variables have to be
+defined with \"let\" before they can be assigned to.
+
+Example:
+    x = 5;
+
+Define the variable instead:
+    let x = 5;"),
+    ("E0012", "An expression uses a variable that was never defined.
+
+Example:
+    let y = x + 1;
+
+Define x before using it:
+    let x = 0;
+    let y = x + 1;"),
+    ("E0013", "The type of the assigned expression doesn't match the type of the variable.
+A variable keeps the type it was defined with.
+
+Example:
+    let x = 5;
+    x = \"hello\";"),
+    ("E0014", "A function is called that doesn't exist. Functions have to be defined with
+\"fn\" at the top level of the file (the definition can come after the call)."),
+    ("E0015", "A function is called with the wrong number of arguments.
+
+Example:
+    print(1, 2);
+
+print takes exactly one argument:
+    print(1);"),
+    ("E0016", "The unary operator + can only be used on numbers (ints or floats), - only on signed
+ints (int, i8, i16, i32) and floats, because an unsigned int can't be negative. The
+negation ! can only be used on bools.
+
+Examples:
+    let x = -\"hello\";
+    let y = !5;
+    let uint z = 5;
+    let w = -z;"),
+    ("E0017", "The operands of a binary operation have the wrong types:
+  - arithmetic (+ - * / %) needs two numbers (ints or floats)
+  - shifts (<< >>) need two ints, the result has the type of the left one
+  - comparisons (< <= > >=) need two numbers or two chars
+  - equality (== !=) needs two values of the same type (ints and floats can be mixed),
+    strings can't be compared
+  - logical operators (&& ||) need two bools
+Two different int types can only be mixed if one of them can hold every value of the
+other one, which is then converted: i8 and i32 give an i32, u8 and i16 give an i16. A
+signed and an unsigned int can't be mixed otherwise (there is no type for i8 and u8,
+or int and uint). An int literal without a suffix takes the type of the other side
+if it fits into it.
+
+Examples:
+    let x = 5 + \"hello\";
+    let i8 a = 1;
+    let u8 b = 2;
+    let c = a + b;"),
+    ("E0018", "Function calls couldn't be used inside of expressions in earlier versions.
+This error is no longer emitted."),
+    ("E0019", "The condition of an if statement or a while loop has to be of type bool.
+There is no implicit conversion from numbers or other types to bool.
+
+Example:
+    let x = 5;
+    if x {
+        print(x);
+    }"),
+    ("E0020", "break and continue jump to the end or the start of the loop they're in, so
+they can't be used outside of a loop.
+
+Example:
+    if true {
+        break;
+    }"),
+    ("E0021", "A function with the same name is defined more than once, or a definition
+uses the name of an inbuilt function like print. Function names have to be unique.
+
+Example:
+    fn foo() {}
+    fn foo() {}"),
+    ("E0022", "An argument of a function call has a different type than the parameter it
+is passed to. Arguments are only converted implicitly like in a variable definition:
+an int becomes a wider int (i8 to int, u8 to u16 or to i16, but never signed to
+unsigned), and int literals take the type of the parameter if they fit into it. Ints
+aren't converted to floats, and floats never to ints.
+
+Examples:
+    fn square(int x) -> int {
+        return x * x;
+    }
+    let y = square(1.5);
+
+    fn half(float x) -> float {
+        return x / 2.0;
+    }
+    let z = half(3);
+
+Convert the argument with \"as\", or write float literals with a decimal point:
+    let z = half(3.0);"),
+    ("E0023", "A function without a return type is used inside of an expression. It doesn't
+produce a value, so it can only be called as a command.
+
+Example:
+    fn greet() {
+        print(\"hello\");
+    }
+    let x = greet();"),
+    ("E0024", "return ends the function it's in, so it can't be used outside of a function.
+
+Example:
+    let x = 5;
+    return;"),
+    ("E0025", "The returned expression doesn't match the return type of the function.
+
+Example:
+    fn half(int x) -> int {
+        return x / 2.0;
+    }"),
+    ("E0026", "A function with a return type uses return without a value.
+
+Example:
+    fn answer() -> int {
+        return;
+    }"),
+    ("E0027", "A function without a return type returns a value. Either remove the value or
+add a return type with -> after the parameters.
+
+Example:
+    fn answer() {
+        return 42;
+    }"),
+    ("E0028", "A function with a return type can reach the end of its body without a return.
+Every path through the function has to end with a return, an if statement only counts
+if both the if and the else block return.
+
+Example:
+    fn sign(int x) -> int {
+        if x < 0 {
+            return -1;
+        }
+    }"),
+    ("E0029", "Functions can only be defined at the top level of the file, not inside of
+if statements, loops or other functions.
+
+Example:
+    if true {
+        fn foo() {}
+    }"),
+    ("E0030", "This error is no longer emitted. It was reported for valid programs that used a
+feature the code generator couldn't compile yet (user-defined functions), but the whole
+language can be compiled now."),
+    ("E0031", "An int was divided by 0 (with / or %) while the program was running. Compiled
+programs are killed by the operating system in this case (SIGFPE). Float division by 0
+isn't an error, it results in inf or NaN.
+
+Example:
+    let zero = 0;
+    print(5 / zero);"),
+    ("E0032", "The lowest int (-9223372036854775808) was divided by -1 while the program was
+running. The result (9223372036854775808) doesn't fit into an int. Compiled programs are
+killed by the operating system in this case (SIGFPE)."),
+    ("E0033", "A string or character literal contains a backslash that doesn't start a valid
+escape sequence. These escape sequences can be used:
+    \\n  newline            \\\\  backslash
+    \\t  tab                \\\"  double quote
+    \\r  carriage return    \\'  single quote
+    \\0  null character     \\xNN  ASCII character (two hex digits, up to 7F)
+    \\u{N}  any Unicode character (1 to 6 hex digits, e.g. \\u{1F600})
+
+Example:
+    print(\"C:\\Users\");
+
+Escape the backslash itself to write it:
+    print(\"C:\\\\Users\");"),
+    ("E0034", "A character literal has to contain exactly one character (an escape sequence
+counts as one). Text with more than one character is a string, written with \".
+
+Examples:
+    let c = 'ab';
+    let c = '';
+
+Valid characters:
+    let c = 'a';
+    let c = '\\n';"),
+    ("E0035", "A number literal is too large for its type. Ints without a suffix are 64 bit signed
+integers, so they go from -9223372036854775808 to 9223372036854775807 (the lowest
+value of a signed type can only be written with a minus directly in front of the
+literal). With a suffix, the literal has to fit into that type: 255u8 is the
+largest u8, and bigger values than the largest int need the suffix u64. Floats are
+64 bit floating point numbers, which can't be larger than about 1.8e308 (smaller
+numbers are rounded to the closest float, very small ones to 0).
+
+Examples:
+    let x = 9223372036854775808;
+    let mask = 0xFFFFFFFFFFFFFFFF;
+    let y = 1e400;
+    let z = 256u8;"),
+    ("E0036", "A value is cast with \"as\" to a type it can't be converted to. These casts exist:
+  - int to another int type: the value wraps around if it doesn't fit (300 as u8 is 44,
+    -1 as uint is 18446744073709551615)
+  - int to float: rounded to the closest float
+  - float to int: rounded toward zero, values outside of the range of the type become
+    its lowest or highest value, NaN becomes 0 (2.9 as int is 2, -1.5 as uint is 0)
+  - char to int: the code point of the character, wrapped like an int ('A' as u8 is 65)
+  - int to char: the character with the value as its code point, values that aren't
+    valid code points become the replacement character U+FFFD
+  - bool to int: 1 for true, 0 for false
+  - any type to itself
+Everything else is an error, e.g. strings can't be cast at all and there is no cast
+from a number to a bool (compare it with 0 instead).
+
+Examples:
+    let x = \"5\" as int;
+    let y = 1 as bool;
+    let z = 2.5 as char;"),
+    ("E0037", "An int is used together with a float in a binary operation (arithmetic, a
+comparison or ==/!=), so it's converted to a float implicitly. Ints above 2^53 can't be
+represented exactly as floats, so the conversion can silently lose precision.
+
+By default, this is only a warning and the program is compiled anyway. With
+--strict-numeric, it's an error, and the conversion has to be written with \"as\".
+
+Example:
+    let count = 3;
+    let average = 10.0 / count;
+    let doubled = average * 2;
+
+Convert the int explicitly, and write float literals with a decimal point:
+    let average = 10.0 / count as float;
+    let doubled = average * 2.0;"),
+    ("E0038", "A function has two parameters with the same name. Inside of the function, the name
+could only refer to one of them, so the parameters of a function have to have different
+names.
+
+Example:
+    fn add(int a, int a) -> int {
+        return a + a;
+    }"),
+    ("E0039", "The program called functions inside of each other too deeply while it was running
+with \"hj run\", usually because a recursion doesn't end or goes too deep, so the stack
+of the interpreter is full. Compiled programs crash with a segmentation fault when their
+stack is full.
+
+Example:
+    fn forever(int n) -> int {
+        return forever(n + 1);
+    }
+    print(forever(0));"),
+];
+
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS.iter().find(|(c, _)| c.eq_ignore_ascii_case(code)).map(|(_, explanation)| *explanation)
+}
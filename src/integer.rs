@@ -0,0 +1,120 @@
+use std::cmp::Ordering;
+
+use crate::nodes::Type;
+
+// the arithmetic of the int types, shared by the interpreter and the constant folding of the optimizer
+// every int is kept in 64 bits, like in a register of the compiled code: signed types sign-extended and unsigned ones
+// zero-extended (so a uint above i64::MAX is a negative i64 here), the results of all operations are brought back into
+// that form, which makes them wrap around on overflow
+// dividing by 0 fails for every type, dividing the lowest value by -1 only for int (the narrower types have room for the
+// result in 64 bits, so it just wraps around like any other overflow)
+
+// cuts the value down to the width of the type and extends it back to 64 bits
+pub fn wrap(value: i64, t: Type) -> i64 {
+    let unused = 64 - t.bits();
+    if t.is_signed() {
+        (value << unused) >> unused
+    } else {
+        (((value as u64) << unused) >> unused) as i64
+    }
+}
+
+// the lowest and the highest value of the type
+pub fn range(t: Type) -> (i128, i128) {
+    let bits = t.bits();
+    if t.is_signed() {
+        (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+    } else {
+        (0, (1 << bits) - 1)
+    }
+}
+
+pub fn fits(value: i128, t: Type) -> bool {
+    let (min, max) = range(t);
+    min <= value && value <= max
+}
+
+// the number the stored bits stand for
+pub fn value(bits: i64, t: Type) -> i128 {
+    if t.is_signed() { bits as i128 } else { bits as u64 as i128 }
+}
+
+pub fn add(left: i64, right: i64, t: Type) -> i64 {
+    wrap(left.wrapping_add(right), t)
+}
+
+pub fn sub(left: i64, right: i64, t: Type) -> i64 {
+    wrap(left.wrapping_sub(right), t)
+}
+
+pub fn mul(left: i64, right: i64, t: Type) -> i64 {
+    wrap(left.wrapping_mul(right), t)
+}
+
+pub fn neg(value: i64, t: Type) -> i64 {
+    wrap(value.wrapping_neg(), t)
+}
+
+// None if the division fails at runtime
+pub fn div(left: i64, right: i64, t: Type) -> Option<i64> {
+    if t.is_signed() {
+        left.checked_div(right).map(|result| wrap(result, t))
+    } else {
+        (left as u64).checked_div(right as u64).map(|result| result as i64)
+    }
+}
+
+pub fn rem(left: i64, right: i64, t: Type) -> Option<i64> {
+    if t.is_signed() {
+        left.checked_rem(right)
+    } else {
+        (left as u64).checked_rem(right as u64).map(|result| result as i64)
+    }
+}
+
+// only the lowest bits of the count are used (as many as are needed for the width of the type)
+fn shift_count(count: i64, t: Type) -> u32 {
+    (count as u32) & (t.bits() - 1)
+}
+
+pub fn shl(value: i64, count: i64, t: Type) -> i64 {
+    wrap(value << shift_count(count, t), t)
+}
+
+// signed ints keep their sign, unsigned ones are filled with zeros
+pub fn shr(value: i64, count: i64, t: Type) -> i64 {
+    if t.is_signed() {
+        value >> shift_count(count, t)
+    } else {
+        ((value as u64) >> shift_count(count, t)) as i64
+    }
+}
+
+pub fn compare(left: i64, right: i64, t: Type) -> Ordering {
+    if t.is_signed() {
+        left.cmp(&right)
+    } else {
+        (left as u64).cmp(&(right as u64))
+    }
+}
+
+pub fn to_float(value: i64, t: Type) -> f64 {
+    if t.is_signed() { value as f64 } else { value as u64 as f64 }
+}
+
+// rounds toward zero, values outside of the range of the type become its lowest or highest value and NaN becomes 0
+// (the conversion of an f64 to an i128 already does that for the range of i128)
+pub fn from_float(value: f64, t: Type) -> i64 {
+    let (min, max) = range(t);
+    (value as i128).clamp(min, max) as i64
+}
+
+// the char with the value as its code point, values that aren't code points (negative ones, surrogates, or above 0x10FFFF)
+// become the replacement character U+FFFD
+pub fn to_char(value: i64, t: Type) -> char {
+    u32::try_from(self::value(value, t)).ok().and_then(char::from_u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+pub fn to_string(value: i64, t: Type) -> String {
+    if t.is_signed() { value.to_string() } else { (value as u64).to_string() }
+}
@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::errors::RuntimeError;
+use crate::integer;
+use crate::nodes::*;
+
+// evaluates the type-checked AST directly, which is also the reference for what the compiled programs have to do
+// ints wrap around on overflow like in the compiled code
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    // every int type, in its 64 bit form (see the integer module), the type is known from the AST
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str(String),
+}
+
+impl Value {
+    // the text print writes for the value (without the line break), t is the type the value has in the program
+    pub fn to_output(&self, t: Type) -> String {
+        match self {
+            Value::Int(value) => integer::to_string(*value, t),
+            Value::Float(value) => format_float(*value),
+            Value::Bool(value) => value.to_string(),
+            Value::Char(value) => value.to_string(),
+            Value::Str(value) => value.clone(),
+        }
+    }
+}
+
+// floats are written with 6 decimal places, floats from 1e12 on as a number from 1 to 10 and an exponent (e.g. 1.500000e13)
+// this is exactly what the runtime of the compiled programs does (see runtime.rs), step by step, so the results are the same
+pub fn format_float(mut value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    let mut s = String::new();
+    if value < 0.0 {
+        s.push('-');
+        value = -value;
+    }
+    if value == f64::INFINITY {
+        return s + "inf";
+    }
+    let mut exponent = 0;
+    if value >= 1e12 {
+        while value >= 10.0 {
+            value /= 10.0;
+            exponent += 1;
+        }
+    }
+    let millionths = (value * 1e6).round_ties_even() as u64;
+    s += &format!("{}.{:06}", millionths / 1000000, millionths % 1000000);
+    if exponent > 0 {
+        s += &format!("e{}", exponent);
+    }
+    s
+}
+
+// the interpreter recurses through the AST, so "hj run" runs it on a thread with a much bigger stack than the main thread
+// has, which is enough for about as deep recursions as the compiled programs can do (the memory is only used once a
+// recursion gets that deep)
+pub const STACK_SIZE: usize = 1 << 30;
+// the stack of a thread started by Rust with the default size, like the ones the tests run on
+const DEFAULT_STACK_SIZE: usize = 2 << 20;
+// what one call may use of the stack until the next call checks how much is left
+const STACK_RESERVE: usize = 256 << 10;
+
+// the address of a variable in the frame of the caller, the stack grows towards lower addresses
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::ptr::addr_of!(marker) as usize
+}
+
+// how a command ended
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Option<Value>),
+}
+
+// something that stops the whole program
+enum Interrupt {
+    Exit(i32),
+    Error(RuntimeError),
+}
+
+pub struct Interpreter<'a> {
+    // one variable table for each scope, like in the VariableTraverser
+    scopes: Vec<HashMap<String, Value>>,
+    functions: HashMap<String, &'a FunctionDefinitionNode>,
+    output: &'a mut dyn Write,
+    // the size of the stack the interpreter runs on and where it was when the program started, a call that could
+    // overflow it is reported as E0039 instead
+    stack_size: usize,
+    stack_start: usize,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(output: &'a mut dyn Write) -> Interpreter<'a> {
+        Interpreter {
+            scopes: vec!(),
+            functions: HashMap::new(),
+            output,
+            stack_size: DEFAULT_STACK_SIZE,
+            stack_start: 0,
+        }
+    }
+
+    // for a thread with a bigger stack than the default one
+    pub fn with_stack_size(mut self, stack_size: usize) -> Interpreter<'a> {
+        self.stack_size = stack_size;
+        self
+    }
+
+    // runs the program and returns its exit code
+    pub fn run(&mut self, scope_node: &'a ScopeNode) -> Result<i32, RuntimeError> {
+        self.stack_start = stack_address();
+        for command in &scope_node.commands {
+            if let CommandNode::FunctionDefinitionNode(func_def_node) = command {
+                self.functions.insert(func_def_node.function.name.clone(), func_def_node);
+            }
+        }
+        match self.execute_scope(scope_node) {
+            Ok(_) => Ok(0),
+            Err(Interrupt::Exit(code)) => Ok(code),
+            Err(Interrupt::Error(error)) => Err(error),
+        }
+    }
+
+    fn execute_scope(&mut self, scope_node: &'a ScopeNode) -> Result<Flow, Interrupt> {
+        self.scopes.push(HashMap::new());
+        let mut flow = Ok(Flow::Normal);
+        for command in &scope_node.commands {
+            flow = self.execute_command(command);
+            if !matches!(flow, Ok(Flow::Normal)) {
+                break;
+            }
+        }
+        self.scopes.pop();
+        flow
+    }
+
+    // the type checker made sure that every used variable exists
+    fn variable(&mut self, name: &str) -> &mut Value {
+        self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)).expect("Internal interpreter error (variable not found)")
+    }
+
+    fn execute_command(&mut self, command: &'a CommandNode) -> Result<Flow, Interrupt> {
+        match command {
+            CommandNode::VariableDefinitionNode(var_def_node) => {
+                let value = match &var_def_node.expression {
+                    Some(expr) => self.evaluate(expr)?,
+                    // the type is known in this case, variables without a value start out as 0 (like in the compiled code)
+                    None => match Type::from(var_def_node.vtype.as_ref().unwrap()) {
+                        t if t.is_integer() => Value::Int(0),
+                        Type::Float => Value::Float(0.0),
+                        Type::Bool => Value::Bool(false),
+                        Type::Char => Value::Char('\0'),
+                        _ => Value::Str(String::new()),
+                    },
+                };
+                self.scopes.last_mut().unwrap().insert(var_def_node.variable.name.clone(), value);
+            }
+
+            CommandNode::VariableAssignmentNode(var_assign_node) => {
+                let value = self.evaluate(&var_assign_node.expression)?;
+                *self.variable(&var_assign_node.variable.name) = value;
+            }
+
+            CommandNode::FunctionCallNode(func_call_node) => {
+                self.call(func_call_node)?;
+            }
+
+            CommandNode::IfNode(if_node) => {
+                if self.evaluate(&if_node.condition)? == Value::Bool(true) {
+                    return self.execute_scope(&if_node.body);
+                } else if let Some(else_body) = &if_node.else_body {
+                    return self.execute_scope(else_body);
+                }
+            }
+
+            CommandNode::WhileNode(while_node) => {
+                while self.evaluate(&while_node.condition)? == Value::Bool(true) {
+                    match self.execute_scope(&while_node.body)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => (),
+                    }
+                }
+            }
+
+            CommandNode::BreakNode(_) => return Ok(Flow::Break),
+            CommandNode::ContinueNode(_) => return Ok(Flow::Continue),
+
+            // functions were collected before the program started
+            CommandNode::FunctionDefinitionNode(_) => (),
+
+            CommandNode::ReturnNode(return_node) => {
+                let value = match &return_node.expression {
+                    Some(expr) => Some(self.evaluate(expr)?),
+                    None => None,
+                };
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    // calls an inbuilt or a user-defined function and returns what it returned
+    fn call(&mut self, func_call_node: &'a FunctionCallNode) -> Result<Option<Value>, Interrupt> {
+        let mut args = vec!();
+        for arg in &func_call_node.args {
+            args.push(self.evaluate(arg)?);
+        }
+
+        match func_call_node.function.name.as_str() {
+            "print" => {
+                // a closed stdout isn't an error of the program
+                let _ = writeln!(self.output, "{}", args[0].to_output(func_call_node.args[0].t.unwrap()));
+                return Ok(None);
+            }
+            "exit" => {
+                match args[0] {
+                    Value::Int(code) => return Err(Interrupt::Exit(code as i32)),
+                    _ => panic!("Internal interpreter error (exit code isn't an int)"),
+                }
+            }
+            _ => (),
+        }
+
+        let function = self.functions[&func_call_node.function.name];
+        if self.stack_start.saturating_sub(stack_address()) + STACK_RESERVE > self.stack_size {
+            return Err(Interrupt::Error(RuntimeError::StackOverflow { span: func_call_node.span, function: function.function.name.clone() }));
+        }
+        // the function only sees its parameters
+        let mut parameters = HashMap::new();
+        for (param, value) in function.params.iter().zip(args) {
+            parameters.insert(param.variable.name.clone(), value);
+        }
+        let outer_scopes = std::mem::replace(&mut self.scopes, vec!(parameters));
+        let flow = self.execute_scope(&function.body);
+        self.scopes = outer_scopes;
+
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(None),
+        }
+    }
+
+    fn evaluate(&mut self, expression_node: &'a TExpressionNode) -> Result<Value, Interrupt> {
+        let value = match &expression_node.node {
+            ExpressionNode::VariableNode(var_node) => self.variable(&var_node.name).clone(),
+            ExpressionNode::IntLiteralNode(int_node) => Value::Int(int_node.value),
+            ExpressionNode::FloatLiteralNode(float_node) => Value::Float(float_node.value),
+            ExpressionNode::BoolLiteralNode(bool_node) => Value::Bool(bool_node.value),
+            ExpressionNode::CharLiteralNode(char_node) => Value::Char(char_node.value),
+            ExpressionNode::StringLiteralNode(string_node) => Value::Str(string_node.value.clone()),
+
+            ExpressionNode::FunctionCallNode(func_call_node) => {
+                self.call(func_call_node)?.expect("Internal interpreter error (function without return value in expression)")
+            }
+
+            ExpressionNode::UnaryOperationNode(unary_op_node) => {
+                let value = self.evaluate(&unary_op_node.expression)?;
+                match (unary_op_node.operator, value) {
+                    (Operator::Minus, Value::Int(value)) => Value::Int(integer::neg(value, expression_node.t.unwrap())),
+                    (Operator::Minus, Value::Float(value)) => Value::Float(-value),
+                    (Operator::Not, Value::Bool(value)) => Value::Bool(!value),
+                    (_, value) => value,
+                }
+            }
+
+            ExpressionNode::ConversionNode(conversion_node) => {
+                let value = self.evaluate(&conversion_node.expression)?;
+                convert(value, conversion_node.expression.t.unwrap(), expression_node.t.unwrap())
+            }
+
+            ExpressionNode::CastNode(cast_node) => {
+                let value = self.evaluate(&cast_node.expression)?;
+                convert(value, cast_node.expression.t.unwrap(), expression_node.t.unwrap())
+            }
+
+            ExpressionNode::BinaryOperationNode(binary_op_node) => {
+                let operator = binary_op_node.operator;
+                let left = self.evaluate(&binary_op_node.left_expr)?;
+                // && and || only evaluate the right side if the left one doesn't decide the result already
+                if operator == Operator::And && left == Value::Bool(false) {
+                    return Ok(left);
+                }
+                if operator == Operator::Or && left == Value::Bool(true) {
+                    return Ok(left);
+                }
+                let right = self.evaluate(&binary_op_node.right_expr)?;
+                self.binary_operation(binary_op_node, left, right)?
+            }
+        };
+        Ok(value)
+    }
+
+    fn binary_operation(&mut self, binary_op_node: &BinaryOperationNode, left: Value, right: Value) -> Result<Value, Interrupt> {
+        let operator = binary_op_node.operator;
+        let value = match (&left, &right) {
+            (Value::Int(l), Value::Int(r)) => {
+                // both sides have the same type (the type checker converted them), except for shifts, where the count
+                // only has to be an int
+                let t = binary_op_node.left_expr.t.unwrap();
+                let (l, r) = (*l, *r);
+                match operator {
+                    Operator::Plus => Value::Int(integer::add(l, r, t)),
+                    Operator::Minus => Value::Int(integer::sub(l, r, t)),
+                    Operator::Multiply => Value::Int(integer::mul(l, r, t)),
+                    Operator::ShiftLeft => Value::Int(integer::shl(l, r, t)),
+                    Operator::ShiftRight => Value::Int(integer::shr(l, r, t)),
+                    Operator::Divide | Operator::Modulo => {
+                        if r == 0 {
+                            return Err(Interrupt::Error(RuntimeError::DivisionByZero { span: binary_op_node.span }));
+                        }
+                        let result = if operator == Operator::Divide { integer::div(l, r, t) } else { integer::rem(l, r, t) };
+                        match result {
+                            Some(result) => Value::Int(result),
+                            None => return Err(Interrupt::Error(RuntimeError::DivisionOverflow { span: binary_op_node.span })),
+                        }
+                    }
+                    _ => Value::Bool(compare(operator, Some(integer::compare(l, r, t)))),
+                }
+            }
+            (Value::Float(l), Value::Float(r)) => {
+                let (l, r) = (*l, *r);
+                match operator {
+                    Operator::Plus => Value::Float(l + r),
+                    Operator::Minus => Value::Float(l - r),
+                    Operator::Multiply => Value::Float(l * r),
+                    Operator::Divide => Value::Float(l / r),
+                    Operator::Modulo => Value::Float(l % r),
+                    _ => Value::Bool(compare(operator, l.partial_cmp(&r))),
+                }
+            }
+            (Value::Char(l), Value::Char(r)) => Value::Bool(compare(operator, l.partial_cmp(r))),
+            // only equality is left for the other types (and && and ||, where the right side decides the result)
+            _ => match operator {
+                Operator::Equal => Value::Bool(left == right),
+                Operator::NotEqual => Value::Bool(left != right),
+                _ => right,
+            },
+        };
+        Ok(value)
+    }
+}
+
+// the value converted to another type, by an implicit conversion or a cast (the type checker only allows the ones that
+// exist, see VariableTraverser::cast_valid)
+fn convert(value: Value, from: Type, to: Type) -> Value {
+    match value {
+        _ if from == to => value,
+        Value::Int(value) if to == Type::Float => Value::Float(integer::to_float(value, from)),
+        Value::Int(value) if to == Type::Char => Value::Char(integer::to_char(value, from)),
+        Value::Int(value) => Value::Int(integer::wrap(value, to)),
+        Value::Float(value) => Value::Int(integer::from_float(value, to)),
+        Value::Char(value) => Value::Int(integer::wrap(value as i64, to)),
+        Value::Bool(value) => Value::Int(value as i64),
+        Value::Str(_) => panic!("Internal interpreter error (conversion of a string)"),
+    }
+}
+
+// the result of a comparison operator, None means the values are unordered (NaN), then only != is true
+fn compare(operator: Operator, ordering: Option<std::cmp::Ordering>) -> bool {
+    use std::cmp::Ordering::*;
+    match (operator, ordering) {
+        (Operator::NotEqual, None) => true,
+        (_, None) => false,
+        (Operator::Equal, Some(ordering)) => ordering == Equal,
+        (Operator::NotEqual, Some(ordering)) => ordering != Equal,
+        (Operator::Less, Some(ordering)) => ordering == Less,
+        (Operator::LessEqual, Some(ordering)) => ordering != Greater,
+        (Operator::Greater, Some(ordering)) => ordering == Greater,
+        (Operator::GreaterEqual, Some(ordering)) => ordering != Less,
+        _ => panic!("Internal interpreter error (invalid comparison operator)"),
+    }
+}
@@ -0,0 +1,491 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use crate::integer;
+use crate::nodes::Type;
+
+// a typed three-address code between the AST and the backends
+// every function is a list of basic blocks, each block is a list of instructions that always end in a terminator,
+// which is the only place where control flow can leave the block (so the edges between the blocks are explicit)
+// values live in an unlimited amount of virtual registers, each register has one type
+// a variable of the source gets one register that every assignment writes to, until the optimizer turns the IR into SSA form
+// (see ssa.rs), where every register is only written once and phi instructions choose a value depending on the previous block
+
+// a virtual register, written as %n
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct VReg(pub usize);
+
+// a basic block in a function, written as bbn (bb0 is the entry block)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct BlockId(pub usize);
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Constant {
+    // the value in the 64 bit form of the int type (see the integer module)
+    Int(i64, Type),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str(String),
+}
+
+impl Constant {
+    pub fn t(&self) -> Type {
+        match self {
+            Constant::Int(_, t) => *t,
+            Constant::Float(_) => Type::Float,
+            Constant::Bool(_) => Type::Bool,
+            Constant::Char(_) => Type::Char,
+            Constant::Str(_) => Type::Str,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        match self {
+            Constant::Int(value, Type::Int) => value.to_string(),
+            // the other int types are written with their suffix, like in the source code
+            Constant::Int(value, t) => format!("{}{}", integer::to_string(*value, *t), t.suffix()),
+            // debug formatting always has a decimal point or an exponent, so floats can't be mistaken for ints
+            Constant::Float(value) => format!("{:?}", value),
+            Constant::Bool(value) => value.to_string(),
+            Constant::Char(value) => format!("{:?}", value),
+            Constant::Str(value) => format!("{:?}", value),
+        }
+    }
+}
+
+// what an instruction reads, either a register or a constant
+#[derive(Clone, PartialEq, Debug)]
+pub enum Operand {
+    Reg(VReg),
+    Const(Constant),
+}
+
+impl Operand {
+    pub fn to_text(&self) -> String {
+        match self {
+            Operand::Reg(reg) => format!("%{}", reg.0),
+            Operand::Const(constant) => constant.to_text(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Shl,
+    // arithmetic for signed ints, logical for unsigned ones
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinaryOp {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Div => "div",
+            BinaryOp::Rem => "rem",
+            BinaryOp::Shl => "shl",
+            BinaryOp::Shr => "shr",
+            BinaryOp::Eq => "eq",
+            BinaryOp::Ne => "ne",
+            BinaryOp::Lt => "lt",
+            BinaryOp::Le => "le",
+            BinaryOp::Gt => "gt",
+            BinaryOp::Ge => "ge",
+        }
+    }
+
+    pub fn is_comparison(self) -> bool {
+        matches!(self, BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+impl UnaryOp {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "neg",
+            UnaryOp::Not => "not",
+        }
+    }
+}
+
+// both operands of a binary operation have the same type (ints get converted first if they're used with a float)
+#[derive(Clone, PartialEq, Debug)]
+pub enum Inst {
+    Copy { dest: VReg, src: Operand },
+    Binary { dest: VReg, op: BinaryOp, left: Operand, right: Operand },
+    Unary { dest: VReg, op: UnaryOp, src: Operand },
+    // converts the value to the type of the destination register: an int to another int type, to a float or to a char, a
+    // float, char or bool to an int (see VariableTraverser::cast_valid), never to the type the value already has
+    Convert { dest: VReg, src: Operand },
+    // calls an inbuilt or a user-defined function, dest is None if there is no return value or it isn't used
+    Call { dest: Option<VReg>, function: String, args: Vec<Operand> },
+    // only in SSA form, at the start of a block: the value of the operand for the block that control came from
+    Phi { dest: VReg, incoming: Vec<(BlockId, Operand)> },
+}
+
+impl Inst {
+    // the register the instruction writes to
+    pub fn dest(&self) -> Option<VReg> {
+        match self {
+            Inst::Copy { dest, .. } | Inst::Binary { dest, .. } | Inst::Unary { dest, .. } | Inst::Convert { dest, .. } | Inst::Phi { dest, .. } => Some(*dest),
+            Inst::Call { dest, .. } => *dest,
+        }
+    }
+
+    pub fn dest_mut(&mut self) -> Option<&mut VReg> {
+        match self {
+            Inst::Copy { dest, .. } | Inst::Binary { dest, .. } | Inst::Unary { dest, .. } | Inst::Convert { dest, .. } | Inst::Phi { dest, .. } => Some(dest),
+            Inst::Call { dest, .. } => dest.as_mut(),
+        }
+    }
+
+    pub fn is_phi(&self) -> bool {
+        matches!(self, Inst::Phi { .. })
+    }
+
+    // the operands the instruction reads
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Inst::Copy { src, .. } | Inst::Unary { src, .. } | Inst::Convert { src, .. } => vec!(src),
+            Inst::Binary { left, right, .. } => vec!(left, right),
+            Inst::Call { args, .. } => args.iter().collect(),
+            Inst::Phi { incoming, .. } => incoming.iter().map(|(_, operand)| operand).collect(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Inst::Copy { src, .. } | Inst::Unary { src, .. } | Inst::Convert { src, .. } => vec!(src),
+            Inst::Binary { left, right, .. } => vec!(left, right),
+            Inst::Call { args, .. } => args.iter_mut().collect(),
+            Inst::Phi { incoming, .. } => incoming.iter_mut().map(|(_, operand)| operand).collect(),
+        }
+    }
+
+    pub fn to_text(&self, function: &Function) -> String {
+        let body = match self {
+            Inst::Copy { src, .. } => format!("copy {}", src.to_text()),
+            Inst::Binary { op, left, right, .. } => format!("{} {}, {}", op.to_str(), left.to_text(), right.to_text()),
+            Inst::Unary { op, src, .. } => format!("{} {}", op.to_str(), src.to_text()),
+            Inst::Convert { src, .. } => format!("convert {}", src.to_text()),
+            Inst::Call { function: name, args, .. } => {
+                let arg_strs: Vec<String> = args.iter().map(|arg| arg.to_text()).collect();
+                format!("call {}({})", name, arg_strs.join(", "))
+            }
+            Inst::Phi { incoming, .. } => {
+                let incoming_strs: Vec<String> = incoming.iter().map(|(block, operand)| format!("[bb{}: {}]", block.0, operand.to_text())).collect();
+                format!("phi {}", incoming_strs.join(", "))
+            }
+        };
+        match self.dest() {
+            Some(dest) => format!("%{}: {} = {}", dest.0, function.reg_type(dest).to_str(), body),
+            None => body,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch { cond: Operand, then_block: BlockId, else_block: BlockId },
+    // returning from the program itself exits it with status 0
+    Return(Option<Operand>),
+    // the end of a block that can't be reached (e.g. the end of a function that always returns before it)
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec!(*target),
+            Terminator::Branch { then_block, else_block, .. } => vec!(*then_block, *else_block),
+            Terminator::Return(_) | Terminator::Unreachable => vec!(),
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Jump(target) => vec!(target),
+            Terminator::Branch { then_block, else_block, .. } => vec!(then_block, else_block),
+            Terminator::Return(_) | Terminator::Unreachable => vec!(),
+        }
+    }
+
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Terminator::Branch { cond, .. } => vec!(cond),
+            Terminator::Return(Some(value)) => vec!(value),
+            _ => vec!(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Terminator::Branch { cond, .. } => vec!(cond),
+            Terminator::Return(Some(value)) => vec!(value),
+            _ => vec!(),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        match self {
+            Terminator::Jump(target) => format!("jump bb{}", target.0),
+            Terminator::Branch { cond, then_block, else_block } => format!("branch {}, bb{}, bb{}", cond.to_text(), then_block.0, else_block.0),
+            Terminator::Return(Some(value)) => format!("return {}", value.to_text()),
+            Terminator::Return(None) => "return".to_string(),
+            Terminator::Unreachable => "unreachable".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Block {
+    pub insts: Vec<Inst>,
+    pub terminator: Terminator,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Function {
+    pub name: String,
+    // the registers the arguments are in when the function starts
+    pub params: Vec<VReg>,
+    // None if the function doesn't return a value
+    pub return_type: Option<Type>,
+    // blocks[n] is bbn
+    pub blocks: Vec<Block>,
+    // the type of every register, reg_types[n] is the type of %n
+    pub reg_types: Vec<Type>,
+}
+
+impl Function {
+    pub fn new(name: &str, return_type: Option<Type>) -> Function {
+        Function {
+            name: name.to_string(),
+            params: vec!(),
+            return_type,
+            blocks: vec!(),
+            reg_types: vec!(),
+        }
+    }
+
+    pub fn new_reg(&mut self, t: Type) -> VReg {
+        self.reg_types.push(t);
+        VReg(self.reg_types.len() - 1)
+    }
+
+    pub fn reg_type(&self, reg: VReg) -> Type {
+        self.reg_types[reg.0]
+    }
+
+    pub fn operand_type(&self, operand: &Operand) -> Type {
+        match operand {
+            Operand::Reg(reg) => self.reg_type(*reg),
+            Operand::Const(constant) => constant.t(),
+        }
+    }
+
+    pub fn new_block(&mut self) -> BlockId {
+        self.blocks.push(Block { insts: vec!(), terminator: Terminator::Unreachable });
+        BlockId(self.blocks.len() - 1)
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.0]
+    }
+
+    pub fn block_mut(&mut self, id: BlockId) -> &mut Block {
+        &mut self.blocks[id.0]
+    }
+
+    // the blocks that can jump to each block, predecessors()[n] are the ones of bbn
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec!(vec!(); self.blocks.len());
+        for (i, block) in self.blocks.iter().enumerate() {
+            for successor in block.terminator.successors() {
+                // a branch with the same block twice is still only one edge
+                if !predecessors[successor.0].contains(&BlockId(i)) {
+                    predecessors[successor.0].push(BlockId(i));
+                }
+            }
+        }
+        predecessors
+    }
+
+    // the blocks in reverse postorder (every block comes before its successors, except for loops), only reachable blocks are included
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited = vec!(false; self.blocks.len());
+        let mut order = vec!();
+        // (block, whether its successors have been pushed already)
+        let mut stack = vec!((BlockId(0), false));
+        while let Some((block, done)) = stack.pop() {
+            if done {
+                order.push(block);
+                continue;
+            }
+            if visited[block.0] {
+                continue;
+            }
+            visited[block.0] = true;
+            stack.push((block, true));
+            for successor in self.block(block).terminator.successors().into_iter().rev() {
+                if !visited[successor.0] {
+                    stack.push((successor, false));
+                }
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    // removes the blocks that can't be reached from the entry block, the remaining blocks keep their order but get renumbered
+    // phis forget about the removed blocks
+    pub fn remove_unreachable_blocks(&mut self) {
+        let mut reachable = vec!(false; self.blocks.len());
+        for block in self.reverse_postorder() {
+            reachable[block.0] = true;
+        }
+        let mut new_ids = vec!(None; self.blocks.len());
+        let mut count = 0;
+        for (i, is_reachable) in reachable.iter().enumerate() {
+            if *is_reachable {
+                new_ids[i] = Some(BlockId(count));
+                count += 1;
+            }
+        }
+
+        let blocks = std::mem::take(&mut self.blocks);
+        for (i, mut block) in blocks.into_iter().enumerate() {
+            if !reachable[i] {
+                continue;
+            }
+            for successor in block.terminator.successors_mut() {
+                *successor = new_ids[successor.0].unwrap();
+            }
+            for inst in &mut block.insts {
+                if let Inst::Phi { incoming, .. } = inst {
+                    incoming.retain(|(pred, _)| reachable[pred.0]);
+                    for (pred, _) in incoming.iter_mut() {
+                        *pred = new_ids[pred.0].unwrap();
+                    }
+                }
+            }
+            self.blocks.push(block);
+        }
+    }
+
+    // replaces every read of a register in the map with the operand it maps to
+    pub fn replace_uses(&mut self, replacements: &HashMap<VReg, Operand>) {
+        if replacements.is_empty() {
+            return;
+        }
+        for block in &mut self.blocks {
+            let operands = block.insts.iter_mut().flat_map(|inst| inst.operands_mut()).chain(block.terminator.operands_mut());
+            for operand in operands {
+                if let Operand::Reg(reg) = operand {
+                    if let Some(replacement) = replacements.get(reg) {
+                        *operand = replacement.clone();
+                    }
+                }
+            }
+        }
+    }
+
+    // renumbers the registers in the order they're written (parameters first), registers that aren't used anymore disappear
+    pub fn compact_registers(&mut self) {
+        let mut new_regs: HashMap<VReg, VReg> = HashMap::new();
+        let mut reg_types = vec!();
+        let dests = self.blocks.iter().flat_map(|block| block.insts.iter().filter_map(|inst| inst.dest()));
+        for reg in self.params.iter().copied().chain(dests) {
+            if let Entry::Vacant(entry) = new_regs.entry(reg) {
+                entry.insert(VReg(reg_types.len()));
+                reg_types.push(self.reg_type(reg));
+            }
+        }
+        let replacements = new_regs.iter().map(|(old, new)| (*old, Operand::Reg(*new))).collect();
+        self.replace_uses(&replacements);
+        for block in &mut self.blocks {
+            for inst in &mut block.insts {
+                if let Some(dest) = inst.dest_mut() {
+                    *dest = new_regs[dest];
+                }
+            }
+        }
+        for param in &mut self.params {
+            *param = new_regs[param];
+        }
+        self.reg_types = reg_types;
+    }
+
+    pub fn to_text(&self) -> String {
+        let param_strs: Vec<String> = self.params.iter().map(|param| format!("%{}: {}", param.0, self.reg_type(*param).to_str())).collect();
+        let mut s = format!("fn {}({})", self.name, param_strs.join(", "));
+        if let Some(return_type) = self.return_type {
+            s += &format!(" -> {}", return_type.to_str());
+        }
+        s += " {\n";
+        let predecessors = self.predecessors();
+        for (i, block) in self.blocks.iter().enumerate() {
+            s += &format!("bb{}:", i);
+            if !predecessors[i].is_empty() {
+                let pred_strs: Vec<String> = predecessors[i].iter().map(|pred| format!("bb{}", pred.0)).collect();
+                s += &format!(" ; preds: {}", pred_strs.join(", "));
+            }
+            s += "\n";
+            for inst in &block.insts {
+                s += &format!("    {}\n", inst.to_text(self));
+            }
+            s += &format!("    {}\n", block.terminator.to_text());
+        }
+        s += "}\n";
+        s
+    }
+}
+
+// the program itself is a function as well, it's called MAIN_NAME
+#[derive(Clone, PartialEq, Debug)]
+pub struct Module {
+    pub main: Function,
+    pub functions: Vec<Function>,
+}
+
+// user-defined functions can't have this name because it's not a valid identifier
+pub const MAIN_NAME: &str = "<main>";
+
+impl Module {
+    // the program first, then the user-defined functions
+    pub fn all_functions(&self) -> impl Iterator<Item = &Function> {
+        std::iter::once(&self.main).chain(self.functions.iter())
+    }
+
+    pub fn all_functions_mut(&mut self) -> impl Iterator<Item = &mut Function> {
+        std::iter::once(&mut self.main).chain(self.functions.iter_mut())
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn to_text(&self) -> String {
+        let function_strs: Vec<String> = self.all_functions().map(|function| function.to_text()).collect();
+        function_strs.join("\n")
+    }
+}
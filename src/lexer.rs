@@ -1,7 +1,10 @@
 use std::str::Chars;
 
+use crate::errors::LexError;
+use crate::span::Span;
+
 pub enum TokenType {
-    Operator, // +-*/ and so on
+    Operator, // +-*/ == < && ! and so on
     AssignmentOperator, // = += -= *= /= ++ -- and so on
     OpenParen, // (
     CloseParen, // )
@@ -10,14 +13,16 @@ pub enum TokenType {
     OpenBrace, // {
     CloseBrace, // }
     Comma,
-    InbuiltType, // int, uint, float, ufloat, bool, char, str
+    Arrow, // -> (before the return type of a function)
+    InbuiltType, // int, uint, i8 to u64, float, bool, char, str
     Name, // custom names, e.g. for variables
-    NumberLiteral, // all 4 number types (int/uint/float/ufloat)
+    NumberLiteral, // ints (with an optional type suffix, e.g. 10u8) and floats
     StringLiteral, // any text ("foo")
     CharLiteral, // any character ('r')
     BoolLiteral, // true/false
     Semicolon,
-    Keyword // let, if, else, while, ...
+    Keyword, // let, if, else, while, ...
+    Invalid, // characters that caused a lex error, the parser skips the statement without reporting it again
 }
 
 impl TokenType {
@@ -32,6 +37,7 @@ impl TokenType {
             TokenType::OpenBrace => "Opening brace",
             TokenType::CloseBrace => "Closing brace",
             TokenType::Comma => "Comma",
+            TokenType::Arrow => "Arrow",
             TokenType::InbuiltType => "Inbuilt type",
             TokenType::Name => "Custom name",
             TokenType::NumberLiteral => "Number literal",
@@ -39,38 +45,50 @@ impl TokenType {
             TokenType::CharLiteral => "Char literal",
             TokenType::BoolLiteral => "Bool literal",
             TokenType::Semicolon => "Semicolon",
-            TokenType::Keyword => "Keyword"
+            TokenType::Keyword => "Keyword",
+            TokenType::Invalid => "Invalid",
         }
     }
 }
 
-const INBUILT_TYPES: [&str; 5] = ["int", "float", "bool", "char", "str"];
-const KEYWORDS: [&str; 4] = ["let", "if", "else", "while"];
+const INBUILT_TYPES: [&str; 14] = ["int", "uint", "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "float", "bool", "char", "str"];
+// the types an int literal can end with (10u8), see valid_number
+pub const INT_SUFFIXES: [&str; 8] = ["i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64"];
+const KEYWORDS: [&str; 9] = ["let", "if", "else", "while", "break", "continue", "fn", "return", "as"];
 const BOOL_LITERALS: [&str; 2] = ["true", "false"];
 
 pub struct Token {
     pub kind: TokenType,
     pub value: String,
+    pub span: Span,
 }
 
 impl Token {
     fn new(kind: TokenType, contents: String) -> Token {
-        Token { kind, value: contents }
+        // the span gets filled in by create_tokens once the token has been recognized
+        Token { kind, value: contents, span: Span::default() }
     }
 
     pub fn debug_str(&self) -> String {
-        format!("{} (\"{}\")", self.kind.debug_str(), self.value)
+        format!("{} (\"{}\") at {}", self.kind.debug_str(), self.value, self.span.debug_str())
     }
 }
 
 struct Tokenizer<'a> {
     chars: Chars<'a>,
+    // position of the next character
+    offset: usize,
+    line: usize,
+    column: usize,
 }
 
 impl<'a> Tokenizer<'a> {
-    fn new(source: &'a String) -> Tokenizer<'a> {
+    fn new(source: &'a str) -> Tokenizer<'a> {
         Tokenizer {
             chars: source.chars(),
+            offset: 0,
+            line: 1,
+            column: 1,
         }
     }
 
@@ -98,35 +116,31 @@ impl<'a> Tokenizer<'a> {
 
     fn peek_name(&self) -> String {
         let mut string = String::new();
-        let mut cloned = self.chars.clone();
-        loop {
-            match cloned.next() {
-                Some(c) => {
-                    if c.is_ascii_alphanumeric() || c == '_' {
-                        string.push(c);
-                    } else {
-                        break;
-                    }
-                }
-                None => { break; }
+        let cloned = self.chars.clone();
+        for c in cloned {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                string.push(c);
+            } else {
+                break;
             }
         }
         string
     }
 
+    // everything that belongs to the number literal, it's checked with valid_number afterwards
+    // (letters are included so 0x1F and 12abc are read as one literal, and a sign after the e of an exponent as well)
     fn peek_number(&self) -> String {
         let mut string = String::new();
-        let mut cloned = self.chars.clone();
-        loop {
-            match cloned.next() {
-                Some(c) => {
-                    if c.is_ascii_digit() || c == '.' {
-                        string.push(c);
-                    } else {
-                        break;
-                    }
-                }
-                None => { break; }
+        let mut cloned = self.chars.clone().peekable();
+        while let Some(c) = cloned.next() {
+            string.push(c);
+            let is_exponent = (c == 'e' || c == 'E') && !string.starts_with("0x");
+            if is_exponent && matches!(cloned.peek(), Some('+' | '-')) {
+                string.push(cloned.next().unwrap());
+            }
+            match cloned.peek() {
+                Some(c) if c.is_ascii_alphanumeric() || *c == '_' || *c == '.' => {}
+                _ => break,
             }
         }
         string
@@ -159,22 +173,79 @@ impl<'a> Tokenizer<'a> {
         Some(string)
     }
 
+    // the contents of the string or char literal that starts with the next character (without the quotes), None if it isn't closed
+    // a backslash escapes the next character, so an escaped quote doesn't end the literal
+    fn peek_literal(&self) -> Option<String> {
+        let mut cloned = self.chars.clone();
+        let quote = cloned.next()?;
+        let mut string = String::new();
+        loop {
+            let c = cloned.next()?;
+            if c == quote {
+                return Some(string);
+            }
+            string.push(c);
+            if c == '\\' {
+                string.push(cloned.next()?);
+            }
+        }
+    }
+
+    // the whole literal (with the quotes) as a token, if its escape sequences are valid
+    fn literal_token(&self, kind: TokenType, contents: String) -> Result<Token, LexError> {
+        let quote = self.peek(1).unwrap();
+        let value = unescape(&contents).map_err(|(start, found)| {
+            LexError::InvalidEscape { span: self.span_from(start + 1, found.chars().count()), found }
+        })?;
+        if matches!(kind, TokenType::CharLiteral) && value.chars().count() != 1 {
+            let length = contents.chars().count() + 2;
+            return Err(LexError::InvalidCharLiteral { span: self.span(length), found: self.peek_string(length).unwrap(), length: value.chars().count() });
+        }
+        Ok(Token::new(kind, format!("{}{}{}", quote, contents, quote)))
+    }
+
     fn advance(&mut self, n: usize) -> Result<(), ()> {
         for _ in 0..n {
-            match self.chars.next() {
-                None => return Err(()),
-                _ => ()
-            };
+            let c = self.chars.next().ok_or(())?;
+            self.offset += c.len_utf8();
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
         }
         Ok(())
     }
 
-    fn next_token(&mut self) -> Result<Option<Token>, String> {
+    // span of the next n characters
+    fn span(&self, n: usize) -> Span {
+        self.span_from(0, n)
+    }
+
+    // span of n characters, starting after the next skip characters
+    fn span_from(&self, skip: usize, n: usize) -> Span {
+        let (mut offset, mut line, mut column) = (self.offset, self.line, self.column);
+        let mut cloned = self.chars.clone();
+        for c in cloned.by_ref().take(skip) {
+            offset += c.len_utf8();
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        let len: usize = cloned.take(n).map(|c| c.len_utf8()).sum();
+        Span::new(offset, offset + len, line, column)
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, LexError> {
         let first_char = self.peek(1).unwrap();
 
         let token = match first_char {
-            ' ' | '\n' | '\t' =>  {
-                self.chars.next();
+            ' ' | '\n' | '\t' | '\r' =>  {
+                self.advance(1).unwrap();
                 return Ok(None)
             }
 
@@ -187,48 +258,77 @@ impl<'a> Tokenizer<'a> {
             '}' => Token::new(TokenType::CloseBrace, first_char.to_string()),
             ',' => Token::new(TokenType::Comma, first_char.to_string()),
             ';' => Token::new(TokenType::Semicolon, first_char.to_string()),
-            '=' => Token::new(TokenType::AssignmentOperator, first_char.to_string()),
+            '=' => {
+                if self.peek(2).unwrap_or(' ') == '=' {
+                    Token::new(TokenType::Operator, String::from("=="))
+                } else {
+                    Token::new(TokenType::AssignmentOperator, first_char.to_string())
+                }
+            }
+
+            // comparisons and negation, they can be followed by an equal sign (<= >= !=)
+            // doubled they're shifts (<< >>), which can be used for assignments as well (<<= >>=)
+            '<' | '>' | '!' => {
+                if first_char != '!' && self.peek(2).unwrap_or(' ') == first_char {
+                    if self.peek(3).unwrap_or(' ') == '=' {
+                        Token::new(TokenType::AssignmentOperator, self.peek_string(3).unwrap())
+                    } else {
+                        Token::new(TokenType::Operator, self.peek_string(2).unwrap())
+                    }
+                } else if self.peek(2).unwrap_or(' ') == '=' {
+                    Token::new(TokenType::Operator, self.peek_string(2).unwrap())
+                } else {
+                    Token::new(TokenType::Operator, first_char.to_string())
+                }
+            }
+
+            // logical operators only exist doubled (&& ||)
+            '&' | '|' => {
+                if self.peek(2).unwrap_or(' ') == first_char {
+                    Token::new(TokenType::Operator, self.peek_string(2).unwrap())
+                } else {
+                    return Err(LexError::UnexpectedCharacter { span: self.span(1), found: first_char });
+                }
+            }
 
             '+' | '-' | '*' | '/' | '%' => {
                 if first_char == '/' && self.peek(2).unwrap_or(' ') == '/' {
                     let comment = self.peek_until('\n', 2, true).unwrap();
-                    self.advance(comment.len()+2).unwrap();
+                    self.advance(comment.chars().count()+2).unwrap();
                     return Ok(None);
                 }
                 if self.peek(2).unwrap_or(' ') == '=' {
                     Token::new(TokenType::AssignmentOperator, self.peek_string(2).unwrap())
+                } else if first_char == '-' && self.peek(2).unwrap_or(' ') == '>' {
+                    Token::new(TokenType::Arrow, self.peek_string(2).unwrap())
                 } else {
                     Token::new(TokenType::Operator, first_char.to_string())
                 }  
             },
 
             '\'' => {
-                if let Some(mut contents) = self.peek_until('\'', 1, false) {
-                    contents.push('\'');
-                    contents.insert(0, '\'');
-                    Token::new(TokenType::CharLiteral, contents)
+                if let Some(contents) = self.peek_literal() {
+                    self.literal_token(TokenType::CharLiteral, contents)?
                 } else {
-                    return Err("Unexpected EOF (you have to close the \' character literal!)".to_owned()) 
+                    return Err(LexError::UnterminatedChar { span: self.span(1) })
                 }
             }
 
             '"' => {
-                if let Some(mut contents) = self.peek_until('"', 1, false) {
-                    contents.push('"');
-                    contents.insert(0, '"');
-                    Token::new(TokenType::StringLiteral, contents)
+                if let Some(contents) = self.peek_literal() {
+                    self.literal_token(TokenType::StringLiteral, contents)?
                 } else {
-                    return Err("Unexpected EOF (you have to close the \" string literal!)".to_owned()) 
+                    return Err(LexError::UnterminatedString { span: self.span(1) })
                 }
             }
             
             _ => {
                 if first_char.is_ascii_digit() {
                     let num = self.peek_number();
-                    if num.chars().filter(|c| *c == '.').count() <= 1 && !num.starts_with('.') && !num.ends_with('.') {
-                        Token::new(TokenType::NumberLiteral, self.peek_number())
+                    if valid_number(&num) {
+                        Token::new(TokenType::NumberLiteral, num)
                     } else {
-                        return Err("Invalid number syntax!".to_owned())
+                        return Err(LexError::InvalidNumber { span: self.span(num.chars().count()), found: num })
                     }
                 } else if first_char.is_ascii_alphabetic() {
                     let name: String = self.peek_name();
@@ -242,7 +342,7 @@ impl<'a> Tokenizer<'a> {
                         Token::new(TokenType::Name, name)
                     }
                 } else {
-                    return Err(format!("Unexpected character '{}'!", first_char));
+                    return Err(LexError::UnexpectedCharacter { span: self.span(1), found: first_char });
                 }
             }
 
@@ -251,19 +351,160 @@ impl<'a> Tokenizer<'a> {
     }
 }
 
+// number literals are decimal ints (1_000), floats with a fraction and/or an exponent (1.5, 2e10, 1.5e-3) or ints in
+// another base (0x1F, 0b1010, 0o755), underscores can separate digits
+// ints can end with the name of an int type that gives them their type (255u8, 0x7Fi8)
+// whether the value fits into its type is checked by the parser
+fn valid_number(number: &str) -> bool {
+    // one or more digits, underscores are only allowed between them
+    let digits = |part: &str, radix: u32| {
+        !part.is_empty() && !part.starts_with('_') && !part.ends_with('_') && part.chars().all(|c| c.is_digit(radix) || c == '_')
+    };
+    let (number, is_int) = match split_int_suffix(number) {
+        (int, Some(_)) => (int, true),
+        (number, None) => (number, false),
+    };
+    for (prefix, radix) in [("0x", 16), ("0b", 2), ("0o", 8)] {
+        if let Some(rest) = number.strip_prefix(prefix) {
+            return digits(rest, radix);
+        }
+    }
+    let (mantissa, exponent) = match number.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent.strip_prefix(['+', '-']).unwrap_or(exponent))),
+        None => (number, None),
+    };
+    let mantissa_valid = match mantissa.split_once('.') {
+        Some((int_part, fraction)) => digits(int_part, 10) && digits(fraction, 10),
+        None => digits(mantissa, 10),
+    };
+    mantissa_valid && exponent.is_none_or(|exponent| digits(exponent, 10)) && !(is_int && (exponent.is_some() || mantissa.contains('.')))
+}
 
-pub fn create_tokens(source: String) -> Result<Vec<Token>, String> {
+// splits the type suffix off a number literal (the hex digits don't contain i or u, so 0xFFu8 is unambiguous)
+pub fn split_int_suffix(number: &str) -> (&str, Option<&'static str>) {
+    for suffix in INT_SUFFIXES {
+        if let Some(int) = number.strip_suffix(suffix) {
+            return (int, Some(suffix));
+        }
+    }
+    (number, None)
+}
+
+// decodes the escape sequences in the contents of a string or char literal: \n \t \r \\ \" \' \0, \xNN (ASCII only, so
+// strings stay valid UTF-8) and \u{N} with 1 to 6 hex digits
+// an invalid escape sequence is returned with the index of its backslash (in chars)
+pub fn unescape(contents: &str) -> Result<String, (usize, String)> {
+    let mut value = String::new();
+    let mut chars = contents.chars().enumerate().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let mut found = String::from(c);
+        let escaped = match chars.next() {
+            Some((_, c)) => {
+                found.push(c);
+                c
+            }
+            None => return Err((start, found)),
+        };
+        let decoded = match escaped {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '\\' | '"' | '\'' => Some(escaped),
+            '0' => Some('\0'),
+            'x' => {
+                let mut digits = String::new();
+                while digits.len() < 2 {
+                    match chars.peek() {
+                        Some((_, c)) if c.is_ascii_hexdigit() => digits.push(chars.next().unwrap().1),
+                        _ => break,
+                    }
+                }
+                found += &digits;
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(byte) if digits.len() == 2 && byte.is_ascii() => Some(byte as char),
+                    _ => None,
+                }
+            }
+            'u' => {
+                // everything up to the closing brace belongs to the escape sequence (it can't be longer than \u{10FFFF})
+                if let Some((_, '{')) = chars.peek() {
+                    for (_, c) in chars.by_ref() {
+                        found.push(c);
+                        if c == '}' || found.chars().count() == 10 {
+                            break;
+                        }
+                    }
+                }
+                let digits = found.strip_prefix("\\u{").and_then(|rest| rest.strip_suffix('}')).unwrap_or("");
+                if !digits.is_empty() && digits.len() <= 6 && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    char::from_u32(u32::from_str_radix(digits, 16).unwrap())
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        match decoded {
+            Some(c) => value.push(c),
+            None => return Err((start, found)),
+        }
+    }
+    Ok(value)
+}
+
+// lexes the whole source, errors are collected (up to error_limit, 0 means no limit) instead of stopping at the first one
+pub fn create_tokens(source: &str, error_limit: usize) -> Result<Vec<Token>, Vec<LexError>> {
+    let (tokens, errors) = tokenize(source, error_limit);
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors)
+    }
+}
+
+// like create_tokens, but the tokens are returned even if there are errors, so the parser can report its errors as well
+// (the characters of every error become an Invalid token)
+pub fn tokenize(source: &str, error_limit: usize) -> (Vec<Token>, Vec<LexError>) {
     let mut tokens: Vec<Token> = vec![];
+    let mut errors: Vec<LexError> = vec![];
     
-    let mut tokenizer = Tokenizer::new(&source);
+    let mut tokenizer = Tokenizer::new(source);
 
     while !tokenizer.is_empty() {
-        let token = tokenizer.next_token()?;
-        if let Some(token) = token {
-            tokenizer.advance(token.value.len()).unwrap();
-            tokens.push(token);
-        } 
+        match tokenizer.next_token() {
+            Ok(Some(mut token)) => {
+                let len = token.value.chars().count();
+                token.span = tokenizer.span(len);
+                tokenizer.advance(len).unwrap();
+                tokens.push(token);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                // skip the offending characters and keep going, unless the rest of the file is lost anyway
+                let skip = match &e {
+                    LexError::UnexpectedCharacter { .. } => Some(1),
+                    LexError::InvalidNumber { found, .. } => Some(found.chars().count()),
+                    // the rest of the literal is skipped as well
+                    LexError::InvalidEscape { .. } | LexError::InvalidCharLiteral { .. } => tokenizer.peek_literal().map(|contents| contents.chars().count() + 2),
+                    LexError::UnterminatedChar { .. } | LexError::UnterminatedString { .. } => None,
+                };
+                errors.push(e);
+                // once lexing stops, the rest of the file is one invalid token
+                let skip = skip.filter(|_| errors.len() != error_limit);
+                let n = skip.unwrap_or_else(|| tokenizer.chars.clone().count());
+                let mut token = Token::new(TokenType::Invalid, tokenizer.chars.clone().take(n).collect());
+                token.span = tokenizer.span(n);
+                tokens.push(token);
+                if skip.is_none() {
+                    break;
+                }
+                tokenizer.advance(n).unwrap();
+            }
+        }
     }
-
-    Ok(tokens)
+    (tokens, errors)
 }
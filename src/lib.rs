@@ -1,69 +1,177 @@
 use std::fs;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
 
 use clap::Parser;
 
-use crate::lexer::create_tokens;
+use crate::lexer::tokenize;
+use crate::nodes::ScopeNode;
+use crate::output::{Diagnostic, Level, SourceFile};
 
-mod lexer;
-mod nodes;
-mod parser;
-mod variable_traverser;
-mod output;
+pub mod lexer;
+pub mod nodes;
+pub mod parser;
+pub mod variable_traverser;
+pub mod output;
+pub mod span;
+pub mod errors;
+pub mod x86;
+pub mod codegen;
+pub mod runtime;
+pub mod interpreter;
+pub mod encoder;
+pub mod elf;
+pub mod ir;
+pub mod lowering;
+pub mod analysis;
+pub mod ssa;
+pub mod optimizer;
+pub mod regalloc;
+pub mod abi;
+pub mod c_codegen;
+pub mod integer;
 
 // clap generates cli parsing into this struct for us through macros
 
+// what kind of output file gets written
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Emit {
+    /// A static linux executable
+    Exe,
+    /// x86-64 assembly (GAS, intel syntax)
+    Asm,
+    /// The intermediate representation (three-address code)
+    Ir,
+}
+
+// what the program gets compiled to
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Target {
+    /// x86-64 machine code for linux
+    X86_64,
+    /// C99 source code, which any C compiler can turn into an executable
+    C,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// Run a file with the interpreter instead of compiling it
+    Run {
+        /// The name of the file to be run
+        file: String,
+    },
+}
+
 #[derive(Parser, Debug)]
 #[clap(name = "hj compiler")]
 #[clap(author = "karl2883 & Trubiso")]
 #[clap(version = "0.0")]
 #[clap(about = "Compiles hj source files into executables.")]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Config {
-    /// The name of the output file
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// The name of the output file (the name of the source file without the extension by default)
     #[clap(short, long)]
     pub output: Option<String>,
 
+    /// The kind of output file
+    #[clap(long, value_enum, default_value = "exe")]
+    pub emit: Emit,
+
+    /// What to compile to (C source is written instead of an executable with c)
+    #[clap(long, value_enum, default_value = "x86-64")]
+    pub target: Target,
+
+    /// The optimization level (0: none, 1: constant and copy propagation and dead code elimination, 2: also common subexpression elimination)
+    #[clap(short = 'O', value_name = "LEVEL", default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=2))]
+    pub opt_level: u8,
+
     /// Print debug information
-    #[clap(short, long)]
+    #[clap(short, long, global = true)]
     pub debug: bool,
 
+    /// Stop after this many errors (0 means no limit)
+    #[clap(long, value_name = "N", default_value_t = 20, global = true)]
+    pub error_limit: usize,
+
+    /// Make implicit conversions from ints to floats in binary operations errors instead of warnings (they have to be written with "as float")
+    #[clap(long, global = true)]
+    pub strict_numeric: bool,
+
+    /// Print a detailed explanation of an error code (e.g. E0003)
+    #[clap(long, value_name = "CODE")]
+    pub explain: Option<String>,
+
     /// The name of the file to be compiled
-    pub file: String,
+    #[clap(required_unless_present = "explain")]
+    pub file: Option<String>,
+}
+
+fn explain(code: &str) -> Result<(), ()> {
+    match errors::explain(code) {
+        Some(explanation) => {
+            println!("{}", explanation);
+            Ok(())
+        }
+        None => {
+            let error = Diagnostic::error(format!("\"{}\" is not a valid error code", code));
+            output::print_diagnostic(&error, &SourceFile::new("", ""));
+            Err(())
+        }
+    }
 }
 
-pub fn run(config: Config) -> Result<(), ()> {
+fn print_diagnostics(diagnostics: &[Diagnostic], source_file: &SourceFile, error_limit: usize) {
+    let shown = if error_limit == 0 { diagnostics.len() } else { diagnostics.len().min(error_limit) };
+    for diagnostic in &diagnostics[..shown] {
+        output::print_diagnostic(diagnostic, source_file);
+    }
+    if diagnostics.len() > error_limit && error_limit != 0 {
+        output::print_process("Stopping", format!("after {} errors (use --error-limit to change the limit)", error_limit).as_str());
+    }
+}
 
-    output::print_process("Compiling", format!("file {}...", config.file).as_str());
+// the executables are ELF files for linux, other systems can't run them anyway, but the file can still be written there
+fn write_executable(path: &str, bytes: &[u8]) -> std::io::Result<()> {
+    fs::write(path, bytes)?;
+    #[cfg(unix)]
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+// reads, parses and type checks the file, the errors are printed and the warnings returned
+fn check(config: &Config, source_file: &SourceFile) -> Result<(ScopeNode, Vec<Diagnostic>), ()> {
     if config.debug {
         output::print_debug("Printing debug information!")
     }
 
-    let source = match fs::read_to_string(&config.file) {
-        Ok(src) => src,
-        Err(e) => {
-            output::print_error(format!("Error reading from source file \"{}\": {}", &config.file, e).as_str());
-            return Err(());
-        }
-    };
-    
-    let tokens = match create_tokens(source) {
-        Ok(t) => t,
-        Err(e) => {
-            output::print_error(e.as_str());
-            return Err(());
-        }
-    };
+    // one error more than are shown is collected, so print_diagnostics knows whether any were left out
+    let collect_limit = if config.error_limit == 0 { 0 } else { config.error_limit + 1 };
+    // the parser goes on with the tokens the lexer recovered, so the errors of both are reported together
+    let (tokens, lex_errors) = tokenize(source_file.text, collect_limit);
+    let mut diagnostics: Vec<Diagnostic> = lex_errors.iter().map(|e| e.to_diagnostic()).collect();
 
-    if config.debug {
+    if config.debug && lex_errors.is_empty() {
         output::print_debug("Tokens have been generated successfully!");
         let token_str = tokens.iter().map(|token| token.debug_str()).collect::<Vec<String>>().join(",\n");
         output::print_debug(format!("Tokens: {}", token_str).as_str());
     }
 
-    let mut parser = parser::Parser::new(tokens);
-    let mut scope_node = match parser.parse() {
-        Ok(node) => node,
-        Err(e) => {
-            output::print_error(e.as_str());
+    // the parse errors in front of the last lex error can still be among the first error_limit errors
+    let parsed = parser::Parser::new(tokens, collect_limit).parse();
+    let mut scope_node = match parsed {
+        Ok(node) if diagnostics.is_empty() => node,
+        parsed => {
+            if let Err(errors) = parsed {
+                diagnostics.extend(errors.iter().map(|e| e.to_diagnostic()));
+            }
+            // in the order they appear in the file, errors at the end of the file come last
+            diagnostics.sort_by_key(|diagnostic| diagnostic.primary.as_ref().map_or(usize::MAX, |label| label.span.start));
+            print_diagnostics(&diagnostics, source_file, config.error_limit);
             return Err(());
         }
     };
@@ -75,16 +183,143 @@ pub fn run(config: Config) -> Result<(), ()> {
     }
 
     let mut variable_traverser = variable_traverser::VariableTraverser::new();
+    variable_traverser.strict_numeric = config.strict_numeric;
     match variable_traverser.traverse(&mut scope_node) {
         Ok(_) => {},
+        Err(errors) => {
+            let diagnostics: Vec<Diagnostic> = errors.iter().map(|e| e.to_diagnostic()).collect();
+            print_diagnostics(&diagnostics, source_file, config.error_limit);
+            return Err(());
+        }
+    }
+    if config.debug {
+        output::print_debug(&format!("Amount of variables: {}", variable_traverser.variable_count));
+    }
+
+    let warnings = variable_traverser.warnings.iter().map(|w| w.to_diagnostic().with_level(Level::Warning)).collect();
+    Ok((scope_node, warnings))
+}
+
+fn read_source(file: &str) -> Result<String, ()> {
+    match fs::read_to_string(file) {
+        Ok(src) => Ok(src),
         Err(e) => {
-            output::print_error(e.as_str());
+            let error = Diagnostic::error(format!("Error reading from source file \"{}\": {}", file, e));
+            output::print_diagnostic(&error, &SourceFile::new(file, ""));
+            Err(())
+        }
+    }
+}
+
+fn compile(config: &Config, file: &str) -> Result<(), ()> {
+    output::print_process("Compiling", format!("file {}...", file).as_str());
+    let source = read_source(file)?;
+    let source_file = SourceFile::new(file, &source);
+    let (scope_node, warnings) = check(config, &source_file)?;
+    for warning in &warnings {
+        output::print_diagnostic(warning, &source_file);
+    }
+
+    // without an output file, the output is written next to the source file
+    let (extension, description) = match (config.emit, config.target) {
+        (Emit::Ir, _) => ("ir", "IR"),
+        (Emit::Asm, Target::C) => {
+            let error = Diagnostic::error("There is no assembly for the C target (without --emit, the C source is written)".to_string());
+            output::print_diagnostic(&error, &SourceFile::new(file, ""));
             return Err(());
         }
+        (Emit::Exe, Target::C) => ("c", "C source"),
+        (Emit::Exe, Target::X86_64) => ("", "executable"),
+        (Emit::Asm, Target::X86_64) => ("s", "assembly"),
+    };
+    let output_file = match &config.output {
+        Some(output) => output.clone(),
+        // a source file without an extension would be overwritten by the executable
+        None if Path::new(file).extension().is_none() && extension.is_empty() => format!("{}.out", file),
+        None => Path::new(file).with_extension(extension).to_string_lossy().to_string(),
+    };
+    if is_same_file(file, &output_file) {
+        let error = Diagnostic::error(format!("The output file \"{}\" is the source file", output_file)).with_help("choose another output file with -o");
+        output::print_diagnostic(&error, &SourceFile::new(file, ""));
+        return Err(());
+    }
+
+    let mut module = lowering::lower(&scope_node);
+    let pass_manager = optimizer::PassManager::new(config.opt_level);
+    if config.debug {
+        output::print_debug(&format!("Optimization passes: {}", pass_manager.pass_names().join(", ")));
     }
-    let variable_table = variable_traverser.variable_table;
-    let vtable_len = variable_table.len();
-    println!("Amount of variables: {}", vtable_len);
+    pass_manager.run(&mut module);
 
+    let write_result = if config.emit == Emit::Ir {
+        fs::write(&output_file, module.to_text())
+    } else if config.target == Target::C {
+        fs::write(&output_file, c_codegen::generate(&module))
+    } else {
+        // registers are only allocated when optimizing, with -O0 every value stays in its stack slot
+        let program = codegen::CodeGenerator::new(config.opt_level > 0).generate(&module);
+        if config.debug {
+            output::print_debug(&format!("Amount of instructions: {}", program.instruction_count()));
+        }
+        match config.emit {
+            Emit::Asm => fs::write(&output_file, program.to_gas()),
+            _ => write_executable(&output_file, &elf::write_executable(&encoder::encode(&program))),
+        }
+    };
+    if let Err(e) = write_result {
+        let error = Diagnostic::error(format!("Error writing to output file \"{}\": {}", &output_file, e));
+        output::print_diagnostic(&error, &SourceFile::new(&output_file, ""));
+        return Err(());
+    }
+    output::print_process("Finished", format!("writing {} to {}", description, output_file).as_str());
     Ok(())
 }
+
+// whether both paths lead to the same file, paths that don't exist (yet) are only compared as they are
+fn is_same_file(first: &str, second: &str) -> bool {
+    match (fs::canonicalize(first), fs::canonicalize(second)) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => Path::new(first) == Path::new(second),
+    }
+}
+
+// runs the file with the interpreter, only the program writes to stdout (unless there are errors)
+fn interpret(config: &Config, file: &str) -> Result<i32, ()> {
+    let source = read_source(file)?;
+    let source_file = SourceFile::new(file, &source);
+    let (scope_node, warnings) = check(config, &source_file)?;
+    for warning in &warnings {
+        output::eprint_diagnostic(warning, &source_file);
+    }
+
+    // deep recursions need more stack than the main thread has
+    let result = std::thread::scope(|scope| {
+        let thread = std::thread::Builder::new().stack_size(interpreter::STACK_SIZE).spawn_scoped(scope, || {
+            let stdout = std::io::stdout();
+            let mut output = std::io::BufWriter::new(stdout.lock());
+            let result = interpreter::Interpreter::new(&mut output).with_stack_size(interpreter::STACK_SIZE).run(&scope_node);
+            let _ = output.flush();
+            result
+        }).expect("Internal interpreter error (the interpreter thread couldn't be started)");
+        thread.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    });
+
+    result.map_err(|error| {
+        output::print_diagnostic(&error.to_diagnostic(), &source_file);
+    })
+}
+
+// returns the exit code hj should exit with (the one of the program for "hj run")
+#[allow(clippy::result_unit_err)]
+pub fn run(config: Config) -> Result<i32, ()> {
+    if let Some(code) = &config.explain {
+        return explain(code).map(|_| 0);
+    }
+    match &config.command {
+        Some(Command::Run { file }) => interpret(&config, file),
+        None => {
+            // clap makes sure there is a file if there is nothing to explain
+            compile(&config, config.file.as_ref().unwrap()).map(|_| 0)
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use crate::ir::*;
+use crate::nodes::*;
+
+// turns the type-checked AST into the IR
+// if statements, loops, && and || become branches between blocks, the scopes disappear (every variable gets its own register)
+
+pub fn lower(scope_node: &ScopeNode) -> Module {
+    let mut main = Lowerer::new(MAIN_NAME, None);
+    main.lower_body(scope_node);
+    let mut functions = vec!();
+    for command in &scope_node.commands {
+        // the type checker made sure that functions are only defined at the top level
+        if let CommandNode::FunctionDefinitionNode(func_def_node) = command {
+            let return_type = func_def_node.return_type.as_deref().map(Type::from);
+            let mut lowerer = Lowerer::new(&func_def_node.function.name, return_type);
+            let mut parameters = HashMap::new();
+            for param in &func_def_node.params {
+                let reg = lowerer.function.new_reg(Type::from(&param.ptype));
+                lowerer.function.params.push(reg);
+                parameters.insert(param.variable.name.clone(), reg);
+            }
+            // the parameters are in their own scope around the body, like in the VariableTraverser
+            lowerer.scopes.push(parameters);
+            lowerer.lower_body(&func_def_node.body);
+            functions.push(lowerer.function);
+        }
+    }
+    Module { main: main.function, functions }
+}
+
+struct Lowerer {
+    function: Function,
+    // the block new instructions get added to
+    current: BlockId,
+    // one table for each scope, mapping variable names to their registers
+    scopes: Vec<HashMap<String, VReg>>,
+    // (the block continue jumps to, the block break jumps to) for each loop the current command is in
+    loop_blocks: Vec<(BlockId, BlockId)>,
+}
+
+impl Lowerer {
+    fn new(name: &str, return_type: Option<Type>) -> Lowerer {
+        let mut function = Function::new(name, return_type);
+        let current = function.new_block();
+        Lowerer {
+            function,
+            current,
+            scopes: vec!(),
+            loop_blocks: vec!(),
+        }
+    }
+
+    fn lower_body(&mut self, scope_node: &ScopeNode) {
+        self.lower_scope(scope_node);
+        // falling off the end of a function that returns a value isn't possible (the type checker made sure of that)
+        let terminator = match self.function.return_type {
+            Some(_) => Terminator::Unreachable,
+            None => Terminator::Return(None),
+        };
+        self.terminate(terminator);
+        self.function.remove_unreachable_blocks();
+    }
+
+    fn emit(&mut self, inst: Inst) {
+        let current = self.current;
+        self.function.block_mut(current).insts.push(inst);
+    }
+
+    // ends the current block, everything after this goes into a new block (which has no predecessors yet)
+    fn terminate(&mut self, terminator: Terminator) {
+        let current = self.current;
+        self.function.block_mut(current).terminator = terminator;
+        self.current = self.function.new_block();
+    }
+
+    // ends the current block with a jump to the target and continues in the target
+    fn jump_to(&mut self, target: BlockId) {
+        let current = self.current;
+        self.function.block_mut(current).terminator = Terminator::Jump(target);
+        self.current = target;
+    }
+
+    fn lower_scope(&mut self, scope_node: &ScopeNode) {
+        self.scopes.push(HashMap::new());
+        for command in &scope_node.commands {
+            self.lower_command(command);
+        }
+        self.scopes.pop();
+    }
+
+    // the register of a variable, the type checker made sure that it exists
+    fn variable(&self, name: &str) -> VReg {
+        *self.scopes.iter().rev().find_map(|scope| scope.get(name)).expect("Internal compiler error (variable not found in lowering)")
+    }
+
+    fn lower_command(&mut self, command: &CommandNode) {
+        match command {
+            CommandNode::VariableDefinitionNode(var_def_node) => {
+                let (t, value) = match &var_def_node.expression {
+                    Some(expr) => (expr.t.unwrap(), self.lower_expression(expr)),
+                    // a variable without an expression starts out as 0 (or as an empty string)
+                    None => {
+                        let t = Type::from(var_def_node.vtype.as_ref().unwrap());
+                        (t, Operand::Const(Lowerer::zero(t)))
+                    }
+                };
+                // the expression is lowered before the variable exists, so "let x = x + 1;" still reads the previous x
+                let reg = self.function.new_reg(t);
+                self.emit(Inst::Copy { dest: reg, src: value });
+                self.scopes.last_mut().unwrap().insert(var_def_node.variable.name.clone(), reg);
+            }
+
+            CommandNode::VariableAssignmentNode(var_assign_node) => {
+                // compound assignments were desugared by the parser already ("x += 1" is "x = x + 1")
+                let value = self.lower_expression(&var_assign_node.expression);
+                let reg = self.variable(&var_assign_node.variable.name);
+                self.emit(Inst::Copy { dest: reg, src: value });
+            }
+
+            CommandNode::FunctionCallNode(func_call_node) => {
+                // a returned value is thrown away
+                self.lower_call(func_call_node, None);
+            }
+
+            CommandNode::IfNode(if_node) => {
+                let cond = self.lower_expression(&if_node.condition);
+                let then_block = self.function.new_block();
+                let else_block = self.function.new_block();
+                let end_block = match if_node.else_body {
+                    Some(_) => self.function.new_block(),
+                    None => else_block,
+                };
+                self.terminate_with_branch(cond, then_block, else_block);
+
+                self.current = then_block;
+                self.lower_scope(&if_node.body);
+                self.jump_to(end_block);
+
+                if let Some(else_body) = &if_node.else_body {
+                    self.current = else_block;
+                    self.lower_scope(else_body);
+                    self.jump_to(end_block);
+                }
+                self.current = end_block;
+            }
+
+            CommandNode::WhileNode(while_node) => {
+                let cond_block = self.function.new_block();
+                self.jump_to(cond_block);
+                let cond = self.lower_expression(&while_node.condition);
+                let body_block = self.function.new_block();
+                let end_block = self.function.new_block();
+                self.terminate_with_branch(cond, body_block, end_block);
+
+                self.current = body_block;
+                self.loop_blocks.push((cond_block, end_block));
+                self.lower_scope(&while_node.body);
+                self.loop_blocks.pop();
+                self.jump_to(cond_block);
+                self.current = end_block;
+            }
+
+            CommandNode::BreakNode(_) => {
+                let (_, end_block) = *self.loop_blocks.last().expect("Internal compiler error (break outside of loop in lowering)");
+                self.terminate(Terminator::Jump(end_block));
+            }
+
+            CommandNode::ContinueNode(_) => {
+                let (cond_block, _) = *self.loop_blocks.last().expect("Internal compiler error (continue outside of loop in lowering)");
+                self.terminate(Terminator::Jump(cond_block));
+            }
+
+            // functions are lowered on their own
+            CommandNode::FunctionDefinitionNode(_) => (),
+
+            CommandNode::ReturnNode(return_node) => {
+                let value = return_node.expression.as_ref().map(|expr| self.lower_expression(expr));
+                self.terminate(Terminator::Return(value));
+            }
+        }
+    }
+
+    fn terminate_with_branch(&mut self, cond: Operand, then_block: BlockId, else_block: BlockId) {
+        let current = self.current;
+        self.function.block_mut(current).terminator = Terminator::Branch { cond, then_block, else_block };
+    }
+
+    fn zero(t: Type) -> Constant {
+        match t {
+            t if t.is_integer() => Constant::Int(0, t),
+            Type::Float => Constant::Float(0.0),
+            Type::Bool => Constant::Bool(false),
+            Type::Char => Constant::Char('\0'),
+            _ => Constant::Str(String::new()),
+        }
+    }
+
+    // the result is put into a new register with the given type, None if the value isn't used
+    fn lower_call(&mut self, func_call_node: &FunctionCallNode, result_type: Option<Type>) -> Option<VReg> {
+        let args = func_call_node.args.iter().map(|arg| self.lower_expression(arg)).collect();
+        let dest = result_type.map(|t| self.function.new_reg(t));
+        self.emit(Inst::Call { dest, function: func_call_node.function.name.clone(), args });
+        dest
+    }
+
+    fn lower_expression(&mut self, expression_node: &TExpressionNode) -> Operand {
+        let t = expression_node.t.expect("Internal compiler error (untyped expression in lowering)");
+        match &expression_node.node {
+            ExpressionNode::VariableNode(var_node) => Operand::Reg(self.variable(&var_node.name)),
+            ExpressionNode::IntLiteralNode(int_node) => Operand::Const(Constant::Int(int_node.value, t)),
+            ExpressionNode::FloatLiteralNode(float_node) => Operand::Const(Constant::Float(float_node.value)),
+            ExpressionNode::BoolLiteralNode(bool_node) => Operand::Const(Constant::Bool(bool_node.value)),
+            ExpressionNode::CharLiteralNode(char_node) => Operand::Const(Constant::Char(char_node.value)),
+            ExpressionNode::StringLiteralNode(string_node) => Operand::Const(Constant::Str(string_node.value.clone())),
+
+            ExpressionNode::FunctionCallNode(func_call_node) => {
+                let dest = self.lower_call(func_call_node, Some(t)).unwrap();
+                Operand::Reg(dest)
+            }
+
+            ExpressionNode::UnaryOperationNode(unary_op_node) => {
+                let src = self.lower_expression(&unary_op_node.expression);
+                let op = match unary_op_node.operator {
+                    Operator::Minus => UnaryOp::Neg,
+                    Operator::Not => UnaryOp::Not,
+                    // +a is just a
+                    _ => return src,
+                };
+                let dest = self.function.new_reg(t);
+                self.emit(Inst::Unary { dest, op, src });
+                Operand::Reg(dest)
+            }
+
+            ExpressionNode::ConversionNode(conversion_node) => {
+                let value = self.lower_expression(&conversion_node.expression);
+                self.convert(value, t)
+            }
+
+            ExpressionNode::CastNode(cast_node) => {
+                let value = self.lower_expression(&cast_node.expression);
+                // a cast to the type the value already has doesn't do anything
+                if cast_node.expression.t == Some(t) { value } else { self.convert(value, t) }
+            }
+
+            ExpressionNode::BinaryOperationNode(binary_op_node) => {
+                if binary_op_node.operator.is_logical() {
+                    return self.lower_logical_operation(binary_op_node);
+                }
+
+                // the type checker converted both sides to the same type already
+                let left = self.lower_expression(&binary_op_node.left_expr);
+                let right = self.lower_expression(&binary_op_node.right_expr);
+
+                let op = match binary_op_node.operator {
+                    Operator::Plus => BinaryOp::Add,
+                    Operator::Minus => BinaryOp::Sub,
+                    Operator::Multiply => BinaryOp::Mul,
+                    Operator::Divide => BinaryOp::Div,
+                    Operator::Modulo => BinaryOp::Rem,
+                    Operator::ShiftLeft => BinaryOp::Shl,
+                    Operator::ShiftRight => BinaryOp::Shr,
+                    Operator::Equal => BinaryOp::Eq,
+                    Operator::NotEqual => BinaryOp::Ne,
+                    Operator::Less => BinaryOp::Lt,
+                    Operator::LessEqual => BinaryOp::Le,
+                    Operator::Greater => BinaryOp::Gt,
+                    Operator::GreaterEqual => BinaryOp::Ge,
+                    _ => panic!("Internal compiler error (invalid binary operator in lowering)"),
+                };
+                let dest = self.function.new_reg(t);
+                self.emit(Inst::Binary { dest, op, left, right });
+                Operand::Reg(dest)
+            }
+        }
+    }
+
+    fn convert(&mut self, value: Operand, t: Type) -> Operand {
+        let dest = self.function.new_reg(t);
+        self.emit(Inst::Convert { dest, src: value });
+        Operand::Reg(dest)
+    }
+
+    // the right side of && and || is only evaluated if the left one doesn't decide the result already
+    // a && b: result = a; if result { result = b }
+    // a || b: result = a; if !result { result = b }
+    fn lower_logical_operation(&mut self, binary_op_node: &BinaryOperationNode) -> Operand {
+        let result = self.function.new_reg(Type::Bool);
+        let left = self.lower_expression(&binary_op_node.left_expr);
+        self.emit(Inst::Copy { dest: result, src: left });
+
+        let right_block = self.function.new_block();
+        let end_block = self.function.new_block();
+        let (then_block, else_block) = match binary_op_node.operator {
+            Operator::And => (right_block, end_block),
+            _ => (end_block, right_block),
+        };
+        self.terminate_with_branch(Operand::Reg(result), then_block, else_block);
+
+        self.current = right_block;
+        let right = self.lower_expression(&binary_op_node.right_expr);
+        self.emit(Inst::Copy { dest: result, src: right });
+        self.jump_to(end_block);
+        self.current = end_block;
+        Operand::Reg(result)
+    }
+}
@@ -3,8 +3,17 @@ use clap::Parser;
 
 fn main() {
     let args = Config::parse();
+    let running = args.command.is_some();
 
-    if let Err(()) = hj::run(args) {
-        println!("Unable to compile (see errors above)!");
+    match hj::run(args) {
+        Ok(exit_code) => std::process::exit(exit_code),
+        Err(()) => {
+            if running {
+                println!("Unable to run (see errors above)!");
+            } else {
+                println!("Unable to compile (see errors above)!");
+            }
+            std::process::exit(1);
+        }
     }
 }
@@ -1,13 +1,42 @@
+use crate::span::Span;
 
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Associativity {
+    Left,  // a - b - c is (a - b) - c
+    Right, // a = b = c would be a = (b = c)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Operator {
     Plus,
     Minus,
     Multiply,
     Divide,
     Modulo,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    // the count of a shift is taken modulo the width of the shifted int, >> keeps the sign of signed ints
+    ShiftLeft,
+    ShiftRight,
+    // && and || short-circuit: the right side is only evaluated if the left side doesn't decide the result already
+    And,
+    Or,
+    Not,
 }
 
 impl Operator {
+    // every operator in the order of the enum, new operators have to be added here as well
+    pub const ALL: [Operator; 16] = [
+        Operator::Plus, Operator::Minus, Operator::Multiply, Operator::Divide, Operator::Modulo,
+        Operator::Equal, Operator::NotEqual, Operator::Less, Operator::LessEqual, Operator::Greater, Operator::GreaterEqual,
+        Operator::ShiftLeft, Operator::ShiftRight, Operator::And, Operator::Or, Operator::Not,
+    ];
+
     pub fn from(op: &str) -> Operator {
         match op {
             "+" => Operator::Plus,
@@ -15,6 +44,17 @@ impl Operator {
             "*" => Operator::Multiply,
             "/" => Operator::Divide,
             "%" => Operator::Modulo,
+            "==" => Operator::Equal,
+            "!=" => Operator::NotEqual,
+            "<" => Operator::Less,
+            "<=" => Operator::LessEqual,
+            ">" => Operator::Greater,
+            ">=" => Operator::GreaterEqual,
+            "<<" => Operator::ShiftLeft,
+            ">>" => Operator::ShiftRight,
+            "&&" => Operator::And,
+            "||" => Operator::Or,
+            "!" => Operator::Not,
             _ => panic!("Internal compiler error (unknown operator)")
         }
     }
@@ -27,33 +67,119 @@ impl Operator {
                 Operator::Multiply => "*",
                 Operator::Divide => "/",
                 Operator::Modulo => "%",
+                Operator::Equal => "==",
+                Operator::NotEqual => "!=",
+                Operator::Less => "<",
+                Operator::LessEqual => "<=",
+                Operator::Greater => ">",
+                Operator::GreaterEqual => ">=",
+                Operator::ShiftLeft => "<<",
+                Operator::ShiftRight => ">>",
+                Operator::And => "&&",
+                Operator::Or => "||",
+                Operator::Not => "!",
             }
         )
     }
 
-    pub fn priority_score(&self) -> u32 {
+    // binding power of unary operators (+a, -a, !a), higher than the one of every binary operator
+    pub const PREFIX_BINDING_POWER: u32 = 100;
+    // binding power of casts (a as u8), between the binary and the unary operators: a * b as float is a * (b as float),
+    // -a as u8 is (-a) as u8
+    pub const CAST_BINDING_POWER: u32 = 50;
+
+    // higher score = binds more tightly, None for operators that can't be used between two values
+    pub fn priority_score(&self) -> Option<u32> {
         match self {
-            Operator::Plus | Operator::Minus => 1,
-            Operator::Multiply | Operator::Divide | Operator::Modulo => 2,
+            Operator::Or => Some(1),
+            Operator::And => Some(2),
+            Operator::Equal | Operator::NotEqual => Some(3),
+            Operator::Less | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual => Some(4),
+            Operator::ShiftLeft | Operator::ShiftRight => Some(5),
+            Operator::Plus | Operator::Minus => Some(6),
+            Operator::Multiply | Operator::Divide | Operator::Modulo => Some(7),
+            // only used as a unary operator
+            Operator::Not => None,
         }
     }
+
+    pub fn associativity(&self) -> Associativity {
+        match self {
+            Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Modulo => Associativity::Left,
+            Operator::Equal | Operator::NotEqual => Associativity::Left,
+            Operator::Less | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual => Associativity::Left,
+            Operator::ShiftLeft | Operator::ShiftRight => Associativity::Left,
+            Operator::And | Operator::Or => Associativity::Left,
+            // only used as a prefix operator, !!a is !(!a)
+            Operator::Not => Associativity::Right,
+        }
+    }
+
+    // (left, right) binding power for the pratt parser: the operand in between goes to the side that binds more tightly,
+    // so the associativity decides which side gets the bigger number
+    pub fn infix_binding_power(&self) -> Option<(u32, u32)> {
+        let score = self.priority_score()?;
+        match self.associativity() {
+            Associativity::Left => Some((score * 2, score * 2 + 1)),
+            Associativity::Right => Some((score * 2 + 1, score * 2)),
+        }
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(self, Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Modulo)
+    }
+
+    pub fn is_shift(&self) -> bool {
+        matches!(self, Operator::ShiftLeft | Operator::ShiftRight)
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, Operator::Less | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual)
+    }
+
+    pub fn is_equality(&self) -> bool {
+        matches!(self, Operator::Equal | Operator::NotEqual)
+    }
+
+    pub fn is_logical(&self) -> bool {
+        matches!(self, Operator::And | Operator::Or)
+    }
 }
 
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug)]
 #[derive(Copy, Clone)]
 pub enum Type {
+    // int and uint are 64 bit (i64 and u64 are other names for them), the others have their width in the name
     Int,
+    I8,
+    I16,
+    I32,
+    UInt,
+    U8,
+    U16,
+    U32,
     Float,
     Bool,
     Char,
-    Str
+    Str,
+    // poison type of expressions that already caused an error, it's accepted everywhere so one error doesn't cause many more
+    Error,
 }
 
 impl Type {
+    pub const INTEGERS: [Type; 8] = [Type::Int, Type::I8, Type::I16, Type::I32, Type::UInt, Type::U8, Type::U16, Type::U32];
+
     pub fn from(typename: &str) -> Type {
         match typename {
-            "int" => Type::Int,
+            "int" | "i64" => Type::Int,
+            "i8" => Type::I8,
+            "i16" => Type::I16,
+            "i32" => Type::I32,
+            "uint" | "u64" => Type::UInt,
+            "u8" => Type::U8,
+            "u16" => Type::U16,
+            "u32" => Type::U32,
             "float" => Type::Float,
             "bool" => Type::Bool,
             "char" => Type::Char,
@@ -62,13 +188,70 @@ impl Type {
         }
     }
 
-    pub fn to_str(&self) -> &str {
+    pub fn to_str(self) -> &'static str {
         match self {
             Type::Int => "int",
+            Type::I8 => "i8",
+            Type::I16 => "i16",
+            Type::I32 => "i32",
+            Type::UInt => "uint",
+            Type::U8 => "u8",
+            Type::U16 => "u16",
+            Type::U32 => "u32",
             Type::Float => "float",
             Type::Bool => "bool",
             Type::Char => "char",
             Type::Str => "str",
+            Type::Error => "{error}",
+        }
+    }
+
+    // the name of an int type as the suffix of a literal (10u8), it always contains the width
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Type::Int => "i64",
+            Type::UInt => "u64",
+            _ => self.to_str(),
+        }
+    }
+
+    pub fn is_error(self) -> bool {
+        self == Type::Error
+    }
+
+    pub fn is_integer(self) -> bool {
+        Type::INTEGERS.contains(&self)
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(self, Type::Int | Type::I8 | Type::I16 | Type::I32)
+    }
+
+    pub fn is_numeric(self) -> bool {
+        self.is_integer() || self == Type::Float
+    }
+
+    // the number of bits of an int type
+    pub fn bits(self) -> u32 {
+        match self {
+            Type::I8 | Type::U8 => 8,
+            Type::I16 | Type::U16 => 16,
+            Type::I32 | Type::U32 => 32,
+            Type::Int | Type::UInt => 64,
+            _ => panic!("Internal compiler error (bits of the non-int type {})", self.to_str()),
+        }
+    }
+
+    // whether every value of this int type is also a value of the other one, so it can be converted without asking
+    // (to a wider int with the same signedness, or from unsigned to a wider signed int)
+    pub fn widens_to(self, other: Type) -> bool {
+        if !self.is_integer() || !other.is_integer() {
+            return false;
+        }
+        match (self.is_signed(), other.is_signed()) {
+            (true, true) | (false, false) => self.bits() <= other.bits(),
+            (false, true) => self.bits() < other.bits(),
+            (true, false) => false,
         }
     }
 }
@@ -76,67 +259,149 @@ impl Type {
 
 pub struct ScopeNode {
     pub commands: Vec<CommandNode>,
+    pub span: Span,
 }
 
 pub struct VariableNode {
     pub name: String,
+    pub span: Span,
 }
 
 pub struct VariableDefinitionNode {
     pub vtype: Option<String>, // temporary
     pub variable: VariableNode,
     pub expression: Option<Box<TExpressionNode>>,
+    pub span: Span,
 }
 
 pub struct VariableAssignmentNode {
     pub variable: VariableNode,
     pub expression: Box<TExpressionNode>,
+    pub span: Span,
 }
 
 pub struct BinaryOperationNode {
     pub left_expr: Box<TExpressionNode>,
     pub operator: Operator,
     pub right_expr: Box<TExpressionNode>,
+    pub span: Span,
 }
 
 pub struct UnaryOperationNode {
     pub operator: Operator,
     pub expression: Box<TExpressionNode>,
+    pub span: Span,
 }
 
 pub struct IntLiteralNode {
+    // the bits of the value as it's stored in 64 bits (see integer.rs), so a u64 above i64::MAX is negative here
     pub value: i64,
+    // the type written after the number (10u8), without one the literal is an int that can take the type of the other
+    // values it's used with (see VariableTraverser::convert_implicitly and VariableTraverser::adapts_to)
+    pub suffix: Option<Type>,
+    pub span: Span,
 }
 
 pub struct FloatLiteralNode {
     pub value: f64,
+    pub span: Span,
 }
 
 pub struct BoolLiteralNode {
     pub value: bool,
+    pub span: Span,
 }
 
 pub struct StringLiteralNode {
     pub value: String,
+    pub span: Span,
 }
 
 pub struct CharLiteralNode {
     pub value: char,
+    pub span: Span,
+}
+
+// converts the value of the expression to the type of this node, only inserted by the type checker (e.g. an i8 used as an
+// int, or an int used together with a float)
+pub struct ConversionNode {
+    pub expression: Box<TExpressionNode>,
+    pub span: Span,
+}
+
+// converts the value of the expression to the type written after "as" (see VariableTraverser::cast_valid for the casts
+// that exist)
+pub struct CastNode {
+    pub expression: Box<TExpressionNode>,
+    pub target: String,
+    pub target_span: Span,
+    pub span: Span,
 }
 
 pub struct FunctionCallNode {
     pub function: FunctionNode,
     pub args: Vec<TExpressionNode>,
+    pub span: Span,
 }
 
 pub struct FunctionNode {
     pub name: String,
+    pub span: Span,
+}
+
+pub struct IfNode {
+    pub condition: Box<TExpressionNode>,
+    pub body: ScopeNode,
+    // "else if" is stored as an else scope containing just another if node
+    pub else_body: Option<ScopeNode>,
+    pub span: Span,
+}
+
+pub struct WhileNode {
+    pub condition: Box<TExpressionNode>,
+    pub body: ScopeNode,
+    pub span: Span,
+}
+
+pub struct BreakNode {
+    pub span: Span,
+}
+
+pub struct ContinueNode {
+    pub span: Span,
+}
+
+pub struct ParameterNode {
+    pub ptype: String, // temporary, like VariableDefinitionNode::vtype
+    pub variable: VariableNode,
+    pub span: Span,
+}
+
+pub struct FunctionDefinitionNode {
+    pub function: FunctionNode,
+    pub params: Vec<ParameterNode>,
+    // None if the function doesn't return a value
+    pub return_type: Option<String>,
+    pub body: ScopeNode,
+    pub span: Span,
+}
+
+pub struct ReturnNode {
+    pub expression: Option<Box<TExpressionNode>>,
+    pub span: Span,
 }
 
+#[allow(clippy::enum_variant_names)]
 pub enum CommandNode {
     VariableDefinitionNode(VariableDefinitionNode),
     VariableAssignmentNode(VariableAssignmentNode),
     FunctionCallNode(FunctionCallNode),
+    IfNode(IfNode),
+    WhileNode(WhileNode),
+    BreakNode(BreakNode),
+    ContinueNode(ContinueNode),
+    FunctionDefinitionNode(FunctionDefinitionNode),
+    ReturnNode(ReturnNode),
 }
 
 pub struct TExpressionNode {
@@ -144,6 +409,7 @@ pub struct TExpressionNode {
     pub t: Option<Type>,
 }
 
+#[allow(clippy::enum_variant_names)]
 pub enum ExpressionNode {
     BinaryOperationNode(BinaryOperationNode),
     UnaryOperationNode(UnaryOperationNode),
@@ -154,6 +420,58 @@ pub enum ExpressionNode {
     StringLiteralNode(StringLiteralNode),
     CharLiteralNode(CharLiteralNode),
     FunctionCallNode(FunctionCallNode),
+    ConversionNode(ConversionNode),
+    CastNode(CastNode),
+}
+
+impl ExpressionNode {
+    pub fn span(&self) -> Span {
+        match self {
+            ExpressionNode::BinaryOperationNode(node) => node.span,
+            ExpressionNode::UnaryOperationNode(node) => node.span,
+            ExpressionNode::VariableNode(node) => node.span,
+            ExpressionNode::IntLiteralNode(node) => node.span,
+            ExpressionNode::FloatLiteralNode(node) => node.span,
+            ExpressionNode::BoolLiteralNode(node) => node.span,
+            ExpressionNode::StringLiteralNode(node) => node.span,
+            ExpressionNode::CharLiteralNode(node) => node.span,
+            ExpressionNode::FunctionCallNode(node) => node.span,
+            ExpressionNode::ConversionNode(node) => node.span,
+            ExpressionNode::CastNode(node) => node.span,
+        }
+    }
+
+    pub fn span_mut(&mut self) -> &mut Span {
+        match self {
+            ExpressionNode::BinaryOperationNode(node) => &mut node.span,
+            ExpressionNode::UnaryOperationNode(node) => &mut node.span,
+            ExpressionNode::VariableNode(node) => &mut node.span,
+            ExpressionNode::IntLiteralNode(node) => &mut node.span,
+            ExpressionNode::FloatLiteralNode(node) => &mut node.span,
+            ExpressionNode::BoolLiteralNode(node) => &mut node.span,
+            ExpressionNode::StringLiteralNode(node) => &mut node.span,
+            ExpressionNode::CharLiteralNode(node) => &mut node.span,
+            ExpressionNode::FunctionCallNode(node) => &mut node.span,
+            ExpressionNode::ConversionNode(node) => &mut node.span,
+            ExpressionNode::CastNode(node) => &mut node.span,
+        }
+    }
+}
+
+impl CommandNode {
+    pub fn span(&self) -> Span {
+        match self {
+            CommandNode::VariableDefinitionNode(node) => node.span,
+            CommandNode::VariableAssignmentNode(node) => node.span,
+            CommandNode::FunctionCallNode(node) => node.span,
+            CommandNode::IfNode(node) => node.span,
+            CommandNode::WhileNode(node) => node.span,
+            CommandNode::BreakNode(node) => node.span,
+            CommandNode::ContinueNode(node) => node.span,
+            CommandNode::FunctionDefinitionNode(node) => node.span,
+            CommandNode::ReturnNode(node) => node.span,
+        }
+    }
 }
 
 pub fn get_tab_str(tab_lvl: usize) -> String {
@@ -183,23 +501,36 @@ impl TExpressionNode {
             }
 
             ExpressionNode::FunctionCallNode(node) => {
-                let mut s = get_tab_str(tab_lvl) + "Function call";
-                s += &format!("calling to function {}\n", node.function.name);
+                let mut s = get_tab_str(tab_lvl) + "Function call ";
+                s += &format!("calling to function {} (at {})\n", node.function.name, node.function.span.debug_str());
                 if node.args.is_empty() {
                     s += &format!("{}without arguments\n", get_tab_str(tab_lvl));
                 } else {
-                    let mut i = 1;
-                    for arg in &node.args {
-                        s += &format!("{}Argument {}:\n", get_tab_str(tab_lvl), i);
+                    for (i, arg) in node.args.iter().enumerate() {
+                        s += &format!("{}Argument {}:\n", get_tab_str(tab_lvl), i+1);
                         s += &arg.debug_str(tab_lvl+1);
-                        i += 1;
                     }
                 }
                 s
             }
             
             ExpressionNode::IntLiteralNode(node) => {
-                format!("{}Int literal with value {}\n", get_tab_str(tab_lvl), node.value)
+                match node.suffix {
+                    Some(suffix) => format!("{}Int literal with value {} and suffix {}\n", get_tab_str(tab_lvl), node.value, suffix.suffix()),
+                    None => format!("{}Int literal with value {}\n", get_tab_str(tab_lvl), node.value),
+                }
+            }
+
+            ExpressionNode::ConversionNode(node) => {
+                let mut s = get_tab_str(tab_lvl) + &format!("Conversion to {}:\n", self.t.map_or("?", Type::to_str));
+                s += &node.expression.debug_str(tab_lvl+1);
+                s
+            }
+
+            ExpressionNode::CastNode(node) => {
+                let mut s = get_tab_str(tab_lvl) + &format!("Cast to {}:\n", node.target);
+                s += &node.expression.debug_str(tab_lvl+1);
+                s
             }
 
             ExpressionNode::FloatLiteralNode(node) => {
@@ -229,7 +560,7 @@ impl CommandNode {
                 if let Some(vtype) = &node.vtype {
                     s += &format!(" with explicit type {}", vtype);
                 }
-                s += &format!(" defining variable {}\n", node.variable.name);
+                s += &format!(" defining variable {} (at {})\n", node.variable.name, node.span.debug_str());
                 if let Some(expr) = &node.expression {
                     s += &get_tab_str(tab_lvl+1);
                     s += "with expression:\n";
@@ -240,7 +571,7 @@ impl CommandNode {
 
             CommandNode::VariableAssignmentNode(node) => {
                 let mut s = get_tab_str(tab_lvl) + "Variable assignment ";
-                s += &format!("assigning to variable {}\n", node.variable.name);
+                s += &format!("assigning to variable {} (at {})\n", node.variable.name, node.span.debug_str());
                 s += &format!("{}with expression:\n", get_tab_str(tab_lvl+1));
                 s += &node.expression.as_ref().debug_str(tab_lvl+1);   
                 s
@@ -248,30 +579,89 @@ impl CommandNode {
 
             CommandNode::FunctionCallNode(node) => {
                 let mut s = get_tab_str(tab_lvl) + "Function call command ";
-                s += &format!("calling to function {}\n", node.function.name);
+                s += &format!("calling to function {} (at {})\n", node.function.name, node.function.span.debug_str());
                 if node.args.is_empty() {
                     s += &format!("{}without arguments\n", get_tab_str(tab_lvl));
                 } else {
-                    let mut i = 1;
-                    for arg in &node.args {
-                        s += &format!("{}Argument {}:\n", get_tab_str(tab_lvl), i);
+                    for (i, arg) in node.args.iter().enumerate() {
+                        s += &format!("{}Argument {}:\n", get_tab_str(tab_lvl), i+1);
                         s += &arg.debug_str(tab_lvl+1);
-                        i += 1;
                     }
                 }
                 s
             }
+
+            CommandNode::IfNode(node) => {
+                let mut s = get_tab_str(tab_lvl) + &format!("If statement (at {})\n", node.span.debug_str());
+                s += &format!("{}with condition:\n", get_tab_str(tab_lvl+1));
+                s += &node.condition.debug_str(tab_lvl+1);
+                s += &format!("{}with body:\n", get_tab_str(tab_lvl+1));
+                s += &node.body.debug_str_nested(tab_lvl+1);
+                if let Some(else_body) = &node.else_body {
+                    s += &format!("{}with else body:\n", get_tab_str(tab_lvl+1));
+                    s += &else_body.debug_str_nested(tab_lvl+1);
+                }
+                s
+            }
+
+            CommandNode::WhileNode(node) => {
+                let mut s = get_tab_str(tab_lvl) + &format!("While loop (at {})\n", node.span.debug_str());
+                s += &format!("{}with condition:\n", get_tab_str(tab_lvl+1));
+                s += &node.condition.debug_str(tab_lvl+1);
+                s += &format!("{}with body:\n", get_tab_str(tab_lvl+1));
+                s += &node.body.debug_str_nested(tab_lvl+1);
+                s
+            }
+
+            CommandNode::BreakNode(node) => {
+                format!("{}Break (at {})\n", get_tab_str(tab_lvl), node.span.debug_str())
+            }
+
+            CommandNode::ContinueNode(node) => {
+                format!("{}Continue (at {})\n", get_tab_str(tab_lvl), node.span.debug_str())
+            }
+
+            CommandNode::FunctionDefinitionNode(node) => {
+                let mut s = get_tab_str(tab_lvl) + &format!("Function definition of function {} (at {})", node.function.name, node.span.debug_str());
+                if let Some(return_type) = &node.return_type {
+                    s += &format!(" with return type {}", return_type);
+                }
+                s += "\n";
+                for param in &node.params {
+                    s += &format!("{}Parameter {} with type {}\n", get_tab_str(tab_lvl+1), param.variable.name, param.ptype);
+                }
+                s += &format!("{}with body:\n", get_tab_str(tab_lvl+1));
+                s += &node.body.debug_str_nested(tab_lvl+1);
+                s
+            }
+
+            CommandNode::ReturnNode(node) => {
+                let mut s = get_tab_str(tab_lvl) + &format!("Return (at {})\n", node.span.debug_str());
+                if let Some(expr) = &node.expression {
+                    s += &format!("{}with expression:\n", get_tab_str(tab_lvl+1));
+                    s += &expr.debug_str(tab_lvl+1);
+                }
+                s
+            }
         }
     }
 }
 
 impl ScopeNode {
     pub fn debug_str(&self) -> String {
-        let mut s = String::from("Outer scope node with commands:\n");
+        let mut s = format!("Outer scope node (from {}) with commands:\n", self.span.debug_str());
         for command in &self.commands {
             s += &command.debug_str(1);
         }
         s
     }
+
+    pub fn debug_str_nested(&self, tab_lvl: usize) -> String {
+        let mut s = format!("{}Scope node (from {}) with commands:\n", get_tab_str(tab_lvl), self.span.debug_str());
+        for command in &self.commands {
+            s += &command.debug_str(tab_lvl+1);
+        }
+        s
+    }
 }
 
@@ -0,0 +1,504 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::analysis::Dominators;
+use crate::integer;
+use crate::ir::*;
+use crate::nodes::Type;
+use crate::ssa;
+
+// the optimization passes on the IR, which all work on functions in SSA form
+// -O0: nothing, -O1: constant propagation, copy propagation, dead code elimination and merging blocks,
+// -O2: also common subexpression elimination
+// the passes run again and again until none of them changes anything, because each of them can give the others more to do
+
+pub trait Pass {
+    fn name(&self) -> &'static str;
+    // returns whether the function changed
+    fn run(&self, function: &mut Function) -> bool;
+}
+
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+// a limit for the rounds, in case passes keep undoing each other
+const MAX_ROUNDS: usize = 20;
+
+impl PassManager {
+    pub fn new(level: u8) -> PassManager {
+        let mut passes: Vec<Box<dyn Pass>> = vec!();
+        if level >= 1 {
+            passes.push(Box::new(ConstantPropagation));
+            passes.push(Box::new(CopyPropagation));
+        }
+        if level >= 2 {
+            passes.push(Box::new(CommonSubexpressionElimination));
+        }
+        if level >= 1 {
+            passes.push(Box::new(DeadCodeElimination));
+            passes.push(Box::new(SimplifyControlFlow));
+        }
+        PassManager { passes }
+    }
+
+    pub fn pass_names(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    pub fn run(&self, module: &mut Module) {
+        if self.passes.is_empty() {
+            return;
+        }
+        for function in module.all_functions_mut() {
+            ssa::construct(function);
+            for _ in 0..MAX_ROUNDS {
+                let mut changed = false;
+                for pass in &self.passes {
+                    changed |= pass.run(function);
+                }
+                if !changed {
+                    break;
+                }
+            }
+            function.compact_registers();
+        }
+    }
+}
+
+// the results of the instructions whose operands are all constants are calculated while compiling,
+// and branches on constants become jumps (the blocks that can't be reached anymore are removed)
+pub struct ConstantPropagation;
+
+impl Pass for ConstantPropagation {
+    fn name(&self) -> &'static str {
+        "constant propagation"
+    }
+
+    fn run(&self, function: &mut Function) -> bool {
+        let mut changed = false;
+        loop {
+            let mut constants: HashMap<VReg, Operand> = HashMap::new();
+            for block in &function.blocks {
+                for inst in &block.insts {
+                    let dest = match inst.dest() {
+                        Some(dest) => dest,
+                        None => continue,
+                    };
+                    if let Some(constant) = fold(inst, &function.reg_types) {
+                        constants.insert(dest, Operand::Const(constant));
+                    }
+                }
+            }
+            // the instructions that calculated the constants aren't needed anymore, dead code elimination removes them
+            let mut found_new = false;
+            for block in &function.blocks {
+                for inst in &block.insts {
+                    if inst.operands().iter().any(|operand| matches!(operand, Operand::Reg(reg) if constants.contains_key(reg))) {
+                        found_new = true;
+                    }
+                }
+                if block.terminator.operands().iter().any(|operand| matches!(operand, Operand::Reg(reg) if constants.contains_key(reg))) {
+                    found_new = true;
+                }
+            }
+            function.replace_uses(&constants);
+            let folded_branch = fold_branches(function);
+            if !found_new && !folded_branch {
+                break;
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+// the constant result of the instruction, if it has one
+fn fold(inst: &Inst, reg_types: &[Type]) -> Option<Constant> {
+    match inst {
+        Inst::Copy { src: Operand::Const(constant), .. } => Some(constant.clone()),
+        Inst::Binary { op, left: Operand::Const(left), right: Operand::Const(right), .. } => fold_binary(*op, left, right),
+        Inst::Unary { op, src: Operand::Const(constant), .. } => match (op, constant) {
+            (UnaryOp::Neg, Constant::Int(value, t)) => Some(Constant::Int(integer::neg(*value, *t), *t)),
+            (UnaryOp::Neg, Constant::Float(value)) => Some(Constant::Float(-value)),
+            (UnaryOp::Not, Constant::Bool(value)) => Some(Constant::Bool(!value)),
+            _ => None,
+        },
+        Inst::Convert { dest, src: Operand::Const(constant) } => Some(match (constant, reg_types[dest.0]) {
+            (Constant::Int(value, t), Type::Float) => Constant::Float(integer::to_float(*value, *t)),
+            (Constant::Int(value, t), Type::Char) => Constant::Char(integer::to_char(*value, *t)),
+            (Constant::Int(value, _), dest_type) => Constant::Int(integer::wrap(*value, dest_type), dest_type),
+            (Constant::Float(value), dest_type) => Constant::Int(integer::from_float(*value, dest_type), dest_type),
+            (Constant::Char(value), dest_type) => Constant::Int(integer::wrap(*value as i64, dest_type), dest_type),
+            (Constant::Bool(value), dest_type) => Constant::Int(*value as i64, dest_type),
+            (Constant::Str(_), _) => panic!("Internal compiler error (conversion of a string)"),
+        }),
+        // a phi that gets the same constant from everywhere
+        Inst::Phi { incoming, .. } => {
+            let first = match incoming.first() {
+                Some((_, Operand::Const(constant))) => constant,
+                _ => return None,
+            };
+            if incoming.iter().all(|(_, operand)| matches!(operand, Operand::Const(constant) if constant == first)) {
+                Some(first.clone())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+// calculates the operation exactly like the compiled code would (ints wrap around),
+// divisions that fail at runtime (by 0, or i64::MIN / -1) are left for the runtime
+fn fold_binary(op: BinaryOp, left: &Constant, right: &Constant) -> Option<Constant> {
+    let value = match (left, right) {
+        (Constant::Int(l, t), Constant::Int(r, _)) => {
+            let (l, r, t) = (*l, *r, *t);
+            match op {
+                BinaryOp::Add => Constant::Int(integer::add(l, r, t), t),
+                BinaryOp::Sub => Constant::Int(integer::sub(l, r, t), t),
+                BinaryOp::Mul => Constant::Int(integer::mul(l, r, t), t),
+                BinaryOp::Div => Constant::Int(integer::div(l, r, t)?, t),
+                BinaryOp::Rem => Constant::Int(integer::rem(l, r, t)?, t),
+                BinaryOp::Shl => Constant::Int(integer::shl(l, r, t), t),
+                BinaryOp::Shr => Constant::Int(integer::shr(l, r, t), t),
+                _ => Constant::Bool(compare(op, Some(integer::compare(l, r, t)))),
+            }
+        }
+        (Constant::Float(l), Constant::Float(r)) => match op {
+            BinaryOp::Add => Constant::Float(l + r),
+            BinaryOp::Sub => Constant::Float(l - r),
+            BinaryOp::Mul => Constant::Float(l * r),
+            BinaryOp::Div => Constant::Float(l / r),
+            BinaryOp::Rem => Constant::Float(l % r),
+            _ => Constant::Bool(compare(op, l.partial_cmp(r))),
+        },
+        (Constant::Char(l), Constant::Char(r)) => Constant::Bool(compare(op, l.partial_cmp(r))),
+        (Constant::Bool(l), Constant::Bool(r)) => Constant::Bool(compare(op, l.partial_cmp(r))),
+        _ => return None,
+    };
+    Some(value)
+}
+
+// None means the values are unordered (NaN), then only "ne" is true
+fn compare(op: BinaryOp, ordering: Option<std::cmp::Ordering>) -> bool {
+    use std::cmp::Ordering::*;
+    match (op, ordering) {
+        (BinaryOp::Ne, None) => true,
+        (_, None) => false,
+        (BinaryOp::Eq, Some(ordering)) => ordering == Equal,
+        (BinaryOp::Ne, Some(ordering)) => ordering != Equal,
+        (BinaryOp::Lt, Some(ordering)) => ordering == Less,
+        (BinaryOp::Le, Some(ordering)) => ordering != Greater,
+        (BinaryOp::Gt, Some(ordering)) => ordering == Greater,
+        (BinaryOp::Ge, Some(ordering)) => ordering != Less,
+        _ => panic!("Internal compiler error (invalid comparison in constant propagation)"),
+    }
+}
+
+// replaces branches on constants with jumps, returns whether there were any
+fn fold_branches(function: &mut Function) -> bool {
+    let mut folded = false;
+    for block_index in 0..function.blocks.len() {
+        let block_id = BlockId(block_index);
+        let (taken, not_taken) = match &function.block(block_id).terminator {
+            Terminator::Branch { cond: Operand::Const(Constant::Bool(value)), then_block, else_block } => {
+                if *value { (*then_block, *else_block) } else { (*else_block, *then_block) }
+            }
+            _ => continue,
+        };
+        function.block_mut(block_id).terminator = Terminator::Jump(taken);
+        // the phis in the block that isn't jumped to anymore lose this block as a predecessor
+        if taken != not_taken {
+            for inst in &mut function.block_mut(not_taken).insts {
+                if let Inst::Phi { incoming, .. } = inst {
+                    incoming.retain(|(pred, _)| *pred != block_id);
+                }
+            }
+        }
+        folded = true;
+    }
+    if folded {
+        function.remove_unreachable_blocks();
+    }
+    folded
+}
+
+// a copy (or a phi that always gets the same value) is removed, everything that reads it reads the original value instead
+pub struct CopyPropagation;
+
+impl Pass for CopyPropagation {
+    fn name(&self) -> &'static str {
+        "copy propagation"
+    }
+
+    fn run(&self, function: &mut Function) -> bool {
+        let mut replacements: HashMap<VReg, Operand> = HashMap::new();
+        for block in &function.blocks {
+            for inst in &block.insts {
+                match inst {
+                    Inst::Copy { dest, src } => {
+                        replacements.insert(*dest, src.clone());
+                    }
+                    Inst::Phi { dest, incoming } => {
+                        // reads of the phi itself (through a loop) don't count as a different value
+                        let mut values = incoming.iter().map(|(_, operand)| operand).filter(|operand| **operand != Operand::Reg(*dest));
+                        let first = match values.next() {
+                            Some(first) => first,
+                            None => continue,
+                        };
+                        if values.all(|operand| operand == first) {
+                            replacements.insert(*dest, first.clone());
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        if replacements.is_empty() {
+            return false;
+        }
+
+        // a copy of a copy has to end up at the original value
+        let regs: Vec<VReg> = replacements.keys().copied().collect();
+        for reg in regs {
+            let mut value = replacements[&reg].clone();
+            let mut steps = 0;
+            while let Operand::Reg(next) = value {
+                match replacements.get(&next) {
+                    // a cycle of copies can only be in code that can't be reached, the limit stops it
+                    Some(next_value) if steps < replacements.len() => {
+                        value = next_value.clone();
+                        steps += 1;
+                    }
+                    _ => break,
+                }
+            }
+            replacements.insert(reg, value);
+        }
+
+        for block in &mut function.blocks {
+            block.insts.retain(|inst| match inst.dest() {
+                Some(dest) => !replacements.contains_key(&dest),
+                None => true,
+            });
+        }
+        function.replace_uses(&replacements);
+        true
+    }
+}
+
+// an instruction that calculates the same thing as an instruction that always runs before it is replaced by a copy of that result
+pub struct CommonSubexpressionElimination;
+
+impl Pass for CommonSubexpressionElimination {
+    fn name(&self) -> &'static str {
+        "common subexpression elimination"
+    }
+
+    fn run(&self, function: &mut Function) -> bool {
+        let dominators = Dominators::new(function);
+        let reg_types = function.reg_types.clone();
+        let mut available: Vec<HashMap<String, VReg>> = vec!();
+        let mut changed = false;
+        // walks down the dominator tree, the results of the dominating blocks are available in the blocks below
+        let mut stack = vec!((BlockId(0), false));
+        while let Some((block_id, leaving)) = stack.pop() {
+            if leaving {
+                available.pop();
+                continue;
+            }
+            let mut block_available = HashMap::new();
+            for inst in &mut function.block_mut(block_id).insts {
+                let key = match expression_key(inst, &reg_types) {
+                    Some(key) => key,
+                    None => continue,
+                };
+                let dest = inst.dest().unwrap();
+                let existing = available.iter().rev().chain(std::iter::once(&block_available)).find_map(|table| table.get(&key)).copied();
+                match existing {
+                    Some(reg) => {
+                        *inst = Inst::Copy { dest, src: Operand::Reg(reg) };
+                        changed = true;
+                    }
+                    None => {
+                        block_available.insert(key, dest);
+                    }
+                }
+            }
+            available.push(block_available);
+            stack.push((block_id, true));
+            for child in dominators.children[block_id.0].iter().rev() {
+                stack.push((*child, false));
+            }
+        }
+        changed
+    }
+}
+
+// what the instruction calculates, the same key means the same result
+// only instructions without side effects have one, and the operands of commutative operations are sorted
+fn expression_key(inst: &Inst, reg_types: &[Type]) -> Option<String> {
+    match inst {
+        Inst::Binary { op, left, right, .. } => {
+            let (mut left, mut right) = (left.to_text(), right.to_text());
+            if matches!(op, BinaryOp::Add | BinaryOp::Mul | BinaryOp::Eq | BinaryOp::Ne) && left > right {
+                std::mem::swap(&mut left, &mut right);
+            }
+            Some(format!("{} {}, {}", op.to_str(), left, right))
+        }
+        Inst::Unary { op, src, .. } => Some(format!("{} {}", op.to_str(), src.to_text())),
+        // the same value can be converted to different types
+        Inst::Convert { dest, src } => Some(format!("convert {} to {}", src.to_text(), reg_types[dest.0].to_str())),
+        _ => None,
+    }
+}
+
+// instructions whose results are never read are removed, unless they do something else as well
+pub struct DeadCodeElimination;
+
+impl Pass for DeadCodeElimination {
+    fn name(&self) -> &'static str {
+        "dead code elimination"
+    }
+
+    fn run(&self, function: &mut Function) -> bool {
+        let mut changed = false;
+        loop {
+            let mut used: HashSet<VReg> = HashSet::new();
+            for block in &function.blocks {
+                let operands = block.insts.iter().flat_map(|inst| inst.operands()).chain(block.terminator.operands());
+                for operand in operands {
+                    if let Operand::Reg(reg) = operand {
+                        used.insert(*reg);
+                    }
+                }
+            }
+
+            let mut removed = false;
+            for block in &mut function.blocks {
+                let before = block.insts.len();
+                let reg_types = &function.reg_types;
+                block.insts.retain(|inst| match inst.dest() {
+                    Some(dest) => used.contains(&dest) || has_side_effects(inst, reg_types),
+                    None => true,
+                });
+                removed |= block.insts.len() != before;
+                // calls stay, but their result doesn't need a register
+                for inst in &mut block.insts {
+                    if let Inst::Call { dest, .. } = inst {
+                        if dest.is_some_and(|dest| !used.contains(&dest)) {
+                            *dest = None;
+                        }
+                    }
+                }
+            }
+            if !removed {
+                break;
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+// calls can print or exit, and an int division can fail at runtime (unless the divisor is a constant that can't make it fail,
+// only int has a division by -1 that fails)
+fn has_side_effects(inst: &Inst, reg_types: &[Type]) -> bool {
+    match inst {
+        Inst::Call { .. } => true,
+        Inst::Binary { op: BinaryOp::Div | BinaryOp::Rem, right, .. } => match right {
+            Operand::Const(Constant::Int(value, t)) => *value == 0 || (*value == -1 && *t == Type::Int),
+            Operand::Const(_) => false,
+            Operand::Reg(reg) => reg_types[reg.0].is_integer(),
+        },
+        _ => false,
+    }
+}
+
+// a block that is always jumped to from the same block is appended to that block,
+// and jumps to empty blocks go to where the empty block would jump to instead
+pub struct SimplifyControlFlow;
+
+impl Pass for SimplifyControlFlow {
+    fn name(&self) -> &'static str {
+        "control flow simplification"
+    }
+
+    fn run(&self, function: &mut Function) -> bool {
+        let mut changed = false;
+        while merge_block(function) || skip_empty_block(function) {
+            function.remove_unreachable_blocks();
+            changed = true;
+        }
+        changed
+    }
+}
+
+// merges one pair of blocks, returns whether there was one
+fn merge_block(function: &mut Function) -> bool {
+    let predecessors = function.predecessors();
+    for block_index in 0..function.blocks.len() {
+        let target = match function.blocks[block_index].terminator {
+            Terminator::Jump(target) => target,
+            _ => continue,
+        };
+        if target.0 == block_index || target.0 == 0 || predecessors[target.0].len() != 1 {
+            continue;
+        }
+        let block_id = BlockId(block_index);
+        // the target won't be reached anymore, so it only needs to be emptied
+        let target_block = std::mem::replace(function.block_mut(target), Block { insts: vec!(), terminator: Terminator::Unreachable });
+        for inst in target_block.insts {
+            // a phi with only one predecessor is just a copy
+            let inst = match inst {
+                Inst::Phi { dest, mut incoming } => Inst::Copy { dest, src: incoming.remove(0).1 },
+                inst => inst,
+            };
+            function.block_mut(block_id).insts.push(inst);
+        }
+        // the successors of the target now come from this block
+        for successor in target_block.terminator.successors() {
+            for inst in &mut function.block_mut(successor).insts {
+                if let Inst::Phi { incoming, .. } = inst {
+                    for (pred, _) in incoming.iter_mut() {
+                        if *pred == target {
+                            *pred = block_id;
+                        }
+                    }
+                }
+            }
+        }
+        function.block_mut(block_id).terminator = target_block.terminator;
+        return true;
+    }
+    false
+}
+
+// lets the predecessors of one empty block jump to its target directly, returns whether there was one
+// (not if the target has phis, they would need to know which of the predecessors control came from)
+fn skip_empty_block(function: &mut Function) -> bool {
+    for block_index in 1..function.blocks.len() {
+        let block = &function.blocks[block_index];
+        let target = match block.terminator {
+            Terminator::Jump(target) if block.insts.is_empty() && target.0 != block_index => target,
+            _ => continue,
+        };
+        if function.block(target).insts.iter().any(|inst| inst.is_phi()) {
+            continue;
+        }
+        let mut redirected = false;
+        for pred in &mut function.blocks {
+            for successor in pred.terminator.successors_mut() {
+                if successor.0 == block_index {
+                    *successor = target;
+                    redirected = true;
+                }
+            }
+        }
+        if redirected {
+            return true;
+        }
+    }
+    false
+}
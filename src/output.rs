@@ -1,22 +1,219 @@
-use colored::Colorize;
+use colored::{ColoredString, Colorize};
+
+use crate::span::Span;
 
 pub fn print_process(verb: &str, rest: &str) {
     println!("{} {}",
-        format!("{}", verb).blue().bold(),
+        verb.blue().bold(),
         rest
     );
 }
 
 pub fn print_debug(msg: &str) {
     println!("{} {}",
-        format!("Debug:").green().bold(),
-        format!("{}", msg).yellow(),
+        "Debug:".green().bold(),
+        msg.yellow(),
     );
 }
 
-pub fn print_error(msg: &str) {
-    println!("{} {}",
-        format!("Error:").red().bold(),
-        format!("{}", msg).bold(),
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Error,
+    // the program still compiles
+    Warning,
+}
+
+impl Level {
+    fn paint(self, s: &str) -> ColoredString {
+        match self {
+            Level::Error => s.red().bold(),
+            Level::Warning => s.yellow().bold(),
+        }
+    }
+
+    fn to_str(self) -> &'static str {
+        match self {
+            Level::Error => "Error",
+            Level::Warning => "Warning",
+        }
+    }
+}
+
+// a message attached to a part of the source code
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+// everything that's needed to report a problem with the source file to the user
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub level: Level,
+    // stable error code, see errors.rs
+    pub code: Option<&'static str>,
+    pub message: String,
+    // the primary label (the code that caused the problem), rendered with ^^^
+    pub primary: Option<Label>,
+    // secondary labels (e.g. "variable defined here"), rendered with ---
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String) -> Diagnostic {
+        Diagnostic::new(Level::Error, message)
+    }
+
+    fn new(level: Level, message: String) -> Diagnostic {
+        Diagnostic {
+            level,
+            code: None,
+            message,
+            primary: None,
+            secondary: vec!(),
+            notes: vec!(),
+            help: vec!(),
+        }
+    }
+
+    pub fn with_level(mut self, level: Level) -> Diagnostic {
+        self.level = level;
+        self
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Diagnostic {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_span(mut self, span: Span, label: &str) -> Diagnostic {
+        self.primary = Some(Label { span, message: label.to_string() });
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, label: &str) -> Diagnostic {
+        self.secondary.push(Label { span, message: label.to_string() });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: &str) -> Diagnostic {
+        self.help.push(help.to_string());
+        self
+    }
+}
+
+// the source file the diagnostics point into
+pub struct SourceFile<'a> {
+    pub name: &'a str,
+    pub text: &'a str,
+}
+
+impl<'a> SourceFile<'a> {
+    pub fn new(name: &'a str, text: &'a str) -> SourceFile<'a> {
+        SourceFile { name, text }
+    }
+
+    // the text of the given line (starting at 1), without the line break
+    fn line(&self, line: usize) -> &'a str {
+        self.text.lines().nth(line - 1).unwrap_or("")
+    }
+}
+
+pub fn print_diagnostic(diagnostic: &Diagnostic, source: &SourceFile) {
+    print!("{}", render_diagnostic(diagnostic, source));
+}
+
+// for "hj run", where stdout belongs to the program
+pub fn eprint_diagnostic(diagnostic: &Diagnostic, source: &SourceFile) {
+    eprint!("{}", render_diagnostic(diagnostic, source));
+}
+
+pub fn render_diagnostic(diagnostic: &Diagnostic, source: &SourceFile) -> String {
+    let level = diagnostic.level;
+    let header = match diagnostic.code {
+        Some(code) => format!("{}[{}]:", level.to_str(), code),
+        None => format!("{}:", level.to_str()),
+    };
+    let mut s = format!("{} {}\n",
+        level.paint(&header),
+        diagnostic.message.bold(),
     );
+
+    // all labels sorted by where they appear, so the snippet reads from top to bottom
+    let mut labels: Vec<(&Label, bool)> = vec!();
+    if let Some(primary) = &diagnostic.primary {
+        labels.push((primary, true));
+    }
+    labels.extend(diagnostic.secondary.iter().map(|label| (label, false)));
+    labels.sort_by_key(|(label, _)| (label.span.line, label.span.column));
+
+    let max_line = labels.iter().map(|(label, _)| label.span.line).max().unwrap_or(0);
+    let gutter_width = max_line.to_string().len();
+    let gutter = " ".repeat(gutter_width);
+    let bar = "|".blue().bold();
+
+    match &diagnostic.primary {
+        Some(primary) => {
+            s += &format!("{}{} {}:{}:{}\n", gutter, "-->".blue().bold(), source.name, primary.span.line, primary.span.column);
+        }
+        None => {
+            if !labels.is_empty() || !diagnostic.notes.is_empty() || !diagnostic.help.is_empty() {
+                s += &format!("{}{} {}\n", gutter, "-->".blue().bold(), source.name);
+            }
+        }
+    }
+
+    if !labels.is_empty() {
+        s += &format!("{} {}\n", gutter, bar);
+        let mut last_line = 0;
+        for (label, is_primary) in &labels {
+            let line = label.span.line;
+            if line != last_line {
+                if last_line != 0 && line > last_line + 1 {
+                    s += &format!("{}\n", "...".blue().bold());
+                }
+                let line_str = format!("{:>width$}", line, width = gutter_width);
+                s += &format!("{} {} {}\n", line_str.blue().bold(), bar, source.line(line));
+                last_line = line;
+            }
+
+            // underline the label, but never past the end of its first line
+            let text = source.line(line);
+            let start = label.span.column - 1;
+            let line_len = text.chars().count();
+            let len = source.text.get(label.span.start..label.span.end)
+                .map(|spanned| spanned.chars().take_while(|c| *c != '\n').count())
+                .unwrap_or(1)
+                .min(line_len.saturating_sub(start))
+                .max(1);
+            // tabs in front of the label are kept so the markers line up with the code
+            let padding: String = text.chars().take(start).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            let marker = if *is_primary {
+                level.paint(format!("{} {}", "^".repeat(len), label.message).trim_end())
+            } else {
+                format!("{} {}", "-".repeat(len), label.message).trim_end().blue().bold()
+            };
+            s += &format!("{} {} {}{}\n", gutter, bar, padding, marker);
+        }
+        s += &format!("{} {}\n", gutter, bar);
+    }
+
+    for note in &diagnostic.notes {
+        s += &format!("{} {} {}\n", gutter, "= note:".bold(), note);
+    }
+    for help in &diagnostic.help {
+        s += &format!("{} {} {}\n", gutter, "= help:".bold(), help);
+    }
+    if let Some(code) = diagnostic.code {
+        s += &format!("{} {} run \"hj --explain {}\" for more information\n", gutter, "= note:".bold(), code);
+    }
+    s
 }
@@ -1,30 +1,46 @@
-use crate::lexer::{Token, TokenType};
+use crate::lexer::{split_int_suffix, unescape, Token, TokenType};
 
 use crate::nodes::*;
+use crate::errors::ParseError;
+use crate::integer;
+use crate::span::Span;
 
 pub struct Parser {
     tokens: Vec<Token>,
     idx: usize,
+    // how many errors are collected before giving up (0 means no limit)
+    error_limit: usize,
+    errors: Vec<ParseError>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser { tokens, idx: 0 }
+    pub fn new(tokens: Vec<Token>, error_limit: usize) -> Parser {
+        Parser { tokens, idx: 0, error_limit, errors: vec!() }
     }
 
     fn next(&mut self) -> Option<&Token> {
         let token = self.tokens.get(self.idx);
         self.idx += 1;
-        return token;
+        token
     }
 
-    fn next_or_err(&mut self, msg: &str) -> Result<&Token, String> {
+    // span of the token that was consumed last
+    fn prev_span(&self) -> Span {
+        self.tokens[self.idx - 1].span
+    }
+
+    // span from the start of the given span to the end of the token that was consumed last
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.prev_span())
+    }
+
+    fn next_or_err(&mut self, context: &'static str, expected: &'static str) -> Result<&Token, ParseError> {
         match self.tokens.get(self.idx) {
             Some(token) => {
                 self.idx += 1;
                 Ok(token)
             }
-            None => Err(String::from(msg))
+            None => Err(self.eof_error(context, expected))
         }
     }
 
@@ -32,76 +48,97 @@ impl Parser {
         self.tokens.get(self.idx + relative_idx)
     }
 
-    fn get_or_err(&self, relative_idx: usize, msg: &str) -> Result<&Token, String> {
+    fn get_or_err(&self, relative_idx: usize, context: &'static str, expected: &'static str) -> Result<&Token, ParseError> {
         match self.tokens.get(self.idx + relative_idx) {
             Some(token) => Ok(token),
-            None => Err(String::from(msg))
+            None => Err(self.eof_error(context, expected))
         }
     }
 
-    fn parse_single_value(&mut self) -> Result<ExpressionNode, String> {
-        let next_token = self.next_or_err("Unexpected EOF when trying to parse expression (missing value)")?;
+    fn eof_error(&self, context: &'static str, expected: &'static str) -> ParseError {
+        // point right behind the last token
+        let span = self.tokens.last().map(|last| {
+            let column = last.span.column + last.value.chars().count();
+            Span::new(last.span.end, last.span.end, last.span.line, column)
+        });
+        ParseError::UnexpectedEof { span, context, expected }
+    }
+
+    fn unexpected(token: &Token, context: &'static str, expected: &'static str) -> ParseError {
+        ParseError::UnexpectedToken { span: token.span, found: token.value.clone(), context, expected }
+    }
+
+    fn parse_single_value(&mut self) -> Result<ExpressionNode, ParseError> {
+        // the lowest value of a signed type is only in range when it's negated, so the minus becomes part of the literal
+        if let (Some(minus), Some(literal)) = (self.get(0), self.get(1)) {
+            if minus.value == "-" && matches!(literal.kind, TokenType::NumberLiteral) {
+                if let Some((value, suffix)) = lowest_int(&literal.value) {
+                    let span = minus.span.to(literal.span);
+                    self.idx += 2;
+                    return Ok(ExpressionNode::IntLiteralNode(IntLiteralNode { value, suffix, span }));
+                }
+            }
+        }
+        let next_token = self.next_or_err("while parsing expression", "value")?;
+        let span = next_token.span;
         match next_token.kind {
             // parenthesis -> nested
             TokenType::OpenParen => {
-                let value = self.parse_expression()?;
+                let mut value = self.parse_expression()?;
                 // safe because otherwise parse_expression would've thrown an error
                 let next = self.next().unwrap();
                 match next.kind {
                     TokenType::CloseParen => {
+                        // the parentheses are part of the span, so (a + b) * c covers the closing parenthesis as well
+                        *value.span_mut() = self.span_from(span);
                         Ok(value)
                     }
                     _ => {
-                        Err(format!("Unexpected token \"{}\" after expression (expected closing parenthesis)", next.value))
+                        Err(Parser::unexpected(next, "while parsing expression", "closing parenthesis"))
                     }
                 }
             }
 
-            // operator -> unary operator (either + or -)
+            // operator -> unary operator (+, - or !)
             TokenType::Operator => {
                 let operator = Operator::from(next_token.value.as_str());
                 match operator {
-                    Operator::Plus | Operator::Minus => {
-                        let value = self.parse_single_value()?;
+                    Operator::Plus | Operator::Minus | Operator::Not => {
+                        // prefix operators bind more tightly than any binary operator (-a * b is (-a) * b)
+                        let value = self.parse_expression_bp(Operator::PREFIX_BINDING_POWER)?;
                         let node = UnaryOperationNode {
                             operator,
                             expression: Box::new(TExpressionNode{node: value, t: None}),
+                            span: self.span_from(span),
                         };
                         Ok(ExpressionNode::UnaryOperationNode(node))
                     }
                     _ => {
-                        Err(format!("The operator \"{}\" can't be used as a unary operator (expected value before it)", next_token.value))
+                        Err(ParseError::InvalidUnaryOperator { span: next_token.span, operator: next_token.value.clone() })
                     }
                 }
             }
 
             // literals (nice and easy)
-            TokenType::NumberLiteral => {
-                if next_token.value.contains(".") {
-                    let node = FloatLiteralNode { value: next_token.value.parse::<f64>().unwrap() };
-                    Ok(ExpressionNode::FloatLiteralNode(node))
-                } else {
-                    let node = IntLiteralNode { value: next_token.value.parse::<i64>().unwrap() };
-                    Ok(ExpressionNode::IntLiteralNode(node))
-                }
-            }
+            TokenType::NumberLiteral => number_literal(next_token),
 
             TokenType::BoolLiteral => {
                 let mut value = false;
                 if next_token.value == "true" {
                     value = true; 
                 }
-                let node = BoolLiteralNode { value };
+                let node = BoolLiteralNode { value, span };
                 Ok(ExpressionNode::BoolLiteralNode(node))
             }
 
             TokenType::StringLiteral => {
-                let node = StringLiteralNode { value: next_token.value.clone().trim_matches('"').to_string() };
+                let node = StringLiteralNode { value: literal_value(&next_token.value), span };
                 Ok(ExpressionNode::StringLiteralNode(node))
             }
 
             TokenType::CharLiteral => {
-                let node = CharLiteralNode { value: next_token.value.chars().nth(1).unwrap() };
+                // the lexer made sure there is exactly one character
+                let node = CharLiteralNode { value: literal_value(&next_token.value).chars().next().unwrap(), span };
                 Ok(ExpressionNode::CharLiteralNode(node))
             }
             
@@ -109,7 +146,7 @@ impl Parser {
             TokenType::Name => {
                 // so we avoid borrowing errors in case it's a variable
                 let name = next_token.value.clone();
-                let second = self.get_or_err(0, "Unexpected EOF while parsing expression (missed a semicolon?)")?;
+                let second = self.get_or_err(0, "while parsing expression", "semicolon")?;
                 match second.kind {
                     // function call
                     TokenType::OpenParen => {
@@ -119,159 +156,182 @@ impl Parser {
                     }
                     // variable
                     _ => {
-                        let node = VariableNode { name };
+                        let node = VariableNode { name, span };
                         Ok(ExpressionNode::VariableNode(node))
                     }
                 }
             }
 
             _ => {
-                Err(format!("Unexpected token \"{}\" while parsing expression, expected value", next_token.value))
+                Err(Parser::unexpected(next_token, "while parsing expression", "value"))
             }
         }
     }
 
-    fn parse_binary_expression(&mut self, left_expr: ExpressionNode) -> Result<ExpressionNode, String> {
-        // we will assume the next token is an operator
-        let op_token = self.next().unwrap();
-        let op = Operator::from(op_token.value.as_str());
-        let mut right_expr = self.parse_single_value()?;
-        
-        loop {
-            let token_after = self.get_or_err(0, "Unexpected EOF when trying to parsing expression (missed a semicolon?)")?;
-            if let TokenType::Operator = token_after.kind {
-                let next_op = Operator::from(token_after.value.as_str());
-                if next_op.priority_score() > op.priority_score() {
-                    right_expr = self.parse_binary_expression(right_expr)?;
-                    continue;
+    // pratt parser: parses an expression, but only includes binary operators whose left binding power is at least min_bp
+    // (a higher binding power binds more tightly, see Operator::infix_binding_power)
+    fn parse_expression_bp(&mut self, min_bp: u32) -> Result<ExpressionNode, ParseError> {
+        // we need a basis node for the expression, so we parse the first token(s)
+        let mut left_expr = self.parse_single_value()?;
+
+        // reaching EOF is fine here, the caller of parse_expression deals with it
+        while let Some(op_token) = self.get(0) {
+            if let (TokenType::Keyword, "as") = (&op_token.kind, op_token.value.as_str()) {
+                if Operator::CAST_BINDING_POWER < min_bp {
+                    break;
                 }
+                left_expr = self.parse_cast(left_expr)?;
+                continue;
             }
-            break;
+            if !matches!(op_token.kind, TokenType::Operator) {
+                break;
+            }
+            let op = Operator::from(op_token.value.as_str());
+            let (left_bp, right_bp) = match op.infix_binding_power() {
+                Some(bp) => bp,
+                None => {return Err(Parser::unexpected(op_token, "while parsing expression", "binary operator"))}
+            };
+            if left_bp < min_bp {
+                break;
+            }
+            self.idx += 1;
+
+            let right_expr = self.parse_expression_bp(right_bp)?;
+            left_expr = ExpressionNode::BinaryOperationNode(BinaryOperationNode {
+                span: left_expr.span().to(right_expr.span()),
+                left_expr: Box::new(TExpressionNode { node: left_expr, t: None }),
+                operator: op,
+                right_expr: Box::new(TExpressionNode { node: right_expr, t: None }),
+            });
         }
-
-        let node = BinaryOperationNode {
-            left_expr: Box::new(TExpressionNode { node: left_expr, t: None }),
-            operator: op,
-            right_expr: Box::new(TExpressionNode { node: right_expr, t: None }),
-        };
-
-        Ok(ExpressionNode::BinaryOperationNode(node))
+        Ok(left_expr)
     }
 
-    fn parse_expression(&mut self) -> Result<ExpressionNode, String> {
-        // we need a basis node for the expression, so we parse the first token(s)
-        let mut current_expression = self.parse_single_value()?;
-        loop {
-            let next_token = self.next_or_err("Unexpected EOF when trying to parse expression (missed a semicolon?)")?;
-            match next_token.kind {
-                // stop tokens
-                TokenType::Comma | TokenType::CloseParen | TokenType::Semicolon => {
-                    // expression over, caller of expression function should deal with stop tokens
-                    self.idx -= 1;
-                    break;
-                },
-
-                TokenType::Operator => {
-                    // let the binary expression parsing handle it
-                    self.idx -= 1;
-                    current_expression = self.parse_binary_expression(current_expression)?; 
-                }
-
-
-                // part of the expression
-                _ => {return Err(format!("Unexpected token \"{}\" while parsing expression (forgot a semicolon?)", next_token.value))},
+    // a cast of the expression that was parsed so far, the "as" is the next token
+    fn parse_cast(&mut self, expression: ExpressionNode) -> Result<ExpressionNode, ParseError> {
+        self.idx += 1;
+        let target = self.next_or_err("while parsing cast", "type")?;
+        let target_span = target.span;
+        match target.kind {
+            TokenType::InbuiltType => {
+                let target = target.value.clone();
+                Ok(ExpressionNode::CastNode(CastNode {
+                    span: expression.span().to(target_span),
+                    expression: Box::new(TExpressionNode { node: expression, t: None }),
+                    target,
+                    target_span,
+                }))
             }
+            _ => Err(Parser::unexpected(target, "while parsing cast", "type")),
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<ExpressionNode, ParseError> {
+        let expression = self.parse_expression_bp(0)?;
+        let next_token = self.get_or_err(0, "while parsing expression", "semicolon")?;
+        match next_token.kind {
+            // stop tokens, the expression is over and the caller of parse_expression should deal with them
+            TokenType::Comma | TokenType::CloseParen | TokenType::Semicolon | TokenType::OpenBrace => Ok(expression),
+            _ => Err(Parser::unexpected(next_token, "while parsing expression", "operator or semicolon")),
         }
-        return Ok(current_expression);
     }
 
-    fn parse_variable_definition(&mut self) -> Result<VariableDefinitionNode, String> {
+    fn parse_variable_definition(&mut self) -> Result<VariableDefinitionNode, ParseError> {
         // we can assume the "let" is there because the method got called
-        self.idx += 1;
+        let start = self.next().unwrap().span;
 
         let mut vtype: Option<String> = None;
-        let mut first = self.next_or_err("Unexpected EOF when trying to parse a variable definition (expected variable name or type)")?;
+        let mut first = self.next_or_err("while parsing variable definition", "variable name or type")?;
         if let TokenType::InbuiltType = first.kind {
             vtype = Some(first.value.clone());
-            first = self.next_or_err("Unexpected EOF when trying to parse a variable definition (expected variable name)")?;
+            first = self.next_or_err("while parsing variable definition", "variable name")?;
         }
 
         let var_name = match first.kind {
             TokenType::Name => first.value.clone(),
-            _ => {return Err(format!("Unexpected token \"{}\" while parsing variable definition (expected variable name)", first.value))}
+            _ => {return Err(Parser::unexpected(first, "while parsing variable definition", "variable name"))}
         };
-        let var_node = VariableNode {name: var_name};
+        let var_node = VariableNode {name: var_name, span: first.span};
 
-        let assignment_operator = self.next_or_err("Unexpected EOF when trying to parse a variable definition (expected equal sign)")?;
+        let assignment_operator = self.next_or_err("while parsing variable definition", "equal sign")?;
+        let operator_span = assignment_operator.span;
         let assignment_operator = match assignment_operator.kind {
             TokenType::AssignmentOperator => assignment_operator.value.clone(),
-            _ => {return Err(format!("Unexpected token \"{}\" while parsing variable definition (expected variable name)", assignment_operator.value))}
+            _ => {return Err(Parser::unexpected(assignment_operator, "while parsing variable definition", "equal sign"))}
         };
 
         let expression = match assignment_operator.as_str() {
             "=" => self.parse_expression()?,
-            _ => {return Err(format!("Can't use special assignment operator \"{}\" for a variable definition", assignment_operator))}
+            _ => {
+                return Err(ParseError::SpecialAssignmentInDefinition { span: operator_span, operator: assignment_operator })
+            }
         };
 
-        let semicolon = self.next_or_err("Unexpected EOF when trying to parse a variable assignment (expected semicolon)")?;
+        let semicolon = self.next_or_err("while parsing variable definition", "semicolon")?;
 
         match semicolon.kind {
             TokenType::Semicolon => (),
-            _ => {return Err(format!("Unexpected token \"{}\" while parsing variable assignment (expected semicolon)", semicolon.value))}
+            _ => {return Err(Parser::unexpected(semicolon, "while parsing variable definition", "semicolon"))}
         }
 
-        return Ok(VariableDefinitionNode {vtype, variable: var_node, expression: Some(Box::new(TExpressionNode { node: expression, t: None }))})
+        Ok(VariableDefinitionNode {vtype, variable: var_node, expression: Some(Box::new(TExpressionNode { node: expression, t: None })), span: self.span_from(start)})
     }
 
-    fn parse_variable_assignment(&mut self) -> Result<VariableAssignmentNode, String> {
-        let var_name = self.next_or_err("Unexpected EOF when trying to parse a variable assignment (expected variable name)")?;
+    fn parse_variable_assignment(&mut self) -> Result<VariableAssignmentNode, ParseError> {
+        let var_name = self.next_or_err("while parsing variable assignment", "variable name")?;
+        let start = var_name.span;
         let var_name = match var_name.kind {
             TokenType::Name => var_name.value.clone(),
-            _ => {return Err(format!("Unexpected token \"{}\" while parsing variable assignment (expected variable name)", var_name.value))}
+            _ => {return Err(Parser::unexpected(var_name, "while parsing variable assignment", "variable name"))}
         };
-        let var_node = VariableNode {name: var_name.clone()};
+        let var_node = VariableNode {name: var_name.clone(), span: start};
 
-        let assignment_operator = self.next_or_err("Unexpected EOF when trying to parse a variable assignment (expected equal sign)")?;
+        let assignment_operator = self.next_or_err("while parsing variable assignment", "equal sign")?;
         let assignment_operator = match assignment_operator.kind {
             TokenType::AssignmentOperator => assignment_operator.value.clone(),
-            _ => {return Err(format!("Unexpected token \"{}\" while parsing variable assignment (expected variable name)", assignment_operator.value))}
+            _ => {return Err(Parser::unexpected(assignment_operator, "while parsing variable assignment", "equal sign"))}
         };
 
         let expression = match assignment_operator.as_str() {
             "=" => self.parse_expression()?,
             _ => {
-                let operator = assignment_operator.get(..1).unwrap();
+                let operator = assignment_operator.strip_suffix('=').unwrap();
                 let operator = Operator::from(operator);
+                let right_expr = self.parse_expression()?;
+                // the desugared operation covers the whole "x += ..." part
                 let op_node = BinaryOperationNode {
-                    left_expr: Box::new(TExpressionNode { node: ExpressionNode::VariableNode(VariableNode {name: var_name}), t: None }),
+                    span: start.to(right_expr.span()),
+                    left_expr: Box::new(TExpressionNode { node: ExpressionNode::VariableNode(VariableNode {name: var_name, span: start}), t: None }),
                     operator,
-                    right_expr: Box::new(TExpressionNode { node: self.parse_expression()?, t: None })
+                    right_expr: Box::new(TExpressionNode { node: right_expr, t: None })
                 };
                 ExpressionNode::BinaryOperationNode(op_node)
             }
         };
 
-        let semicolon = self.next_or_err("Unexpected EOF when trying to parse a variable assignment (expected semicolon)")?;
+        let semicolon = self.next_or_err("while parsing variable assignment", "semicolon")?;
 
         match semicolon.kind {
             TokenType::Semicolon => (),
-            _ => {return Err(format!("Unexpected token \"{}\" while parsing variable assignment (expected semicolon)", semicolon.value))}
+            _ => {return Err(Parser::unexpected(semicolon, "while parsing variable assignment", "semicolon"))}
         }
 
-        return Ok(VariableAssignmentNode {variable: var_node, expression: Box::new(TExpressionNode {node: expression, t: None})})
+        Ok(VariableAssignmentNode {variable: var_node, expression: Box::new(TExpressionNode {node: expression, t: None}), span: self.span_from(start)})
     }
 
-    fn parse_function_call(&mut self) -> Result<FunctionCallNode, String> {
+    fn parse_function_call(&mut self) -> Result<FunctionCallNode, ParseError> {
         // we can assume it's a function name because that's when this function gets called
-        let function_name = self.next().unwrap().value.clone();
+        let function_token = self.next().unwrap();
+        let start = function_token.span;
         let function_node = FunctionNode {
-            name: function_name,
+            name: function_token.value.clone(),
+            span: start,
         };
         // we can also assume that the opening parenthesis is there for the same reason
         self.idx += 1;
 
         let mut args: Vec<TExpressionNode> = vec!();
-        let next_token = self.get_or_err(0, "Unexpected EOF when trying to parse function call (expected closing parenthesis)")?;
+        let next_token = self.get_or_err(0, "while parsing function call", "closing parenthesis")?;
         match next_token.kind {
             TokenType::CloseParen => {
                 self.idx += 1;
@@ -280,64 +340,374 @@ impl Parser {
                 loop {
                     let expression = self.parse_expression()?;
                     args.push(TExpressionNode {node: expression, t: None});
-                    let next_token = self.next_or_err("Unexpected EOF when trying to parse function call (expected closing parenthesis)")?;
+                    let next_token = self.next_or_err("while parsing function call", "closing parenthesis")?;
                     match next_token.kind {
                         TokenType::CloseParen => {break;}
                         TokenType::Comma => (),
-                        _ => {return Err(format!("Unexpected token \"{}\" in function parameters", next_token.value))}
+                        _ => {return Err(Parser::unexpected(next_token, "while parsing function call", "comma or closing parenthesis"))}
                     }
                 }
             }
         }
 
-        let semicolon = self.next_or_err("Unexpected EOF when trying to parse function call (expected semicolon)")?;
+        Ok(FunctionCallNode {
+            function: function_node,
+            args,
+            span: self.span_from(start),
+        })
+    }
 
+    // a function call used as a command has to be followed by a semicolon (unlike one inside of an expression)
+    fn parse_function_call_command(&mut self) -> Result<FunctionCallNode, ParseError> {
+        let function_call_node = self.parse_function_call()?;
+        let semicolon = self.next_or_err("while parsing function call", "semicolon")?;
+        match semicolon.kind {
+            TokenType::Semicolon => Ok(function_call_node),
+            _ => Err(Parser::unexpected(semicolon, "while parsing function call", "semicolon"))
+        }
+    }
+
+    fn parse_function_definition(&mut self) -> Result<FunctionDefinitionNode, ParseError> {
+        let context = "while parsing function definition";
+        // we can assume the "fn" is there because the method got called
+        let start = self.next().unwrap().span;
+
+        let name_token = self.next_or_err(context, "function name")?;
+        let function = match name_token.kind {
+            TokenType::Name => FunctionNode { name: name_token.value.clone(), span: name_token.span },
+            _ => {return Err(Parser::unexpected(name_token, context, "function name"))}
+        };
+
+        let open_paren = self.next_or_err(context, "opening parenthesis")?;
+        match open_paren.kind {
+            TokenType::OpenParen => (),
+            _ => {return Err(Parser::unexpected(open_paren, context, "opening parenthesis"))}
+        }
+
+        let mut params = vec!();
+        if let TokenType::CloseParen = self.get_or_err(0, context, "closing parenthesis")?.kind {
+            self.idx += 1;
+        } else {
+            loop {
+                // every parameter is written as "type name"
+                let type_token = self.next_or_err(context, "parameter type")?;
+                let param_start = type_token.span;
+                let ptype = match type_token.kind {
+                    TokenType::InbuiltType => type_token.value.clone(),
+                    _ => {return Err(Parser::unexpected(type_token, context, "parameter type"))}
+                };
+                let name_token = self.next_or_err(context, "parameter name")?;
+                let variable = match name_token.kind {
+                    TokenType::Name => VariableNode { name: name_token.value.clone(), span: name_token.span },
+                    _ => {return Err(Parser::unexpected(name_token, context, "parameter name"))}
+                };
+                params.push(ParameterNode { ptype, variable, span: self.span_from(param_start) });
+
+                let next_token = self.next_or_err(context, "closing parenthesis")?;
+                match next_token.kind {
+                    TokenType::CloseParen => break,
+                    TokenType::Comma => (),
+                    _ => {return Err(Parser::unexpected(next_token, context, "comma or closing parenthesis"))}
+                }
+            }
+        }
+
+        let mut return_type = None;
+        if let TokenType::Arrow = self.get_or_err(0, context, "opening brace")?.kind {
+            self.idx += 1;
+            let type_token = self.next_or_err(context, "return type")?;
+            match type_token.kind {
+                TokenType::InbuiltType => return_type = Some(type_token.value.clone()),
+                _ => {return Err(Parser::unexpected(type_token, context, "return type"))}
+            }
+        }
+
+        let body = self.parse_scope(context)?;
+
+        Ok(FunctionDefinitionNode {
+            function,
+            params,
+            return_type,
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_return(&mut self) -> Result<ReturnNode, ParseError> {
+        // we can assume the "return" is there because the method got called
+        let start = self.next().unwrap().span;
+        let mut expression = None;
+        if !matches!(self.get_or_err(0, "while parsing return", "semicolon")?.kind, TokenType::Semicolon) {
+            expression = Some(Box::new(TExpressionNode { node: self.parse_expression()?, t: None }));
+        }
+        let semicolon = self.next_or_err("while parsing return", "semicolon")?;
         match semicolon.kind {
             TokenType::Semicolon => (),
-            _ => {return Err(format!("Unexpected token \"{}\" while parsing function call (expected semicolon)", semicolon.value))}
+            _ => {return Err(Parser::unexpected(semicolon, "while parsing return", "semicolon"))}
         }
+        Ok(ReturnNode { expression, span: self.span_from(start) })
+    }
 
-        Ok(FunctionCallNode {
-            function: function_node,
-            args,
+    // parses a block of commands in braces, errors inside of it are recorded and the block continues after them
+    fn parse_scope(&mut self, context: &'static str) -> Result<ScopeNode, ParseError> {
+        let open_brace = self.next_or_err(context, "opening brace")?;
+        let start = open_brace.span;
+        match open_brace.kind {
+            TokenType::OpenBrace => (),
+            _ => {return Err(Parser::unexpected(open_brace, context, "opening brace"))}
+        }
+
+        let errors_before = self.errors.len();
+        let mut commands = vec!();
+        loop {
+            if self.idx >= self.tokens.len() && self.errors.len() > errors_before {
+                // an error inside the block already went until the end of the file
+                break;
+            }
+            let next_token = self.get_or_err(0, context, "closing brace")?;
+            if let TokenType::CloseBrace = next_token.kind {
+                self.idx += 1;
+                break;
+            }
+            match self.parse_command() {
+                Ok(command) => commands.push(command),
+                Err(e) => {
+                    self.record_error(e);
+                    if self.error_limit_reached() {
+                        self.idx = self.tokens.len();
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(ScopeNode { commands, span: self.span_from(start) })
+    }
+
+    fn parse_if(&mut self) -> Result<IfNode, ParseError> {
+        // we can assume the "if" is there because the method got called
+        let start = self.next().unwrap().span;
+        let condition = self.parse_expression()?;
+        let body = self.parse_scope("while parsing if statement")?;
+
+        let mut else_body = None;
+        if let Some(token) = self.get(0) {
+            if let (TokenType::Keyword, "else") = (&token.kind, token.value.as_str()) {
+                self.idx += 1;
+                let next_token = self.get_or_err(0, "while parsing else branch", "opening brace or if")?;
+                if let (TokenType::Keyword, "if") = (&next_token.kind, next_token.value.as_str()) {
+                    let else_if_node = self.parse_if()?;
+                    else_body = Some(ScopeNode { span: else_if_node.span, commands: vec!(CommandNode::IfNode(else_if_node)) });
+                } else {
+                    else_body = Some(self.parse_scope("while parsing else branch")?);
+                }
+            }
+        }
+
+        Ok(IfNode {
+            condition: Box::new(TExpressionNode { node: condition, t: None }),
+            body,
+            else_body,
+            span: self.span_from(start),
         })
     }
 
-    fn parse_command(&mut self) -> Result<CommandNode, String> {
+    fn parse_while(&mut self) -> Result<WhileNode, ParseError> {
+        // we can assume the "while" is there because the method got called
+        let start = self.next().unwrap().span;
+        let condition = self.parse_expression()?;
+        let body = self.parse_scope("while parsing while loop")?;
+
+        Ok(WhileNode {
+            condition: Box::new(TExpressionNode { node: condition, t: None }),
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    // parses "break;" and "continue;" and returns the span of the whole command
+    fn parse_loop_control(&mut self, context: &'static str) -> Result<Span, ParseError> {
+        // we can assume the keyword is there because the method got called
+        let start = self.next().unwrap().span;
+        let semicolon = self.next_or_err(context, "semicolon")?;
+        match semicolon.kind {
+            TokenType::Semicolon => Ok(self.span_from(start)),
+            _ => Err(Parser::unexpected(semicolon, context, "semicolon"))
+        }
+    }
+
+    fn parse_command(&mut self) -> Result<CommandNode, ParseError> {
         // it's ok to unwrap since this function will only get called when there are tokens left
         let first = self.get(0).unwrap();
         match first.kind {
             TokenType::Keyword => {
-                if first.value == "let" {
-                    let definition_node = self.parse_variable_definition()?;
-                    Ok(CommandNode::VariableDefinitionNode(definition_node))
-                } else {
-                    Err(format!("Unexpected keyword \"{}\", expected a command (either a variable assignment or a function call)", first.value))
+                match first.value.as_str() {
+                    "let" => {
+                        let definition_node = self.parse_variable_definition()?;
+                        Ok(CommandNode::VariableDefinitionNode(definition_node))
+                    }
+                    "if" => {
+                        let if_node = self.parse_if()?;
+                        Ok(CommandNode::IfNode(if_node))
+                    }
+                    "while" => {
+                        let while_node = self.parse_while()?;
+                        Ok(CommandNode::WhileNode(while_node))
+                    }
+                    "break" => {
+                        let span = self.parse_loop_control("while parsing break")?;
+                        Ok(CommandNode::BreakNode(BreakNode { span }))
+                    }
+                    "continue" => {
+                        let span = self.parse_loop_control("while parsing continue")?;
+                        Ok(CommandNode::ContinueNode(ContinueNode { span }))
+                    }
+                    "fn" => {
+                        let function_definition_node = self.parse_function_definition()?;
+                        Ok(CommandNode::FunctionDefinitionNode(function_definition_node))
+                    }
+                    "return" => {
+                        let return_node = self.parse_return()?;
+                        Ok(CommandNode::ReturnNode(return_node))
+                    }
+                    _ => Err(Parser::unexpected(first, "while parsing command", "a command"))
                 }
             } 
             TokenType::Name => {
-                let second = self.get_or_err(1, "Unexpected EOF when trying to parse a command")?;
+                let second = self.get_or_err(1, "while parsing command", "opening parenthesis or assignment operator")?;
                 match second.kind {
                     TokenType::OpenParen => {
-                        let function_call_node = self.parse_function_call()?;
+                        let function_call_node = self.parse_function_call_command()?;
                         Ok(CommandNode::FunctionCallNode(function_call_node))
                     }
                     TokenType::AssignmentOperator => {
                         let assignment_node = self.parse_variable_assignment()?;
                         Ok(CommandNode::VariableAssignmentNode(assignment_node))
                     }
-                    _ => Err(format!("Unexpected token \"{}\" after custom name, expected a command (either a variable assignment or a function call)", second.value))
+                    _ => Err(Parser::unexpected(second, "while parsing command", "opening parenthesis or assignment operator"))
+                }
+            }
+            _ => Err(Parser::unexpected(first, "while parsing command", "a command"))
+        }
+    }
+
+    fn error_limit_reached(&self) -> bool {
+        self.error_limit != 0 && self.errors.len() >= self.error_limit
+    }
+
+    fn record_error(&mut self, error: ParseError) {
+        self.synchronize(&error);
+        // the lexer already reported the invalid token, so the statement is only skipped
+        let at_invalid_token = error.span().is_some_and(|span| self.tokens.iter().any(|token| matches!(token.kind, TokenType::Invalid) && token.span.start == span.start));
+        if !at_invalid_token {
+            self.errors.push(error);
+        }
+    }
+
+    // panic mode recovery: skip everything up to the next semicolon (which is skipped as well) or closing brace
+    // (which is left for the block it belongs to), starting at the token that caused the error
+    fn synchronize(&mut self, error: &ParseError) {
+        match error.span() {
+            Some(span) => {
+                self.idx = self.tokens.iter().position(|token| token.span.start >= span.start).unwrap_or(self.tokens.len());
+            }
+            // EOF, there is nothing left to parse
+            None => {
+                self.idx = self.tokens.len();
+            }
+        }
+        while let Some(token) = self.get(0) {
+            match token.kind {
+                TokenType::Semicolon => {
+                    self.idx += 1;
+                    break;
                 }
+                TokenType::CloseBrace => break,
+                _ => self.idx += 1,
             }
-            _ => Err(format!("Unexpected token \"{}\", expected a command (either a variable assignment or a function call)", first.value))
         }
     }
 
-    pub fn parse(&mut self) -> Result<ScopeNode, String> {
-        let mut scope_node = ScopeNode { commands: vec!() }; 
-        while self.idx < self.tokens.len() {
-            scope_node.commands.push(self.parse_command()?);
+    pub fn parse(&mut self) -> Result<ScopeNode, Vec<ParseError>> {
+        let mut commands = vec!();
+        while self.idx < self.tokens.len() && !self.error_limit_reached() {
+            match self.parse_command() {
+                Ok(command) => commands.push(command),
+                Err(e) => {
+                    self.record_error(e);
+                    // a closing brace can't close anything at the outermost level, so it gets skipped
+                    if let Some(Token { kind: TokenType::CloseBrace, .. }) = self.get(0) {
+                        self.idx += 1;
+                    }
+                }
+            }
         } 
-        Ok(scope_node)
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+        let span = match (commands.first(), commands.last()) {
+            (Some(first), Some(last)) => first.span().to(last.span()),
+            _ => Span::default(),
+        };
+        Ok(ScopeNode { commands, span })
+    }
+}
+
+// the decoded contents of a string or char literal token, the lexer already checked its escape sequences
+fn literal_value(token_value: &str) -> String {
+    let contents = &token_value[1..token_value.len() - 1];
+    unescape(contents).unwrap_or_else(|_| panic!("Internal compiler error (invalid escape sequence in {})", token_value))
+}
+
+fn is_float_literal(text: &str) -> bool {
+    !text.starts_with("0x") && split_int_suffix(text).1.is_none() && text.contains(['.', 'e', 'E'])
+}
+
+// the value of an int literal (decimal or with a 0x, 0b or 0o prefix) and its suffix, the value is None if it doesn't even
+// fit into 64 bits
+fn int_literal_value(text: &str) -> (Option<u64>, Option<Type>) {
+    let (text, suffix) = split_int_suffix(text);
+    let digits = text.replace('_', "");
+    let (digits, radix) = match digits.get(..2) {
+        Some("0x") => (&digits[2..], 16),
+        Some("0b") => (&digits[2..], 2),
+        Some("0o") => (&digits[2..], 8),
+        _ => (&digits[..], 10),
+    };
+    (u64::from_str_radix(digits, radix).ok(), suffix.map(Type::from))
+}
+
+// the lowest value of a signed type if the literal is its magnitude (like 9223372036854775808 or 128i8), which is only
+// a valid literal with a minus in front of it
+fn lowest_int(text: &str) -> Option<(i64, Option<Type>)> {
+    if is_float_literal(text) {
+        return None;
+    }
+    let (value, suffix) = int_literal_value(text);
+    let t = suffix.unwrap_or(Type::Int);
+    let lowest = integer::range(t).0;
+    if t.is_signed() && value.map(|value| value as i128) == Some(-lowest) {
+        Some((lowest as i64, suffix))
+    } else {
+        None
+    }
+}
+
+// the lexer already checked the syntax of the number, only its range is left
+fn number_literal(token: &Token) -> Result<ExpressionNode, ParseError> {
+    let span = token.span;
+    if is_float_literal(&token.value) {
+        let value = token.value.replace('_', "").parse::<f64>().unwrap();
+        if value.is_infinite() {
+            return Err(ParseError::NumberOutOfRange { span, found: token.value.clone(), t: Type::Float });
+        }
+        Ok(ExpressionNode::FloatLiteralNode(FloatLiteralNode { value, span }))
+    } else {
+        let (value, suffix) = int_literal_value(&token.value);
+        let t = suffix.unwrap_or(Type::Int);
+        match value {
+            // the value is stored in the 64 bit form of its type, which is the same as the u64 for every value in range
+            Some(value) if integer::fits(value as i128, t) => Ok(ExpressionNode::IntLiteralNode(IntLiteralNode { value: value as i64, suffix, span })),
+            _ => Err(ParseError::NumberOutOfRange { span, found: token.value.clone(), t }),
+        }
     }
 }
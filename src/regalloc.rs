@@ -0,0 +1,298 @@
+use crate::abi::{self, ArgLocation};
+use crate::analysis::Liveness;
+use crate::ir::*;
+use crate::nodes::Type;
+use crate::x86::{Reg, Xmm};
+
+// linear scan register allocation over lifetime intervals with holes ("Linear Scan Register Allocation" by Poletto and
+// Sarkar, with the intervals of "Optimized Interval Splitting in a Linear Scan Register Allocator" by Wimmer and Mössenböck)
+// the blocks are put in a line and every register of the IR gets the ranges of positions where its value is needed,
+// then the intervals get registers in the order they start, two intervals can share a register if their ranges don't overlap
+// if there aren't enough registers, the intervals that end last are spilled to a stack slot for their whole lifetime
+// (stack slots are shared the same way)
+// floats get SSE registers, everything else general purpose registers
+// calls may change the caller-saved registers (like in the System V ABI), so values that are needed after a call
+// only get callee-saved registers (which have to be saved by the function), there are no callee-saved SSE registers
+
+// where a register of the IR lives
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Location {
+    Reg(Reg),
+    Xmm(Xmm),
+    // a stack slot, the offset is relative to rbp
+    Stack(i32),
+    // registers that aren't used anywhere
+    Nowhere,
+}
+
+// rax, rdx, r11, xmm14 and xmm15 aren't handed out, the code generator needs them for divisions and for moving values around
+pub const CALLER_SAVED: [Reg; 6] = [Reg::Rcx, Reg::Rsi, Reg::Rdi, Reg::R8, Reg::R9, Reg::R10];
+pub const CALLEE_SAVED: [Reg; 5] = [Reg::Rbx, Reg::R12, Reg::R13, Reg::R14, Reg::R15];
+pub const XMM_REGISTERS: [Xmm; 14] = [
+    Xmm::Xmm0, Xmm::Xmm1, Xmm::Xmm2, Xmm::Xmm3, Xmm::Xmm4, Xmm::Xmm5, Xmm::Xmm6,
+    Xmm::Xmm7, Xmm::Xmm8, Xmm::Xmm9, Xmm::Xmm10, Xmm::Xmm11, Xmm::Xmm12, Xmm::Xmm13,
+];
+
+pub struct Allocation {
+    // locations[n] is where %n lives
+    pub locations: Vec<Location>,
+    // the order the blocks are written in
+    pub order: Vec<BlockId>,
+    // the callee-saved registers that got used, the function has to restore them before returning
+    pub callee_saved: Vec<Reg>,
+    pub slot_count: i32,
+}
+
+struct Interval {
+    reg: VReg,
+    // sorted, a value is written at the start of a range and read for the last time at its end
+    ranges: Vec<(usize, usize)>,
+    float: bool,
+    // whether there is a call while the value is needed
+    crosses_call: bool,
+    // the registers this one is copied from or to (or calculated from), giving them the same register saves moves
+    hints: Vec<VReg>,
+    // the registers the calling convention wants the value in (as an argument or a result)
+    preferred: Vec<Location>,
+}
+
+impl Interval {
+    fn start(&self) -> usize {
+        self.ranges[0].0
+    }
+
+    fn end(&self) -> usize {
+        self.ranges[self.ranges.len() - 1].1
+    }
+
+    // an instruction can write its result into the register of an operand it reads for the last time,
+    // so ranges that only touch don't overlap
+    fn overlaps(&self, other: &Interval) -> bool {
+        self.ranges.iter().any(|(start, end)| {
+            other.ranges.iter().any(|(other_start, other_end)| start < other_end && other_start < end)
+        })
+    }
+}
+
+// every register gets its own stack slot, which is what -O0 uses (the code is easy to follow in a debugger)
+pub fn allocate_naive(function: &Function) -> Allocation {
+    let locations = (0..function.reg_types.len()).map(|i| Location::Stack(-8 * (i as i32 + 1))).collect();
+    Allocation {
+        locations,
+        order: function.reverse_postorder(),
+        callee_saved: vec!(),
+        slot_count: function.reg_types.len() as i32,
+    }
+}
+
+pub fn allocate(function: &Function) -> Allocation {
+    let order = function.reverse_postorder();
+    let mut intervals = build_intervals(function, &order);
+    intervals.sort_by_key(|interval| interval.start());
+
+    let mut locations = vec!(Location::Nowhere; function.reg_types.len());
+    let mut callee_saved: Vec<Reg> = vec!();
+    let mut slot_count = 0;
+    // the intervals that got each register or stack slot so far
+    let mut assigned: Vec<(Location, Vec<usize>)> = vec!();
+
+    for (i, interval) in intervals.iter().enumerate() {
+        let allowed = allowed_locations(interval);
+        let hinted = interval.hints.iter()
+            .map(|hint| locations[hint.0])
+            .chain(interval.preferred.iter().copied())
+            .find(|location| allowed.contains(location) && conflicts(&assigned, &intervals, *location, i).is_empty());
+        let free = hinted.or_else(|| allowed.iter().copied().find(|location| conflicts(&assigned, &intervals, *location, i).is_empty()));
+
+        let location = match free {
+            Some(location) => location,
+            None => {
+                // the register whose intervals end last, they're spilled if they all end after this one
+                let victim = allowed.iter()
+                    .map(|location| (*location, conflicts(&assigned, &intervals, *location, i)))
+                    .max_by_key(|(_, others)| others.iter().map(|j| intervals[*j].end()).min())
+                    .filter(|(_, others)| others.iter().all(|j| intervals[*j].end() > interval.end()));
+                match victim {
+                    Some((location, others)) => {
+                        for (assigned_location, assigned_intervals) in assigned.iter_mut() {
+                            if *assigned_location == location {
+                                assigned_intervals.retain(|j| !others.contains(j));
+                            }
+                        }
+                        for j in others {
+                            let slot = stack_slot(&assigned, &intervals, &locations, j, &mut slot_count);
+                            assign(&mut assigned, &mut locations, &intervals, slot, j);
+                        }
+                        location
+                    }
+                    None => stack_slot(&assigned, &intervals, &locations, i, &mut slot_count),
+                }
+            }
+        };
+
+        if let Location::Reg(reg) = location {
+            if CALLEE_SAVED.contains(&reg) && !callee_saved.contains(&reg) {
+                callee_saved.push(reg);
+            }
+        }
+        assign(&mut assigned, &mut locations, &intervals, location, i);
+    }
+
+    Allocation { locations, order, callee_saved, slot_count }
+}
+
+// the intervals with the location that overlap the one with the index
+fn conflicts(assigned: &[(Location, Vec<usize>)], intervals: &[Interval], location: Location, index: usize) -> Vec<usize> {
+    match assigned.iter().find(|(assigned_location, _)| *assigned_location == location) {
+        Some((_, others)) => others.iter().copied().filter(|j| intervals[*j].overlaps(&intervals[index])).collect(),
+        None => vec!(),
+    }
+}
+
+fn assign(assigned: &mut Vec<(Location, Vec<usize>)>, locations: &mut [Location], intervals: &[Interval], location: Location, index: usize) {
+    locations[intervals[index].reg.0] = location;
+    match assigned.iter_mut().find(|(assigned_location, _)| *assigned_location == location) {
+        Some((_, assigned_intervals)) => assigned_intervals.push(index),
+        None => assigned.push((location, vec!(index))),
+    }
+}
+
+// stack slots are shared like registers, preferably with the values this one is copied from or to
+fn stack_slot(assigned: &[(Location, Vec<usize>)], intervals: &[Interval], locations: &[Location], index: usize, slot_count: &mut i32) -> Location {
+    let hinted = intervals[index].hints.iter().map(|hint| locations[hint.0]);
+    let slots = (1..=*slot_count).map(|slot| Location::Stack(-8 * slot));
+    let free = hinted.chain(slots)
+        .find(|location| matches!(location, Location::Stack(_)) && conflicts(assigned, intervals, *location, index).is_empty());
+    free.unwrap_or_else(|| {
+        *slot_count += 1;
+        Location::Stack(-8 * *slot_count)
+    })
+}
+
+// the registers an interval can get, in the order they're preferred
+// caller-saved registers come first, they don't need to be saved
+fn allowed_locations(interval: &Interval) -> Vec<Location> {
+    if interval.float {
+        if interval.crosses_call {
+            vec!()
+        } else {
+            XMM_REGISTERS.iter().map(|xmm| Location::Xmm(*xmm)).collect()
+        }
+    } else if interval.crosses_call {
+        CALLEE_SAVED.iter().map(|reg| Location::Reg(*reg)).collect()
+    } else {
+        CALLER_SAVED.iter().chain(CALLEE_SAVED.iter()).map(|reg| Location::Reg(*reg)).collect()
+    }
+}
+
+// position 0 is where the parameters arrive, then every block gets a start position and every instruction and the
+// terminator get one in order
+// the ranges are built backwards through each block: a value is needed from where it's written until it's read the last time
+fn build_intervals(function: &Function, order: &[BlockId]) -> Vec<Interval> {
+    let liveness = Liveness::new(function);
+    let reg_count = function.reg_types.len();
+    let mut ranges: Vec<Vec<(usize, usize)>> = vec!(vec!(); reg_count);
+    let mut hints: Vec<Vec<VReg>> = vec!(vec!(); reg_count);
+    let mut preferred: Vec<Vec<Location>> = vec!(vec!(); reg_count);
+    let mut call_positions = vec!();
+
+    // all parameters are there at the same time, even the ones that are never read
+    let param_types: Vec<Type> = function.params.iter().map(|param| function.reg_type(*param)).collect();
+    for (param, arg_location) in function.params.iter().zip(abi::classify(&param_types)) {
+        ranges[param.0].push((0, 1));
+        preferred[param.0].extend(arg_location_to_location(arg_location));
+    }
+
+    let mut block_start = 1;
+    for block_id in order {
+        let block = function.block(*block_id);
+        let terminator_position = block_start + block.insts.len() + 1;
+
+        // where the value that's currently being followed backwards is read for the last time
+        let mut last_reads: Vec<Option<usize>> = vec!(None; reg_count);
+        for reg in &liveness.live_out[block_id.0] {
+            last_reads[reg.0] = Some(terminator_position);
+        }
+        for operand in block.terminator.operands() {
+            if let Operand::Reg(reg) = operand {
+                last_reads[reg.0].get_or_insert(terminator_position);
+            }
+        }
+        if let Terminator::Return(Some(Operand::Reg(reg))) = &block.terminator {
+            if function.reg_type(*reg) == Type::Float {
+                preferred[reg.0].push(Location::Xmm(Xmm::Xmm0));
+            }
+        }
+        for (i, inst) in block.insts.iter().enumerate().rev() {
+            let position = block_start + i + 1;
+            if let Some(dest) = inst.dest() {
+                let end = last_reads[dest.0].take().unwrap_or(position);
+                ranges[dest.0].push((position, end));
+            }
+            for operand in inst.operands() {
+                if let Operand::Reg(reg) = operand {
+                    last_reads[reg.0].get_or_insert(position);
+                }
+            }
+            if let Inst::Call { dest, args, .. } = inst {
+                call_positions.push(position);
+                let arg_types: Vec<Type> = args.iter().map(|arg| function.operand_type(arg)).collect();
+                for (arg, arg_location) in args.iter().zip(abi::classify(&arg_types)) {
+                    if let Operand::Reg(reg) = arg {
+                        preferred[reg.0].extend(arg_location_to_location(arg_location));
+                    }
+                }
+                if let Some(dest) = dest {
+                    if function.reg_type(*dest) == Type::Float {
+                        preferred[dest.0].push(Location::Xmm(Xmm::Xmm0));
+                    }
+                }
+            }
+            match inst {
+                Inst::Copy { dest, src: Operand::Reg(src) } => {
+                    hints[dest.0].push(*src);
+                    hints[src.0].push(*dest);
+                }
+                // calculating in the register of the left operand saves moving it into the result first
+                Inst::Binary { dest, op, left: Operand::Reg(left), .. } if !op.is_comparison() => hints[dest.0].push(*left),
+                Inst::Unary { dest, src: Operand::Reg(src), .. } => hints[dest.0].push(*src),
+                _ => (),
+            }
+        }
+        // the values that are read before they're written in this block come from before it
+        for (reg, last_read) in last_reads.iter().enumerate() {
+            if let Some(end) = last_read {
+                ranges[reg].push((block_start, *end));
+            }
+        }
+
+        block_start = terminator_position + 1;
+    }
+
+    let mut intervals = vec!();
+    for (i, mut reg_ranges) in ranges.into_iter().enumerate() {
+        if reg_ranges.is_empty() {
+            continue;
+        }
+        reg_ranges.sort();
+        // a call at the start of a range writes the value, a call at the end reads it, only calls in between change registers
+        let crosses_call = call_positions.iter().any(|call| reg_ranges.iter().any(|(start, end)| start < call && call < end));
+        intervals.push(Interval {
+            reg: VReg(i),
+            ranges: reg_ranges,
+            float: function.reg_types[i] == Type::Float,
+            crosses_call,
+            hints: std::mem::take(&mut hints[i]),
+            preferred: std::mem::take(&mut preferred[i]),
+        });
+    }
+    intervals
+}
+
+fn arg_location_to_location(arg_location: ArgLocation) -> Option<Location> {
+    match arg_location {
+        ArgLocation::Reg(reg) => Some(Location::Reg(reg)),
+        ArgLocation::Xmm(xmm) => Some(Location::Xmm(xmm)),
+        ArgLocation::Stack(_) => None,
+    }
+}
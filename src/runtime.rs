@@ -0,0 +1,364 @@
+use crate::x86::{Cond, Inst, Operand, Reg, Xmm};
+
+// the routines every compiled program is linked with, they only use linux syscalls (no libc)
+// all of them take their argument in rdi and may change rax, rcx, rdx, rsi, rdi, r8-r11 and xmm0-xmm15
+// (like functions in the System V ABI)
+// the text print writes is part of the language, every way of running a program has to produce exactly the same bytes
+
+pub const PRINT_INT: &str = "hj_print_int";
+// the narrower int types are passed extended to 64 bits, so only uint needs its own routine
+pub const PRINT_UINT: &str = "hj_print_uint";
+// takes the bits of the float
+pub const PRINT_FLOAT: &str = "hj_print_float";
+pub const PRINT_BOOL: &str = "hj_print_bool";
+// takes the code point of the char
+pub const PRINT_CHAR: &str = "hj_print_char";
+// takes a pointer to the string, which is stored as its length (8 bytes) followed by the UTF-8 bytes
+pub const PRINT_STR: &str = "hj_print_str";
+pub const EXIT: &str = "hj_exit";
+
+// write(1, rsi, rdx)
+const WRITE: &str = "hj_write";
+const WRITE_NEWLINE: &str = "hj_write_newline";
+// writes the unsigned int in rdi in decimal, with at least as many digits as given in rsi (padded with zeros)
+const WRITE_UINT: &str = "hj_write_uint";
+
+const RAX: Operand = Operand::Reg(Reg::Rax);
+const RCX: Operand = Operand::Reg(Reg::Rcx);
+const RDX: Operand = Operand::Reg(Reg::Rdx);
+const RSI: Operand = Operand::Reg(Reg::Rsi);
+const RDI: Operand = Operand::Reg(Reg::Rdi);
+const RBX: Operand = Operand::Reg(Reg::Rbx);
+const RBP: Operand = Operand::Reg(Reg::Rbp);
+const RSP: Operand = Operand::Reg(Reg::Rsp);
+const R8: Operand = Operand::Reg(Reg::R8);
+const R12: Operand = Operand::Reg(Reg::R12);
+
+// the constant strings the routines write
+pub fn data() -> Vec<(String, Vec<u8>)> {
+    vec!(
+        ("hj_str_newline".to_string(), b"\n".to_vec()),
+        ("hj_str_minus".to_string(), b"-".to_vec()),
+        ("hj_str_dot".to_string(), b".".to_vec()),
+        ("hj_str_e".to_string(), b"e".to_vec()),
+        ("hj_str_true".to_string(), b"true\n".to_vec()),
+        ("hj_str_false".to_string(), b"false\n".to_vec()),
+        ("hj_str_nan".to_string(), b"NaN".to_vec()),
+        ("hj_str_inf".to_string(), b"inf".to_vec()),
+    )
+}
+
+pub fn routines() -> Vec<Inst> {
+    let mut text = vec!();
+    text.append(&mut write());
+    text.append(&mut write_uint());
+    text.append(&mut print_int());
+    text.append(&mut print_float());
+    text.append(&mut print_bool());
+    text.append(&mut print_char());
+    text.append(&mut print_str());
+    text.append(&mut exit());
+    text
+}
+
+fn label(name: &str) -> Inst {
+    Inst::Label(name.to_string())
+}
+
+// writes a constant string from the data section
+fn write_constant(text: &mut Vec<Inst>, name: &str, len: i32) {
+    text.push(Inst::LeaData(Reg::Rsi, name.to_string()));
+    text.push(Inst::Mov(RDX, Operand::Imm(len)));
+    text.push(Inst::Call(WRITE.to_string()));
+}
+
+fn load_float(xmm: Xmm, value: f64) -> Vec<Inst> {
+    vec!(Inst::MovAbs(Reg::Rax, value.to_bits() as i64), Inst::MovqToXmm(xmm, Reg::Rax))
+}
+
+fn write() -> Vec<Inst> {
+    let mut text = vec!(
+        label(WRITE),
+        Inst::Mov(RAX, Operand::Imm(1)),
+        Inst::Mov(RDI, Operand::Imm(1)),
+        Inst::Syscall,
+        Inst::Ret,
+        label(WRITE_NEWLINE),
+    );
+    write_constant(&mut text, "hj_str_newline", 1);
+    text.push(Inst::Ret);
+    text
+}
+
+fn write_uint() -> Vec<Inst> {
+    let buffer_pos = Operand::Mem { base: Reg::Rsi, offset: 0 };
+    vec!(
+        label(WRITE_UINT),
+        Inst::Push(Reg::Rbp),
+        Inst::Mov(RBP, RSP),
+        Inst::Sub(RSP, Operand::Imm(32)),
+        Inst::Mov(RAX, RDI),
+        Inst::Mov(R8, RSI),
+        // the digits are written backwards, starting from the end of the buffer
+        Inst::Mov(RSI, RBP),
+        Inst::Mov(RCX, Operand::Imm(10)),
+        label(".Lwrite_uint_loop"),
+        Inst::Dec(RSI),
+        Inst::Xor(RDX, RDX),
+        Inst::Div(RCX),
+        Inst::Add(RDX, Operand::Imm('0' as i32)),
+        Inst::MovByte(buffer_pos, RDX),
+        Inst::Dec(R8),
+        Inst::Test(RAX, RAX),
+        Inst::Jcc(Cond::NotEqual, ".Lwrite_uint_loop".to_string()),
+        Inst::Test(R8, R8),
+        Inst::Jcc(Cond::Greater, ".Lwrite_uint_loop".to_string()),
+        Inst::Mov(RDX, RBP),
+        Inst::Sub(RDX, RSI),
+        Inst::Call(WRITE.to_string()),
+        Inst::Leave,
+        Inst::Ret,
+    )
+}
+
+// prints the int in decimal, followed by a line break (with a single write, because this is the most common print)
+// print_uint shares the code, it just never has a sign
+fn print_int() -> Vec<Inst> {
+    let buffer_pos = Operand::Mem { base: Reg::Rsi, offset: 0 };
+    vec!(
+        label(PRINT_UINT),
+        Inst::Push(Reg::Rbp),
+        Inst::Mov(RBP, RSP),
+        Inst::Sub(RSP, Operand::Imm(32)),
+        Inst::Lea(Reg::Rsi, Operand::Mem { base: Reg::Rbp, offset: -1 }),
+        Inst::MovByte(buffer_pos, Operand::Imm('\n' as i32)),
+        Inst::Mov(RAX, RDI),
+        Inst::Xor(RDI, RDI),
+        Inst::Jmp(".Lprint_int_digits".to_string()),
+        label(PRINT_INT),
+        Inst::Push(Reg::Rbp),
+        Inst::Mov(RBP, RSP),
+        Inst::Sub(RSP, Operand::Imm(32)),
+        Inst::Lea(Reg::Rsi, Operand::Mem { base: Reg::Rbp, offset: -1 }),
+        Inst::MovByte(buffer_pos, Operand::Imm('\n' as i32)),
+        // the absolute value is divided as an unsigned number, so the lowest int works as well
+        Inst::Mov(RAX, RDI),
+        Inst::Test(RAX, RAX),
+        Inst::Jcc(Cond::NotSign, ".Lprint_int_digits".to_string()),
+        Inst::Neg(RAX),
+        label(".Lprint_int_digits"),
+        Inst::Mov(RCX, Operand::Imm(10)),
+        label(".Lprint_int_loop"),
+        Inst::Dec(RSI),
+        Inst::Xor(RDX, RDX),
+        Inst::Div(RCX),
+        Inst::Add(RDX, Operand::Imm('0' as i32)),
+        Inst::MovByte(buffer_pos, RDX),
+        Inst::Test(RAX, RAX),
+        Inst::Jcc(Cond::NotEqual, ".Lprint_int_loop".to_string()),
+        Inst::Test(RDI, RDI),
+        Inst::Jcc(Cond::NotSign, ".Lprint_int_write".to_string()),
+        Inst::Dec(RSI),
+        Inst::MovByte(buffer_pos, Operand::Imm('-' as i32)),
+        label(".Lprint_int_write"),
+        Inst::Mov(RDX, RBP),
+        Inst::Sub(RDX, RSI),
+        Inst::Call(WRITE.to_string()),
+        Inst::Leave,
+        Inst::Ret,
+    )
+}
+
+// prints the float with 6 decimal places, floats from 1e12 on are written as a number from 1 to 10 and an exponent
+// (e.g. 1.500000e13), the value stays in rbx and the exponent in r12 across calls
+fn print_float() -> Vec<Inst> {
+    let mut text = vec!(
+        label(PRINT_FLOAT),
+        Inst::Push(Reg::Rbp),
+        Inst::Mov(RBP, RSP),
+        Inst::Push(Reg::Rbx),
+        Inst::Push(Reg::R12),
+        Inst::Mov(RBX, RDI),
+        Inst::MovqToXmm(Xmm::Xmm0, Reg::Rbx),
+        Inst::Ucomisd(Xmm::Xmm0, Xmm::Xmm0),
+        Inst::Jcc(Cond::Parity, ".Lprint_float_nan".to_string()),
+        // negative numbers: write the sign and continue with the absolute value
+        Inst::Xor(RAX, RAX),
+        Inst::MovqToXmm(Xmm::Xmm1, Reg::Rax),
+        Inst::Ucomisd(Xmm::Xmm1, Xmm::Xmm0),
+        Inst::Jcc(Cond::BelowEqual, ".Lprint_float_positive".to_string()),
+    );
+    write_constant(&mut text, "hj_str_minus", 1);
+    text.append(&mut vec!(
+        Inst::MovAbs(Reg::Rax, i64::MIN),
+        Inst::Xor(RBX, RAX),
+        label(".Lprint_float_positive"),
+        Inst::MovqToXmm(Xmm::Xmm0, Reg::Rbx),
+    ));
+    text.append(&mut load_float(Xmm::Xmm1, f64::INFINITY));
+    text.push(Inst::Ucomisd(Xmm::Xmm0, Xmm::Xmm1));
+    text.push(Inst::Jcc(Cond::Equal, ".Lprint_float_inf".to_string()));
+
+    // big numbers get divided by 10 until they are below 10
+    text.push(Inst::Xor(R12, R12));
+    text.append(&mut load_float(Xmm::Xmm1, 1e12));
+    text.push(Inst::Ucomisd(Xmm::Xmm0, Xmm::Xmm1));
+    text.push(Inst::Jcc(Cond::Below, ".Lprint_float_fixed".to_string()));
+    text.append(&mut load_float(Xmm::Xmm1, 10.0));
+    text.append(&mut vec!(
+        label(".Lprint_float_normalize"),
+        Inst::Ucomisd(Xmm::Xmm0, Xmm::Xmm1),
+        Inst::Jcc(Cond::Below, ".Lprint_float_fixed".to_string()),
+        Inst::Divsd(Xmm::Xmm0, Xmm::Xmm1),
+        Inst::Inc(R12),
+        Inst::Jmp(".Lprint_float_normalize".to_string()),
+        label(".Lprint_float_fixed"),
+    ));
+
+    // the number in millionths, rounded to the nearest (ties to even), split into the integer part and the decimals
+    text.append(&mut load_float(Xmm::Xmm1, 1e6));
+    text.append(&mut vec!(
+        Inst::Mulsd(Xmm::Xmm0, Xmm::Xmm1),
+        Inst::Cvtsd2si(Reg::Rax, Xmm::Xmm0),
+        Inst::Xor(RDX, RDX),
+        Inst::Mov(RCX, Operand::Imm(1000000)),
+        Inst::Div(RCX),
+        Inst::Mov(RBX, RDX),
+        Inst::Mov(RDI, RAX),
+        Inst::Mov(RSI, Operand::Imm(1)),
+        Inst::Call(WRITE_UINT.to_string()),
+    ));
+    write_constant(&mut text, "hj_str_dot", 1);
+    text.append(&mut vec!(
+        Inst::Mov(RDI, RBX),
+        Inst::Mov(RSI, Operand::Imm(6)),
+        Inst::Call(WRITE_UINT.to_string()),
+        Inst::Test(R12, R12),
+        Inst::Jcc(Cond::Equal, ".Lprint_float_end".to_string()),
+    ));
+    write_constant(&mut text, "hj_str_e", 1);
+    text.append(&mut vec!(
+        Inst::Mov(RDI, R12),
+        Inst::Mov(RSI, Operand::Imm(1)),
+        Inst::Call(WRITE_UINT.to_string()),
+        Inst::Jmp(".Lprint_float_end".to_string()),
+        label(".Lprint_float_nan"),
+    ));
+    write_constant(&mut text, "hj_str_nan", 3);
+    text.push(Inst::Jmp(".Lprint_float_end".to_string()));
+    text.push(label(".Lprint_float_inf"));
+    write_constant(&mut text, "hj_str_inf", 3);
+    text.append(&mut vec!(
+        label(".Lprint_float_end"),
+        Inst::Call(WRITE_NEWLINE.to_string()),
+        Inst::Pop(Reg::R12),
+        Inst::Pop(Reg::Rbx),
+        Inst::Pop(Reg::Rbp),
+        Inst::Ret,
+    ));
+    text
+}
+
+fn print_bool() -> Vec<Inst> {
+    let mut text = vec!(
+        label(PRINT_BOOL),
+        Inst::Test(RDI, RDI),
+        Inst::Jcc(Cond::Equal, ".Lprint_bool_false".to_string()),
+    );
+    write_constant(&mut text, "hj_str_true", 5);
+    text.push(Inst::Ret);
+    text.push(label(".Lprint_bool_false"));
+    write_constant(&mut text, "hj_str_false", 6);
+    text.push(Inst::Ret);
+    text
+}
+
+// stores the byte 0x80 | ((code point >> shift) & 0x3f) (a continuation byte in UTF-8) at the offset from rsi
+fn continuation_byte(shift: u8, offset: i32) -> Vec<Inst> {
+    vec!(
+        Inst::Mov(RAX, RDI),
+        Inst::Shr(RAX, shift),
+        Inst::And(RAX, Operand::Imm(0x3f)),
+        Inst::Or(RAX, Operand::Imm(0x80)),
+        Inst::MovByte(Operand::Mem { base: Reg::Rsi, offset }, RAX),
+    )
+}
+
+// the first byte of a UTF-8 sequence: prefix | (code point >> shift)
+fn leading_byte(prefix: i32, shift: u8) -> Vec<Inst> {
+    vec!(
+        Inst::Mov(RAX, RDI),
+        Inst::Shr(RAX, shift),
+        Inst::Or(RAX, Operand::Imm(prefix)),
+        Inst::MovByte(Operand::Mem { base: Reg::Rsi, offset: 0 }, RAX),
+    )
+}
+
+// prints the char encoded as UTF-8, followed by a line break
+fn print_char() -> Vec<Inst> {
+    let mut text = vec!(
+        label(PRINT_CHAR),
+        Inst::Push(Reg::Rbp),
+        Inst::Mov(RBP, RSP),
+        Inst::Sub(RSP, Operand::Imm(16)),
+        Inst::Lea(Reg::Rsi, Operand::Mem { base: Reg::Rbp, offset: -8 }),
+        Inst::Cmp(RDI, Operand::Imm(0x80)),
+        Inst::Jcc(Cond::AboveEqual, ".Lprint_char_2".to_string()),
+        Inst::MovByte(Operand::Mem { base: Reg::Rsi, offset: 0 }, RDI),
+        Inst::Mov(RDX, Operand::Imm(1)),
+        Inst::Jmp(".Lprint_char_write".to_string()),
+        label(".Lprint_char_2"),
+        Inst::Cmp(RDI, Operand::Imm(0x800)),
+        Inst::Jcc(Cond::AboveEqual, ".Lprint_char_3".to_string()),
+    );
+    text.append(&mut leading_byte(0xc0, 6));
+    text.append(&mut continuation_byte(0, 1));
+    text.push(Inst::Mov(RDX, Operand::Imm(2)));
+    text.push(Inst::Jmp(".Lprint_char_write".to_string()));
+    text.push(label(".Lprint_char_3"));
+    text.push(Inst::Cmp(RDI, Operand::Imm(0x10000)));
+    text.push(Inst::Jcc(Cond::AboveEqual, ".Lprint_char_4".to_string()));
+    text.append(&mut leading_byte(0xe0, 12));
+    text.append(&mut continuation_byte(6, 1));
+    text.append(&mut continuation_byte(0, 2));
+    text.push(Inst::Mov(RDX, Operand::Imm(3)));
+    text.push(Inst::Jmp(".Lprint_char_write".to_string()));
+    text.push(label(".Lprint_char_4"));
+    text.append(&mut leading_byte(0xf0, 18));
+    text.append(&mut continuation_byte(12, 1));
+    text.append(&mut continuation_byte(6, 2));
+    text.append(&mut continuation_byte(0, 3));
+    text.push(Inst::Mov(RDX, Operand::Imm(4)));
+    text.append(&mut vec!(
+        label(".Lprint_char_write"),
+        // the line break goes right after the char
+        Inst::Add(RDX, RSI),
+        Inst::MovByte(Operand::Mem { base: Reg::Rdx, offset: 0 }, Operand::Imm('\n' as i32)),
+        Inst::Sub(RDX, RSI),
+        Inst::Inc(RDX),
+        Inst::Call(WRITE.to_string()),
+        Inst::Leave,
+        Inst::Ret,
+    ));
+    text
+}
+
+fn print_str() -> Vec<Inst> {
+    vec!(
+        label(PRINT_STR),
+        Inst::Mov(RDX, Operand::Mem { base: Reg::Rdi, offset: 0 }),
+        Inst::Lea(Reg::Rsi, Operand::Mem { base: Reg::Rdi, offset: 8 }),
+        Inst::Call(WRITE.to_string()),
+        Inst::Call(WRITE_NEWLINE.to_string()),
+        Inst::Ret,
+    )
+}
+
+// exits with the status code in rdi (only the lowest 8 bits are seen by the parent process)
+fn exit() -> Vec<Inst> {
+    vec!(
+        label(EXIT),
+        Inst::Mov(RAX, Operand::Imm(60)),
+        Inst::Syscall,
+    )
+}
@@ -0,0 +1,28 @@
+// a range in the source file, used to point at the code a token or node came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,  // byte offset of the first character
+    pub end: usize,    // byte offset right after the last character
+    pub line: usize,   // line of the first character (starting at 1)
+    pub column: usize, // column of the first character (starting at 1, counted in characters)
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Span {
+        Span { start, end, line, column }
+    }
+
+    // the span covering both self and other (self is expected to come first)
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end.max(self.end),
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    pub fn debug_str(&self) -> String {
+        format!("{}:{}", self.line, self.column)
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::analysis::{Dominators, Liveness};
+use crate::ir::*;
+
+// conversion of a function into SSA form (every register is written exactly once) and back
+// registers that are written more than once (the ones of variables that get assigned) get a new register for each write,
+// and where different writes meet, a phi chooses between them
+// the phis are only placed where the register is still read later (pruned SSA), every register of the source is always
+// defined before it's read, so a phi never has to choose a value that doesn't exist
+
+pub fn construct(function: &mut Function) {
+    // parameters are written once when the function starts
+    let mut write_count: HashMap<VReg, usize> = function.params.iter().map(|param| (*param, 1)).collect();
+    let mut write_blocks: HashMap<VReg, Vec<BlockId>> = function.params.iter().map(|param| (*param, vec!(BlockId(0)))).collect();
+    for (i, block) in function.blocks.iter().enumerate() {
+        for dest in block.insts.iter().filter_map(|inst| inst.dest()) {
+            *write_count.entry(dest).or_insert(0) += 1;
+            write_blocks.entry(dest).or_default().push(BlockId(i));
+        }
+    }
+    let mut renamed: Vec<VReg> = write_count.iter().filter(|(_, count)| **count > 1).map(|(reg, _)| *reg).collect();
+    if renamed.is_empty() {
+        return;
+    }
+    // always the same order, so the numbers of the new registers don't depend on the hash map
+    renamed.sort();
+
+    let dominators = Dominators::new(function);
+    let liveness = Liveness::new(function);
+    let predecessors = function.predecessors();
+
+    // a phi is needed where the dominance of a write ends (and then where the dominance of that phi ends, and so on)
+    for reg in &renamed {
+        let mut has_phi: HashSet<BlockId> = HashSet::new();
+        let mut worklist = write_blocks[reg].clone();
+        while let Some(block) = worklist.pop() {
+            for frontier in &dominators.frontiers[block.0] {
+                if has_phi.contains(frontier) || !liveness.live_in[frontier.0].contains(reg) {
+                    continue;
+                }
+                has_phi.insert(*frontier);
+                // the incoming values get filled in when renaming the predecessors, until then they're the old register
+                let incoming = predecessors[frontier.0].iter().map(|pred| (*pred, Operand::Reg(*reg))).collect();
+                function.block_mut(*frontier).insts.insert(0, Inst::Phi { dest: *reg, incoming });
+                worklist.push(*frontier);
+            }
+        }
+    }
+
+    // the newest register for each renamed register, while walking down the dominator tree
+    let mut current: HashMap<VReg, Vec<VReg>> = renamed.iter().map(|reg| (*reg, vec!())).collect();
+    for param in &function.params {
+        if let Some(stack) = current.get_mut(param) {
+            stack.push(*param);
+        }
+    }
+    rename_block(function, &dominators, &mut current, BlockId(0));
+}
+
+fn rename_block(function: &mut Function, dominators: &Dominators, current: &mut HashMap<VReg, Vec<VReg>>, block_id: BlockId) {
+    // the registers written in this block, so their new names can be forgotten at the end
+    let mut written = vec!();
+
+    let mut block = std::mem::replace(function.block_mut(block_id), Block { insts: vec!(), terminator: Terminator::Unreachable });
+    for inst in &mut block.insts {
+        // the operands of phis get renamed in the predecessors
+        if !inst.is_phi() {
+            for operand in inst.operands_mut() {
+                rename_operand(current, operand);
+            }
+        }
+        if let Some(dest) = inst.dest_mut() {
+            if current.contains_key(dest) {
+                let new_reg = function.new_reg(function.reg_type(*dest));
+                current.get_mut(dest).unwrap().push(new_reg);
+                written.push(*dest);
+                *dest = new_reg;
+            }
+        }
+    }
+    for operand in block.terminator.operands_mut() {
+        rename_operand(current, operand);
+    }
+    let mut successors = block.terminator.successors();
+    successors.dedup();
+    *function.block_mut(block_id) = block;
+
+    // the values the phis in the successors get when control comes from this block
+    for successor in successors {
+        for inst in &mut function.block_mut(successor).insts {
+            if let Inst::Phi { incoming, .. } = inst {
+                for (pred, operand) in incoming.iter_mut() {
+                    if *pred == block_id {
+                        rename_operand(current, operand);
+                    }
+                }
+            }
+        }
+    }
+
+    for child in dominators.children[block_id.0].clone() {
+        rename_block(function, dominators, current, child);
+    }
+
+    for reg in written {
+        current.get_mut(&reg).unwrap().pop();
+    }
+}
+
+fn rename_operand(current: &HashMap<VReg, Vec<VReg>>, operand: &mut Operand) {
+    if let Operand::Reg(reg) = operand {
+        if let Some(stack) = current.get(reg) {
+            let newest = stack.last().expect("Internal compiler error (register read before it was written in SSA construction)");
+            *operand = Operand::Reg(*newest);
+        }
+    }
+}
+
+// turns the phis back into copies, so the backends don't need to know about them
+// every phi gets its own temporary register: the predecessors copy their value into it, and the phi becomes a copy from it
+// (copying into the destination directly in the predecessors wouldn't work if another phi of the block still needs its old value)
+// an edge from a block with several successors to a block with several predecessors gets a new block in between,
+// so the copies only happen on that edge
+pub fn destruct(function: &mut Function) {
+    let mut edge_blocks: HashMap<(BlockId, BlockId), BlockId> = HashMap::new();
+    for block_index in 0..function.blocks.len() {
+        let block_id = BlockId(block_index);
+        if !function.block(block_id).insts.iter().any(|inst| inst.is_phi()) {
+            continue;
+        }
+        let insts = std::mem::take(&mut function.block_mut(block_id).insts);
+        let mut new_insts = vec!();
+        // the block can be its own predecessor (a loop without a branch inside of it), so the copies are only added to the
+        // predecessors once the block has its instructions back
+        let mut copies = vec!();
+        for inst in insts {
+            let (dest, incoming) = match inst {
+                Inst::Phi { dest, incoming } => (dest, incoming),
+                _ => {
+                    new_insts.push(inst);
+                    continue;
+                }
+            };
+            let temporary = function.new_reg(function.reg_type(dest));
+            for (pred, value) in incoming {
+                let copy_block = if function.block(pred).terminator.successors().len() > 1 {
+                    *edge_blocks.entry((pred, block_id)).or_insert_with(|| split_edge(function, pred, block_id))
+                } else {
+                    pred
+                };
+                copies.push((copy_block, Inst::Copy { dest: temporary, src: value }));
+            }
+            new_insts.push(Inst::Copy { dest, src: Operand::Reg(temporary) });
+        }
+        function.block_mut(block_id).insts = new_insts;
+        for (copy_block, copy) in copies {
+            function.block_mut(copy_block).insts.push(copy);
+        }
+    }
+}
+
+// puts a new block on the edge from pred to successor and returns it
+fn split_edge(function: &mut Function, pred: BlockId, successor: BlockId) -> BlockId {
+    let new_block = function.new_block();
+    function.block_mut(new_block).terminator = Terminator::Jump(successor);
+    for target in function.block_mut(pred).terminator.successors_mut() {
+        if *target == successor {
+            *target = new_block;
+        }
+    }
+    new_block
+}
@@ -1,139 +1,595 @@
 use std::collections::HashMap;
 
 use crate::nodes::*;
+use crate::errors::TypeError;
+use crate::integer;
+use crate::span::Span;
 
+pub struct VariableEntry {
+    pub t: Type,
+    // where the variable got defined, so errors can point to the definition
+    pub span: Span,
+}
+
+// functions that exist without a definition, they can't be redefined
+const INBUILT_FUNCTIONS: [&str; 2] = ["print", "exit"];
+
+pub struct FunctionSignature {
+    pub params: Vec<Type>,
+    // where each parameter got declared, for errors about arguments
+    pub param_spans: Vec<Span>,
+    // None if the function doesn't return a value
+    pub return_type: Option<Type>,
+    // the name in the definition
+    pub span: Span,
+}
+
+// the function whose body is currently checked
+struct CurrentFunction {
+    name: String,
+    return_type: Option<Type>,
+}
+
+// one variable table for each scope the current command is nested in, the innermost scope is the last one
+// a "let" always defines a new variable in the innermost scope, even if the name already exists there (shadowing)
+// a shadowed variable can't be accessed anymore, but it keeps its type, so it can have a different type than the new one
 pub struct VariableTraverser {
-    // later there will be multiple variable tables for each scope or something like that
-    pub variable_table: HashMap<String, Type> 
+    scopes: Vec<HashMap<String, VariableEntry>>,
+    // how many variables got defined in total (including shadowed ones and function parameters)
+    pub variable_count: usize,
+    // all functions defined at the top level, collected before anything else is checked so they can be called before their definition
+    pub function_table: HashMap<String, FunctionSignature>,
+    // all errors found so far, checking goes on after an error
+    errors: Vec<TypeError>,
+    // problems that don't stop the compilation: ints that are converted to floats implicitly in binary operations
+    pub warnings: Vec<TypeError>,
+    // makes those conversions errors, so they have to be written with "as float"
+    pub strict_numeric: bool,
+    // how many loops the current command is nested in (break and continue need at least one)
+    loop_depth: usize,
+    // how many scopes the current command is nested in (the top level is 1)
+    scope_depth: usize,
+    current_function: Option<CurrentFunction>,
+}
+
+impl Default for VariableTraverser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl VariableTraverser {
     pub fn new() -> VariableTraverser {
         VariableTraverser {
-            variable_table: HashMap::new()
+            scopes: vec!(),
+            variable_count: 0,
+            function_table: HashMap::new(),
+            errors: vec!(),
+            warnings: vec!(),
+            strict_numeric: false,
+            loop_depth: 0,
+            scope_depth: 0,
+            current_function: None,
+        }
+    }
+
+    pub fn traverse(&mut self, scope_node: &mut ScopeNode) -> Result<(), Vec<TypeError>> {
+        self.collect_functions(scope_node);
+        self.check_scope(scope_node);
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    // fill the function table with the signatures of all top level functions
+    fn collect_functions(&mut self, scope_node: &ScopeNode) {
+        for command in &scope_node.commands {
+            if let CommandNode::FunctionDefinitionNode(func_def_node) = command {
+                let name = &func_def_node.function.name;
+                if INBUILT_FUNCTIONS.contains(&name.as_str()) {
+                    self.errors.push(TypeError::DuplicateFunction { span: func_def_node.function.span, name: name.clone(), previous_span: None });
+                    continue;
+                }
+                if let Some(previous) = self.function_table.get(name) {
+                    self.errors.push(TypeError::DuplicateFunction { span: func_def_node.function.span, name: name.clone(), previous_span: Some(previous.span) });
+                    continue;
+                }
+                let signature = FunctionSignature {
+                    params: func_def_node.params.iter().map(|param| Type::from(&param.ptype)).collect(),
+                    param_spans: func_def_node.params.iter().map(|param| param.span).collect(),
+                    return_type: func_def_node.return_type.as_deref().map(Type::from),
+                    span: func_def_node.function.span,
+                };
+                self.function_table.insert(name.clone(), signature);
+            }
         }
     }
 
-    pub fn traverse(&mut self, scope_node: &mut ScopeNode) -> Result<(), String> {
+    fn check_scope(&mut self, scope_node: &mut ScopeNode) {
+        self.scope_depth += 1;
+        self.scopes.push(HashMap::new());
         for command in &mut scope_node.commands {
-            match command {
-                CommandNode::VariableDefinitionNode(var_def_node) => {
-                    let vtype;
-                    // check for left type definition (e.g. let uint x = 'a'; => uint)
-                    if let Some(vtype_str) = &var_def_node.vtype  {
-                        vtype = Type::from(vtype_str); 
-                        // check for right type definition (e.g. let uint x = 'a'; => char) and see if they conflict
-                        if let Some(right_expr) = &mut var_def_node.expression {
-                            self.assign_expression_type(right_expr)?;
-                            let right_type = right_expr.t.as_ref().unwrap();
-                            if vtype != *right_type {
-                                return Err(format!("Mismatching variable types in variable definition: \"{}\" (left) and \"{}\" (right)", vtype_str, right_type.to_str()));
-                            }
+            self.check_command(command);
+        }
+        // everything defined in the scope is gone at its end
+        self.scopes.pop();
+        self.scope_depth -= 1;
+    }
+
+    // find the variable that a name refers to, which is the newest definition in the innermost scope that has one
+    fn lookup_variable(&self, name: &str) -> Option<&VariableEntry> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    // define a new variable in the innermost scope, shadowing any variable with the same name
+    fn define_variable(&mut self, name: &str, entry: VariableEntry) {
+        self.scopes.last_mut().expect("no scope to define a variable in").insert(name.to_string(), entry);
+        self.variable_count += 1;
+    }
+
+    // whether the scope can't be left without running into a return
+    fn always_returns(scope_node: &ScopeNode) -> bool {
+        scope_node.commands.iter().any(|command| match command {
+            CommandNode::ReturnNode(_) => true,
+            // an if statement only counts if both branches return
+            CommandNode::IfNode(if_node) => {
+                match &if_node.else_body {
+                    Some(else_body) => VariableTraverser::always_returns(&if_node.body) && VariableTraverser::always_returns(else_body),
+                    None => false,
+                }
+            }
+            _ => false,
+        })
+    }
+
+    // check the arguments of a function call and return the return type of the function
+    // the outer None means the function doesn't return a value, errors result in the error type
+    fn check_function_call(&mut self, func_call_node: &mut FunctionCallNode) -> Option<Type> {
+        // the arguments get checked in any case, so errors inside them are found as well
+        // (they can only be converted to the types of the parameters if the number of arguments is right)
+        let name = &func_call_node.function.name;
+        let arg_count = func_call_node.args.len();
+        let expected_types: Vec<Option<Type>> = match (name.as_str(), self.function_table.get(name)) {
+            ("exit", _) => vec!(Some(Type::Int); arg_count),
+            (_, Some(signature)) if signature.params.len() == arg_count => signature.params.iter().map(|t| Some(*t)).collect(),
+            _ => vec!(None; arg_count),
+        };
+        let arg_types: Vec<Type> = func_call_node.args.iter_mut().zip(expected_types).map(|(arg_expr, expected)| self.assign_expression_type(arg_expr, expected)).collect();
+
+        // print is inbuilt and accepts every type
+        if name == "print" {
+            if arg_types.len() != 1 {
+                self.errors.push(TypeError::WrongArgumentCount { span: func_call_node.span, function: name.clone(), expected: 1, found: arg_types.len() });
+            }
+            return None;
+        }
+        // exit is inbuilt as well and ends the program with the given status code
+        if name == "exit" {
+            if arg_types.len() != 1 {
+                self.errors.push(TypeError::WrongArgumentCount { span: func_call_node.span, function: name.clone(), expected: 1, found: arg_types.len() });
+            } else if !arg_types[0].is_error() && arg_types[0] != Type::Int {
+                self.errors.push(TypeError::MismatchedArgumentType { span: func_call_node.args[0].node.span(), function: name.clone(), parameter_span: None, expected: Type::Int, found: arg_types[0] });
+            }
+            return None;
+        }
+
+        let signature = match self.function_table.get(name) {
+            Some(signature) => signature,
+            None => {
+                self.errors.push(TypeError::UndefinedFunction { span: func_call_node.function.span, name: name.clone() });
+                return Some(Type::Error);
+            }
+        };
+
+        if signature.params.len() != arg_types.len() {
+            self.errors.push(TypeError::WrongArgumentCount { span: func_call_node.span, function: name.clone(), expected: signature.params.len(), found: arg_types.len() });
+            // the return type is still known, so the call itself doesn't need to be poisoned
+            return signature.return_type;
+        }
+
+        for (i, arg_type) in arg_types.iter().enumerate() {
+            let expected = signature.params[i];
+            if !arg_type.is_error() && !expected.is_error() && *arg_type != expected {
+                self.errors.push(TypeError::MismatchedArgumentType { span: func_call_node.args[i].node.span(), function: name.clone(), parameter_span: Some(signature.param_spans[i]), expected, found: *arg_type });
+            }
+        }
+        signature.return_type
+    }
+
+    fn check_function_definition(&mut self, func_def_node: &mut FunctionDefinitionNode) {
+        let name = func_def_node.function.name.clone();
+        if self.scope_depth > 1 {
+            self.errors.push(TypeError::NestedFunctionDefinition { span: func_def_node.function.span, name });
+            return;
+        }
+
+        // functions can't see the variables outside of them, so the body gets a fresh scope stack with only the parameters
+        // the parameters are in their own scope around the body, so the body can shadow them
+        let outer_scopes = std::mem::replace(&mut self.scopes, vec!(HashMap::new()));
+        for param in &func_def_node.params {
+            // the first parameter with the name is the one the body sees
+            if let Some(previous) = self.lookup_variable(&param.variable.name) {
+                self.errors.push(TypeError::DuplicateParameter { span: param.variable.span, function: name.clone(), name: param.variable.name.clone(), previous_span: previous.span });
+                continue;
+            }
+            self.define_variable(&param.variable.name, VariableEntry { t: Type::from(&param.ptype), span: param.variable.span });
+        }
+        let return_type = func_def_node.return_type.as_deref().map(Type::from);
+        self.current_function = Some(CurrentFunction { name: name.clone(), return_type });
+
+        self.check_scope(&mut func_def_node.body);
+
+        self.current_function = None;
+        self.scopes = outer_scopes;
+
+        if let Some(expected) = return_type {
+            if !VariableTraverser::always_returns(&func_def_node.body) {
+                self.errors.push(TypeError::MissingReturn { span: func_def_node.function.span, function: name, expected });
+            }
+        }
+    }
+
+    fn check_condition(&mut self, condition: &mut TExpressionNode, statement: &'static str) {
+        let condition_type = self.assign_expression_type(condition, None);
+        if !condition_type.is_error() && condition_type != Type::Bool {
+            self.errors.push(TypeError::NonBoolCondition { span: condition.node.span(), statement, found: condition_type });
+        }
+    }
+
+    fn check_command(&mut self, command: &mut CommandNode) {
+        match command {
+            CommandNode::VariableDefinitionNode(var_def_node) => {
+                let vtype;
+                // check for left type definition (e.g. let uint x = 'a'; => uint)
+                if let Some(vtype_str) = &var_def_node.vtype  {
+                    vtype = Type::from(vtype_str);
+                    // check for right type definition (e.g. let uint x = 'a'; => char) and see if they conflict
+                    if let Some(right_expr) = &mut var_def_node.expression {
+                        let right_type = self.assign_expression_type(right_expr, Some(vtype));
+                        if !right_type.is_error() && vtype != right_type {
+                            self.errors.push(TypeError::MismatchedDefinitionType { span: right_expr.node.span(), declared_span: var_def_node.variable.span, declared: vtype, found: right_type });
                         }
+                    }
+                } else {
+                    // check for right type, if the left type is not there, it has to be there
+                    if let Some(right_expr) = &mut var_def_node.expression {
+                        // if the expression is broken, the variable gets the error type, so using it doesn't cause more errors
+                        vtype = self.assign_expression_type(right_expr, None);
                     } else {
-                        // check for right type, if the left type is not there, it has to be there
-                        if let Some(right_expr) = &mut var_def_node.expression {
-                            self.assign_expression_type(right_expr)?;
-                            vtype = right_expr.t.as_ref().unwrap().clone();
-                        } else {
-                            return Err(format!("Undefined type for variable definition!"));
-                        }
+                        self.errors.push(TypeError::UninferableType { span: var_def_node.variable.span });
+                        vtype = Type::Error;
                     }
-                    self.variable_table.insert(var_def_node.variable.name.clone(), vtype);
-                } 
-                
-                CommandNode::VariableAssignmentNode(var_assign_node) => {
-                    // test if the variable even exists
-                    let vtype = match self.variable_table.get(&var_assign_node.variable.name) {
-                        Some(t) => t,
-                        None => { return Err(format!("Assigning to undefined variable \"{}\"", &var_assign_node.variable.name)); }
-                    };
-
-                    let right_expr = var_assign_node.expression.as_mut();
-                    self.assign_expression_type(right_expr)?;
-                    // check if the types match
-                    let right_type = right_expr.t.as_ref().unwrap();
-                    if *vtype != *right_type {
-                        return Err(format!("Cannot assign expression of type \"{}\" to variable of type \"{}\"", right_type.to_str(), vtype.to_str()));
+                }
+                // the expression is checked first, so "let x = x + 1;" still refers to the previous x
+                self.define_variable(&var_def_node.variable.name, VariableEntry { t: vtype, span: var_def_node.variable.span });
+            }
+
+            CommandNode::VariableAssignmentNode(var_assign_node) => {
+                let right_expr = var_assign_node.expression.as_mut();
+                let expected = self.lookup_variable(&var_assign_node.variable.name).map(|entry| entry.t);
+                let right_type = self.assign_expression_type(right_expr, expected);
+
+                // test if the variable even exists (an assignment always refers to the visible variable with the name)
+                let entry = match self.lookup_variable(&var_assign_node.variable.name) {
+                    Some(entry) => entry,
+                    None => {
+                        self.errors.push(TypeError::AssignmentToUndefined { span: var_assign_node.variable.span, name: var_assign_node.variable.name.clone() });
+                        return;
                     }
+                };
+                let vtype = entry.t;
+                let definition_span = entry.span;
+
+                // check if the types match
+                if !vtype.is_error() && !right_type.is_error() && vtype != right_type {
+                    self.errors.push(TypeError::MismatchedAssignmentType { span: right_expr.node.span(), definition_span, expected: vtype, found: right_type });
+                }
+            }
+
+            CommandNode::FunctionCallNode(func_call_node) => {
+                // a returned value is simply thrown away
+                self.check_function_call(func_call_node);
+            }
+
+            CommandNode::IfNode(if_node) => {
+                self.check_condition(&mut if_node.condition, "an if statement");
+                self.check_scope(&mut if_node.body);
+                if let Some(else_body) = &mut if_node.else_body {
+                    self.check_scope(else_body);
+                }
+            }
+
+            CommandNode::WhileNode(while_node) => {
+                self.check_condition(&mut while_node.condition, "a while loop");
+                self.loop_depth += 1;
+                self.check_scope(&mut while_node.body);
+                self.loop_depth -= 1;
+            }
+
+            CommandNode::BreakNode(break_node) => {
+                if self.loop_depth == 0 {
+                    self.errors.push(TypeError::OutsideOfLoop { span: break_node.span, keyword: "break" });
+                }
+            }
+
+            CommandNode::ContinueNode(continue_node) => {
+                if self.loop_depth == 0 {
+                    self.errors.push(TypeError::OutsideOfLoop { span: continue_node.span, keyword: "continue" });
                 }
+            }
+
+            CommandNode::FunctionDefinitionNode(func_def_node) => {
+                self.check_function_definition(func_def_node);
+            }
 
-                CommandNode::FunctionCallNode(func_call_node) => {
-                    // only the print function exists atm, so this is hardcoded
-                    if func_call_node.function.name != "print" {
-                        return Err(format!("Undefined function \"{}\" (only the print function is implemented yet)", func_call_node.function.name));
+            CommandNode::ReturnNode(return_node) => {
+                let expected = self.current_function.as_ref().and_then(|function| function.return_type);
+                let value_type = return_node.expression.as_mut().map(|expr| self.assign_expression_type(expr, expected));
+                let function = match &self.current_function {
+                    Some(function) => function,
+                    None => {
+                        self.errors.push(TypeError::ReturnOutsideFunction { span: return_node.span });
+                        return;
                     }
-                    if func_call_node.args.len() != 1 {
-                        return Err(format!("Invalid number of elements for print function! (You have to supply exactly one element)"));
+                };
+                match (function.return_type, value_type) {
+                    (Some(expected), Some(found)) => {
+                        if !expected.is_error() && !found.is_error() && expected != found {
+                            let span = return_node.expression.as_ref().unwrap().node.span();
+                            self.errors.push(TypeError::MismatchedReturnType { span, function: function.name.clone(), expected, found });
+                        }
+                    }
+                    (Some(expected), None) => {
+                        self.errors.push(TypeError::MissingReturnValue { span: return_node.span, function: function.name.clone(), expected });
                     }
-                    let arg_expr: &mut TExpressionNode = &mut func_call_node.args[0];
-                    self.assign_expression_type(arg_expr)?;
-                    // for now, every type is allowed for the print function
+                    (None, Some(_)) => {
+                        let span = return_node.expression.as_ref().unwrap().node.span();
+                        self.errors.push(TypeError::UnexpectedReturnValue { span, function: function.name.clone() });
+                    }
+                    (None, None) => (),
+                }
+            }
+        }
+    }
+
+    // the resulting type of a binary operation, or None if the operation isn't defined for the types
+    // the operands are converted to the type the operation works on first, so they have the same type afterwards
+    fn binary_operation_type(&mut self, binary_op_node: &mut BinaryOperationNode, left: Type, right: Type) -> Option<Type> {
+        let operator = binary_op_node.operator;
+        if operator.is_logical() {
+            return (left == Type::Bool && right == Type::Bool).then_some(Type::Bool);
+        }
+        if operator.is_shift() {
+            if !left.is_integer() || !right.is_integer() {
+                return None;
+            }
+            // the count gets the type of the shifted value (only its lowest bits are used anyway)
+            if VariableTraverser::convert_implicitly(&mut binary_op_node.right_expr, left) != left {
+                VariableTraverser::convert(&mut binary_op_node.right_expr, left);
+            }
+            return Some(left);
+        }
+
+        let operand_type = if left.is_numeric() && right.is_numeric() {
+            VariableTraverser::common_numeric_type(&binary_op_node.left_expr, &binary_op_node.right_expr)?
+        } else if left == right {
+            left
+        } else {
+            return None;
+        };
+        let valid = if operator.is_arithmetic() {
+            operand_type.is_numeric()
+        } else if operator.is_comparison() {
+            operand_type.is_numeric() || operand_type == Type::Char
+        } else {
+            operand_type != Type::Str
+        };
+        if !valid {
+            return None;
+        }
+        let other_spans = [binary_op_node.right_expr.node.span(), binary_op_node.left_expr.node.span()];
+        for (expression_node, other_span) in [&mut binary_op_node.left_expr, &mut binary_op_node.right_expr].into_iter().zip(other_spans) {
+            // ints used together with a float become floats, which is reported (even when it's an error, the conversion is
+            // still inserted, so the rest of the expression can be checked as usual)
+            if VariableTraverser::convert_implicitly(expression_node, operand_type) != operand_type {
+                let conversion = TypeError::ImplicitFloatConversion { span: expression_node.node.span(), operator, found: expression_node.t.unwrap(), float_span: other_span };
+                if self.strict_numeric {
+                    self.errors.push(conversion);
+                } else {
+                    self.warnings.push(conversion);
                 }
+                VariableTraverser::convert(expression_node, operand_type);
             }
-        } 
-        return Ok(());
+        }
+        Some(if operator.is_arithmetic() { operand_type } else { Type::Bool })
     }
 
+    // the type two numbers are converted to when they're used together: a float if one of them is a float, else the int
+    // type that can hold every value of both of them (int literals take the type of the other side if their value fits)
+    fn common_numeric_type(left_expr: &TExpressionNode, right_expr: &TExpressionNode) -> Option<Type> {
+        let (left, right) = (left_expr.t.unwrap(), right_expr.t.unwrap());
+        if left == right {
+            Some(left)
+        } else if left == Type::Float || right == Type::Float {
+            Some(Type::Float)
+        } else if VariableTraverser::adapts_to(left_expr, right) {
+            Some(right)
+        } else if VariableTraverser::adapts_to(right_expr, left) {
+            Some(left)
+        } else if left.widens_to(right) {
+            Some(right)
+        } else if right.widens_to(left) {
+            Some(left)
+        } else {
+            None
+        }
+    }
+
+    // the implicit conversions, which happen wherever a value of a certain type is needed (for a variable, an argument, a
+    // returned value or the other operand of a binary operation):
+    // - an expression made only of int literals without a suffix simply takes the type if all the literals fit into it
+    //   (let u8 x = 200; or x + 1 for an i16 x)
+    // - an int becomes a wider int (i8 to i32, u8 to u16 or to i16), but a signed int never becomes unsigned
+    // returns the type the expression has afterwards, which is still the old one if it couldn't be converted
+    fn convert_implicitly(expression_node: &mut TExpressionNode, expected: Type) -> Type {
+        let found = expression_node.t.expect("Internal compiler error (conversion of an untyped expression)");
+        if found == expected || found.is_error() || expected.is_error() {
+            found
+        } else if VariableTraverser::adapts_to(expression_node, expected) {
+            VariableTraverser::retype(expression_node, expected);
+            expected
+        } else if found.widens_to(expected) {
+            VariableTraverser::convert(expression_node, expected);
+            expected
+        } else {
+            found
+        }
+    }
+
+    // whether the expression only consists of int literals without a suffix (and arithmetic on them), which all fit into
+    // the int type
+    fn adapts_to(expression_node: &TExpressionNode, t: Type) -> bool {
+        if !t.is_integer() {
+            return false;
+        }
+        match &expression_node.node {
+            ExpressionNode::IntLiteralNode(int_node) => int_node.suffix.is_none() && integer::fits(int_node.value as i128, t),
+            ExpressionNode::UnaryOperationNode(unary_op_node) => match (unary_op_node.operator, &unary_op_node.expression.node) {
+                // -128 is an i8, even though 128 isn't
+                (Operator::Minus, ExpressionNode::IntLiteralNode(int_node)) => int_node.suffix.is_none() && integer::fits(-(int_node.value as i128), t),
+                (Operator::Not, _) => false,
+                _ => VariableTraverser::adapts_to(&unary_op_node.expression, t),
+            },
+            ExpressionNode::BinaryOperationNode(binary_op_node) => {
+                (binary_op_node.operator.is_arithmetic() || binary_op_node.operator.is_shift())
+                    && VariableTraverser::adapts_to(&binary_op_node.left_expr, t)
+                    && VariableTraverser::adapts_to(&binary_op_node.right_expr, t)
+            }
+            _ => false,
+        }
+    }
+
+    // gives an expression that adapts_to the type that type, the literals are stored in the form of the type
+    fn retype(expression_node: &mut TExpressionNode, t: Type) {
+        expression_node.t = Some(t);
+        match &mut expression_node.node {
+            ExpressionNode::IntLiteralNode(int_node) => int_node.value = integer::wrap(int_node.value, t),
+            ExpressionNode::UnaryOperationNode(unary_op_node) => VariableTraverser::retype(&mut unary_op_node.expression, t),
+            ExpressionNode::BinaryOperationNode(binary_op_node) => {
+                VariableTraverser::retype(&mut binary_op_node.left_expr, t);
+                VariableTraverser::retype(&mut binary_op_node.right_expr, t);
+            }
+            _ => (),
+        }
+    }
+
+    // the explicit conversions with "as": between all numbers, from chars and bools to ints, from ints to chars, and from
+    // every type to itself (see integer::from_float and integer::to_char for what happens to values that don't fit)
+    fn cast_valid(from: Type, to: Type) -> bool {
+        from == to
+            || (from.is_numeric() && to.is_numeric())
+            || (matches!(from, Type::Char | Type::Bool) && to.is_integer())
+            || (from.is_integer() && to == Type::Char)
+    }
+
+    // wraps the expression in a conversion to the type
+    fn convert(expression_node: &mut TExpressionNode, t: Type) {
+        let span = expression_node.node.span();
+        let placeholder = TExpressionNode { node: ExpressionNode::BoolLiteralNode(BoolLiteralNode { value: false, span }), t: None };
+        let inner = std::mem::replace(expression_node, placeholder);
+        *expression_node = TExpressionNode { node: ExpressionNode::ConversionNode(ConversionNode { expression: Box::new(inner), span }), t: Some(t) };
+    }
 
     // determine the "t" (type) field for an expression node (and also for the child nodes, if they exist)
-    fn assign_expression_type(&self, expression_node: &mut TExpressionNode) -> Result<(), String> {
+    // errors are recorded and the expression gets the error type, which is accepted everywhere without further errors
+    // if a type is expected where the expression is used, the expression is converted to it if that's possible implicitly
+    // (see convert_implicitly), the caller still has to check whether the returned type is the expected one
+    fn assign_expression_type(&mut self, expression_node: &mut TExpressionNode, expected: Option<Type>) -> Type {
         let expression_type: Type = match &mut expression_node.node {
             ExpressionNode::VariableNode(var_node) => {
                 // check if the variable exists -> if yes, return type of the variable
-                let type_result = self.variable_table.get(&var_node.name);
+                let type_result = self.lookup_variable(&var_node.name);
                 match type_result {
-                    Some(t) => *t,
-                    None => { return Err(format!("Usage of undefined variable \"{}\" in expression!", var_node.name)); }
+                    Some(entry) => entry.t,
+                    None => {
+                        self.errors.push(TypeError::UndefinedVariable { span: var_node.span, name: var_node.name.clone() });
+                        Type::Error
+                    }
                 }
             },
-        
+
             ExpressionNode::UnaryOperationNode(unary_op_node) => {
-                // inherit type of the child expression node, but the type has to be numeric (int/float)
+                // inherit type of the child expression node, but the type has to be numeric for + and - (and signed for -)
+                // or bool for !
                 let sub_expression_node = &mut unary_op_node.expression;
-                self.assign_expression_type(sub_expression_node)?;
-                let sub_expression_type = sub_expression_node.t.as_ref().unwrap();
-                match sub_expression_type {
-                    Type::Int | Type::Float => {},
-                    _ => {
-                        return Err(format!("Invalid type \"{}\" for unary operation (must be either int or float)", sub_expression_type.to_str()));
-                    }
+                let sub_expression_type = self.assign_expression_type(sub_expression_node, None);
+                let valid = match unary_op_node.operator {
+                    Operator::Not => sub_expression_type == Type::Bool,
+                    Operator::Minus => sub_expression_type == Type::Float || sub_expression_type.is_signed(),
+                    _ => sub_expression_type.is_numeric(),
+                };
+                if valid || sub_expression_type.is_error() {
+                    sub_expression_type
+                } else {
+                    self.errors.push(TypeError::InvalidUnaryOperand { span: sub_expression_node.node.span(), operator: unary_op_node.operator, found: sub_expression_type });
+                    Type::Error
                 }
-                *sub_expression_type
             }
-            
+
             ExpressionNode::BinaryOperationNode(binary_op_node) => {
-                let left_expr_node = &mut binary_op_node.left_expr;
-                let right_expr_node = &mut binary_op_node.right_expr;
-                self.assign_expression_type(left_expr_node)?;
-                self.assign_expression_type(right_expr_node)?;
-                let left_expr_type = left_expr_node.t.as_ref().unwrap();
-                let right_expr_type = right_expr_node.t.as_ref().unwrap();
-                // both types have to be numeric (int/float)
-                if (*left_expr_type != Type::Int && *left_expr_type != Type::Float) || (*right_expr_type != Type::Int && *right_expr_type != Type::Float) {
-                    return Err(format!("Invalid types \"{}\" and \"{}\" for binary operation!", left_expr_type.to_str(), right_expr_type.to_str()))
-                }
-                // if at least one of them is float, then the parent type is also float
-                if *right_expr_type == Type::Float || *left_expr_type == Type::Float {
-                    Type::Float
+                let left_expr_type = self.assign_expression_type(&mut binary_op_node.left_expr, None);
+                let right_expr_type = self.assign_expression_type(&mut binary_op_node.right_expr, None);
+                let operator = binary_op_node.operator;
+                if left_expr_type.is_error() || right_expr_type.is_error() {
+                    Type::Error
                 } else {
-                    Type::Int
+                    match self.binary_operation_type(binary_op_node, left_expr_type, right_expr_type) {
+                        Some(t) => t,
+                        None => {
+                            self.errors.push(TypeError::InvalidBinaryOperands { span: binary_op_node.span, operator, left_span: binary_op_node.left_expr.node.span(), left: left_expr_type, right_span: binary_op_node.right_expr.node.span(), right: right_expr_type });
+                            Type::Error
+                        }
+                    }
                 }
             }
 
             ExpressionNode::FunctionCallNode(func_call_node) => {
-                return Err(format!("Function calls in expressions aren't supported yet! (Tried to call \"{}()\" in expression)", func_call_node.function.name))
+                match self.check_function_call(func_call_node) {
+                    Some(t) => t,
+                    None => {
+                        self.errors.push(TypeError::VoidFunctionInExpression { span: func_call_node.span, name: func_call_node.function.name.clone() });
+                        Type::Error
+                    }
+                }
+            }
+
+            ExpressionNode::CastNode(cast_node) => {
+                let found = self.assign_expression_type(&mut cast_node.expression, None);
+                let target = Type::from(&cast_node.target);
+                if found.is_error() || VariableTraverser::cast_valid(found, target) {
+                    target
+                } else {
+                    self.errors.push(TypeError::InvalidCast { span: cast_node.span, expression_span: cast_node.expression.node.span(), found, target });
+                    Type::Error
+                }
             }
 
+            // the type checker inserts conversions itself, after the expression got its type
+            ExpressionNode::ConversionNode(_) => panic!("Internal compiler error (conversion before type checking)"),
+
             // so complicated...
-            ExpressionNode::IntLiteralNode(_) => Type::Int,
+            ExpressionNode::IntLiteralNode(int_node) => int_node.suffix.unwrap_or(Type::Int),
             ExpressionNode::FloatLiteralNode(_) => Type::Float,
             ExpressionNode::BoolLiteralNode(_) => Type::Bool,
             ExpressionNode::CharLiteralNode(_) => Type::Char,
             ExpressionNode::StringLiteralNode(_) => Type::Str,
         };
         expression_node.t = Some(expression_type);
-        return Ok(());
+        match expected {
+            Some(expected) => VariableTraverser::convert_implicitly(expression_node, expected),
+            None => expression_type,
+        }
     }
 }
-
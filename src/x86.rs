@@ -0,0 +1,445 @@
+// x86-64 instructions as they are produced by the code generator, and the GAS (intel syntax) text output for them
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Reg {
+    Rax,
+    Rcx,
+    Rdx,
+    Rbx,
+    Rsp,
+    Rbp,
+    Rsi,
+    Rdi,
+    R8,
+    R9,
+    R10,
+    R11,
+    R12,
+    R13,
+    R14,
+    R15,
+}
+
+impl Reg {
+    // the number of the register in the instruction encoding
+    pub fn number(self) -> u8 {
+        self as u8
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Reg::Rax => "rax",
+            Reg::Rcx => "rcx",
+            Reg::Rdx => "rdx",
+            Reg::Rbx => "rbx",
+            Reg::Rsp => "rsp",
+            Reg::Rbp => "rbp",
+            Reg::Rsi => "rsi",
+            Reg::Rdi => "rdi",
+            Reg::R8 => "r8",
+            Reg::R9 => "r9",
+            Reg::R10 => "r10",
+            Reg::R11 => "r11",
+            Reg::R12 => "r12",
+            Reg::R13 => "r13",
+            Reg::R14 => "r14",
+            Reg::R15 => "r15",
+        }
+    }
+
+    // the name of the lowest byte of the register (e.g. al for rax)
+    pub fn byte_str(self) -> &'static str {
+        match self {
+            Reg::Rax => "al",
+            Reg::Rcx => "cl",
+            Reg::Rdx => "dl",
+            Reg::Rbx => "bl",
+            Reg::Rsp => "spl",
+            Reg::Rbp => "bpl",
+            Reg::Rsi => "sil",
+            Reg::Rdi => "dil",
+            Reg::R8 => "r8b",
+            Reg::R9 => "r9b",
+            Reg::R10 => "r10b",
+            Reg::R11 => "r11b",
+            Reg::R12 => "r12b",
+            Reg::R13 => "r13b",
+            Reg::R14 => "r14b",
+            Reg::R15 => "r15b",
+        }
+    }
+
+    // the lowest 16 bits (e.g. ax for rax)
+    pub fn word_str(self) -> &'static str {
+        match self {
+            Reg::Rax => "ax",
+            Reg::Rcx => "cx",
+            Reg::Rdx => "dx",
+            Reg::Rbx => "bx",
+            Reg::Rsp => "sp",
+            Reg::Rbp => "bp",
+            Reg::Rsi => "si",
+            Reg::Rdi => "di",
+            Reg::R8 => "r8w",
+            Reg::R9 => "r9w",
+            Reg::R10 => "r10w",
+            Reg::R11 => "r11w",
+            Reg::R12 => "r12w",
+            Reg::R13 => "r13w",
+            Reg::R14 => "r14w",
+            Reg::R15 => "r15w",
+        }
+    }
+
+    // the lowest 32 bits (e.g. eax for rax)
+    pub fn dword_str(self) -> &'static str {
+        match self {
+            Reg::Rax => "eax",
+            Reg::Rcx => "ecx",
+            Reg::Rdx => "edx",
+            Reg::Rbx => "ebx",
+            Reg::Rsp => "esp",
+            Reg::Rbp => "ebp",
+            Reg::Rsi => "esi",
+            Reg::Rdi => "edi",
+            Reg::R8 => "r8d",
+            Reg::R9 => "r9d",
+            Reg::R10 => "r10d",
+            Reg::R11 => "r11d",
+            Reg::R12 => "r12d",
+            Reg::R13 => "r13d",
+            Reg::R14 => "r14d",
+            Reg::R15 => "r15d",
+        }
+    }
+}
+
+// the SSE registers, used for floats
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Xmm {
+    Xmm0,
+    Xmm1,
+    Xmm2,
+    Xmm3,
+    Xmm4,
+    Xmm5,
+    Xmm6,
+    Xmm7,
+    Xmm8,
+    Xmm9,
+    Xmm10,
+    Xmm11,
+    Xmm12,
+    Xmm13,
+    Xmm14,
+    Xmm15,
+}
+
+impl Xmm {
+    pub fn number(self) -> u8 {
+        self as u8
+    }
+
+    pub fn to_str(self) -> String {
+        format!("xmm{}", self.number())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Operand {
+    Reg(Reg),
+    Imm(i32), // sign-extended to 64 bit, bigger values have to be loaded with MovAbs
+    Mem { base: Reg, offset: i32 },
+}
+
+impl Operand {
+    fn to_str(self) -> String {
+        match self {
+            Operand::Reg(reg) => reg.to_str().to_string(),
+            Operand::Imm(value) => value.to_string(),
+            // the size is always written, it's needed whenever there is no register operand
+            Operand::Mem { .. } => format!("qword ptr {}", self.address_str()),
+        }
+    }
+
+    // the address of a memory operand without a size (for lea and byte operations)
+    fn address_str(self) -> String {
+        match self {
+            Operand::Mem { base, offset: 0 } => format!("[{}]", base.to_str()),
+            Operand::Mem { base, offset } if offset < 0 => format!("[{} - {}]", base.to_str(), -(offset as i64)),
+            Operand::Mem { base, offset } => format!("[{} + {}]", base.to_str(), offset),
+            _ => self.to_str(),
+        }
+    }
+}
+
+// condition codes for conditional jumps and set instructions
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cond {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Sign,
+    NotSign,
+    // unsigned comparisons, also used for floats
+    Below,
+    BelowEqual,
+    Above,
+    AboveEqual,
+    // set if a float comparison is unordered (one of the values is NaN)
+    Parity,
+    NotParity,
+}
+
+impl Cond {
+    // the number of the condition in the instruction encoding
+    pub fn number(self) -> u8 {
+        match self {
+            Cond::Below => 0x2,
+            Cond::AboveEqual => 0x3,
+            Cond::Equal => 0x4,
+            Cond::NotEqual => 0x5,
+            Cond::BelowEqual => 0x6,
+            Cond::Above => 0x7,
+            Cond::Sign => 0x8,
+            Cond::NotSign => 0x9,
+            Cond::Parity => 0xa,
+            Cond::NotParity => 0xb,
+            Cond::Less => 0xc,
+            Cond::GreaterEqual => 0xd,
+            Cond::LessEqual => 0xe,
+            Cond::Greater => 0xf,
+        }
+    }
+
+    // the condition that is true exactly when this one is false
+    pub fn inverse(self) -> Cond {
+        match self {
+            Cond::Equal => Cond::NotEqual,
+            Cond::NotEqual => Cond::Equal,
+            Cond::Less => Cond::GreaterEqual,
+            Cond::LessEqual => Cond::Greater,
+            Cond::Greater => Cond::LessEqual,
+            Cond::GreaterEqual => Cond::Less,
+            Cond::Sign => Cond::NotSign,
+            Cond::NotSign => Cond::Sign,
+            Cond::Below => Cond::AboveEqual,
+            Cond::BelowEqual => Cond::Above,
+            Cond::Above => Cond::BelowEqual,
+            Cond::AboveEqual => Cond::Below,
+            Cond::Parity => Cond::NotParity,
+            Cond::NotParity => Cond::Parity,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Cond::Equal => "e",
+            Cond::NotEqual => "ne",
+            Cond::Less => "l",
+            Cond::LessEqual => "le",
+            Cond::Greater => "g",
+            Cond::GreaterEqual => "ge",
+            Cond::Sign => "s",
+            Cond::NotSign => "ns",
+            Cond::Below => "b",
+            Cond::BelowEqual => "be",
+            Cond::Above => "a",
+            Cond::AboveEqual => "ae",
+            Cond::Parity => "p",
+            Cond::NotParity => "np",
+        }
+    }
+}
+
+// all operations work on 64 bit values unless the name says otherwise
+// for two operands the destination comes first (like in intel syntax)
+#[derive(Clone, PartialEq, Debug)]
+pub enum Inst {
+    Label(String),
+    Mov(Operand, Operand),
+    MovAbs(Reg, i64),
+    // stores the lowest byte of the source (a register or an immediate) in memory
+    MovByte(Operand, Operand),
+    // zero-extends the lowest byte of the second register into the first one
+    MovzxByte(Reg, Reg),
+    // the same for the lowest 16 bits
+    MovzxWord(Reg, Reg),
+    // sign-extends the lowest 8, 16 or 32 bits of the second register into the first one
+    MovsxByte(Reg, Reg),
+    MovsxWord(Reg, Reg),
+    Movsxd(Reg, Reg),
+    // copies the lowest 32 bits, which clears the upper half of the destination
+    MovDword(Reg, Reg),
+    Lea(Reg, Operand),
+    // loads the address of a label in the data section
+    LeaData(Reg, String),
+    Add(Operand, Operand),
+    Sub(Operand, Operand),
+    Imul(Reg, Operand),
+    And(Operand, Operand),
+    Or(Operand, Operand),
+    Xor(Operand, Operand),
+    Cmp(Operand, Operand),
+    Test(Operand, Operand),
+    Neg(Operand),
+    Inc(Operand),
+    Dec(Operand),
+    Shl(Operand, u8),
+    Shr(Operand, u8),
+    Sar(Operand, u8),
+    // shifts by the count in cl
+    ShlCl(Operand),
+    ShrCl(Operand),
+    SarCl(Operand),
+    // sign-extends rax into rdx:rax before a division
+    Cqo,
+    // signed and unsigned division of rdx:rax, quotient in rax, remainder in rdx
+    Idiv(Operand),
+    Div(Operand),
+    Set(Cond, Reg),
+    Push(Reg),
+    Pop(Reg),
+    Jmp(String),
+    Jcc(Cond, String),
+    Call(String),
+    Ret,
+    Leave,
+    Syscall,
+    // moves the bits of a float between a general purpose and an SSE register
+    MovqToXmm(Xmm, Reg),
+    MovqFromXmm(Reg, Xmm),
+    // copies one SSE register into another
+    Movapd(Xmm, Xmm),
+    Addsd(Xmm, Xmm),
+    Subsd(Xmm, Xmm),
+    Mulsd(Xmm, Xmm),
+    Divsd(Xmm, Xmm),
+    // compares two floats and sets the flags like an unsigned comparison
+    Ucomisd(Xmm, Xmm),
+    // int to float, and float to int (rounded to the nearest int, ties to even, or toward zero with cvttsd2si)
+    Cvtsi2sd(Xmm, Reg),
+    Cvtsd2si(Reg, Xmm),
+    Cvttsd2si(Reg, Xmm),
+    // x87 instructions, only needed for the float remainder (fprem computes it exactly)
+    Fld(Operand),
+    Fstp(Operand),
+    FstpSt1,
+    Fprem,
+    FnstswAx,
+}
+
+impl Inst {
+    pub fn to_gas(&self) -> String {
+        match self {
+            Inst::Label(name) => format!("{}:", name),
+            Inst::Mov(dst, src) => format!("    mov {}, {}", dst.to_str(), src.to_str()),
+            Inst::MovAbs(dst, value) => format!("    movabs {}, {}", dst.to_str(), value),
+            Inst::MovByte(dst, src) => {
+                let src_str = match src {
+                    Operand::Reg(reg) => reg.byte_str().to_string(),
+                    _ => src.to_str(),
+                };
+                format!("    mov byte ptr {}, {}", dst.address_str(), src_str)
+            }
+            Inst::MovzxByte(dst, src) => format!("    movzx {}, {}", dst.to_str(), src.byte_str()),
+            Inst::MovzxWord(dst, src) => format!("    movzx {}, {}", dst.to_str(), src.word_str()),
+            Inst::MovsxByte(dst, src) => format!("    movsx {}, {}", dst.to_str(), src.byte_str()),
+            Inst::MovsxWord(dst, src) => format!("    movsx {}, {}", dst.to_str(), src.word_str()),
+            Inst::Movsxd(dst, src) => format!("    movsxd {}, {}", dst.to_str(), src.dword_str()),
+            Inst::MovDword(dst, src) => format!("    mov {}, {}", dst.dword_str(), src.dword_str()),
+            Inst::Lea(dst, src) => format!("    lea {}, {}", dst.to_str(), src.address_str()),
+            Inst::LeaData(dst, label) => format!("    lea {}, [rip + {}]", dst.to_str(), label),
+            Inst::Add(dst, src) => format!("    add {}, {}", dst.to_str(), src.to_str()),
+            Inst::Sub(dst, src) => format!("    sub {}, {}", dst.to_str(), src.to_str()),
+            Inst::Imul(dst, src) => format!("    imul {}, {}", dst.to_str(), src.to_str()),
+            Inst::And(dst, src) => format!("    and {}, {}", dst.to_str(), src.to_str()),
+            Inst::Or(dst, src) => format!("    or {}, {}", dst.to_str(), src.to_str()),
+            Inst::Xor(dst, src) => format!("    xor {}, {}", dst.to_str(), src.to_str()),
+            Inst::Cmp(left, right) => format!("    cmp {}, {}", left.to_str(), right.to_str()),
+            Inst::Test(left, right) => format!("    test {}, {}", left.to_str(), right.to_str()),
+            Inst::Neg(operand) => format!("    neg {}", operand.to_str()),
+            Inst::Inc(operand) => format!("    inc {}", operand.to_str()),
+            Inst::Dec(operand) => format!("    dec {}", operand.to_str()),
+            Inst::Shl(operand, count) => format!("    shl {}, {}", operand.to_str(), count),
+            Inst::Shr(operand, count) => format!("    shr {}, {}", operand.to_str(), count),
+            Inst::Sar(operand, count) => format!("    sar {}, {}", operand.to_str(), count),
+            Inst::ShlCl(operand) => format!("    shl {}, cl", operand.to_str()),
+            Inst::ShrCl(operand) => format!("    shr {}, cl", operand.to_str()),
+            Inst::SarCl(operand) => format!("    sar {}, cl", operand.to_str()),
+            Inst::Cqo => "    cqo".to_string(),
+            Inst::Idiv(operand) => format!("    idiv {}", operand.to_str()),
+            Inst::Div(operand) => format!("    div {}", operand.to_str()),
+            Inst::Set(cond, reg) => format!("    set{} {}", cond.to_str(), reg.byte_str()),
+            Inst::Push(reg) => format!("    push {}", reg.to_str()),
+            Inst::Pop(reg) => format!("    pop {}", reg.to_str()),
+            Inst::Jmp(label) => format!("    jmp {}", label),
+            Inst::Jcc(cond, label) => format!("    j{} {}", cond.to_str(), label),
+            Inst::Call(label) => format!("    call {}", label),
+            Inst::Ret => "    ret".to_string(),
+            Inst::Leave => "    leave".to_string(),
+            Inst::Syscall => "    syscall".to_string(),
+            Inst::MovqToXmm(dst, src) => format!("    movq {}, {}", dst.to_str(), src.to_str()),
+            Inst::MovqFromXmm(dst, src) => format!("    movq {}, {}", dst.to_str(), src.to_str()),
+            Inst::Movapd(dst, src) => format!("    movapd {}, {}", dst.to_str(), src.to_str()),
+            Inst::Addsd(dst, src) => format!("    addsd {}, {}", dst.to_str(), src.to_str()),
+            Inst::Subsd(dst, src) => format!("    subsd {}, {}", dst.to_str(), src.to_str()),
+            Inst::Mulsd(dst, src) => format!("    mulsd {}, {}", dst.to_str(), src.to_str()),
+            Inst::Divsd(dst, src) => format!("    divsd {}, {}", dst.to_str(), src.to_str()),
+            Inst::Ucomisd(left, right) => format!("    ucomisd {}, {}", left.to_str(), right.to_str()),
+            Inst::Cvtsi2sd(dst, src) => format!("    cvtsi2sd {}, {}", dst.to_str(), src.to_str()),
+            Inst::Cvtsd2si(dst, src) => format!("    cvtsd2si {}, {}", dst.to_str(), src.to_str()),
+            Inst::Cvttsd2si(dst, src) => format!("    cvttsd2si {}, {}", dst.to_str(), src.to_str()),
+            Inst::Fld(operand) => format!("    fld {}", operand.to_str()),
+            Inst::Fstp(operand) => format!("    fstp {}", operand.to_str()),
+            Inst::FstpSt1 => "    fstp st(1)".to_string(),
+            Inst::Fprem => "    fprem".to_string(),
+            Inst::FnstswAx => "    fnstsw ax".to_string(),
+        }
+    }
+}
+
+// a whole program: the code and the constant data it refers to
+pub struct Program {
+    pub text: Vec<Inst>,
+    // labeled byte strings, placed in a read-only section
+    pub data: Vec<(String, Vec<u8>)>,
+    // the labels other object files can use (the functions)
+    pub globals: Vec<String>,
+}
+
+impl Program {
+    // the number of instructions (without the labels), including the ones of the runtime
+    pub fn instruction_count(&self) -> usize {
+        self.text.iter().filter(|inst| !matches!(inst, Inst::Label(_))).count()
+    }
+
+    pub fn to_gas(&self) -> String {
+        // _start is weak, so it's replaced by the one of the C runtime when the assembly is linked with C code
+        let mut s = String::from(".intel_syntax noprefix\n.weak _start\n");
+        for global in &self.globals {
+            s += &format!(".globl {}\n", global);
+        }
+        s += "\n.text\n";
+        for inst in &self.text {
+            s += &inst.to_gas();
+            s += "\n";
+        }
+        if !self.data.is_empty() {
+            s += "\n.section .rodata\n";
+            for (label, bytes) in &self.data {
+                s += &format!("{}:\n", label);
+                if !bytes.is_empty() {
+                    let byte_strs: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+                    s += &format!("    .byte {}\n", byte_strs.join(", "));
+                }
+            }
+        }
+        // the stack doesn't need to be executable
+        s += "\n.section .note.GNU-stack,\"\",@progbits\n";
+        s
+    }
+}
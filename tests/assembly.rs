@@ -0,0 +1,48 @@
+// assembles the output of --emit asm with gcc, the programs have to behave exactly like the executables hj writes itself
+// (the tests are skipped if there is no gcc, unless CI is set)
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+// compiles the file into an executable and into assembly, and runs both programs, None if there is no gcc
+fn run_both(source_path: &Path, level: &str) -> Option<(Output, Output)> {
+    let name = format!("{}{}", source_path.file_stem().unwrap().to_string_lossy(), level);
+    let dir = common::temp_dir("assembly");
+    let (native_path, asm_path, asm_exe_path) = (dir.join(&name), dir.join(format!("{}.s", name)), dir.join(format!("{}-as", name)));
+    for (args, output_path) in [(vec!(level), &native_path), (vec!(level, "--emit", "asm"), &asm_path)] {
+        let compile = common::compile(source_path, &args, output_path);
+        assert!(compile.status.success(), "compilation of {} failed:\n{}", source_path.display(), String::from_utf8_lossy(&compile.stdout));
+    }
+
+    // the assembly brings its own _start and runtime, so it's linked without the C library
+    let gcc = common::run_tool(Command::new("gcc").args(["-nostdlib", "-static", "-no-pie"]).arg(&asm_path).arg("-o").arg(&asm_exe_path))?;
+    assert!(gcc.status.success(), "{} can't be assembled:\n{}", asm_path.display(), String::from_utf8_lossy(&gcc.stderr));
+    Some((Command::new(&native_path).output().unwrap(), Command::new(&asm_exe_path).output().unwrap()))
+}
+
+#[test]
+fn matches_native_programs() {
+    let programs = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("programs");
+    for entry in fs::read_dir(programs).unwrap() {
+        let path = entry.unwrap().path();
+        for level in ["-O0", "-O2"] {
+            let Some((native, asm)) = run_both(&path, level) else { return };
+            assert_eq!(String::from_utf8_lossy(&native.stdout), String::from_utf8_lossy(&asm.stdout), "different output for {} with {}", path.display(), level);
+            assert_eq!(native.status.code(), asm.status.code(), "different exit code for {} with {}", path.display(), level);
+        }
+    }
+}
+
+#[test]
+fn int_arithmetic_variables_and_print() {
+    let dir = common::temp_dir("assembly");
+    let source_path = dir.join("arithmetic.hj");
+    fs::write(&source_path, "let a = 7; let b = a * 6 - 2; b /= 3; let c = -b % 5 + (a << 2); print(a); print(b); print(c);").unwrap();
+    for level in ["-O0", "-O2"] {
+        let Some((_, asm)) = run_both(&source_path, level) else { return };
+        assert_eq!(String::from_utf8_lossy(&asm.stdout), "7\n13\n25\n", "wrong output with {}", level);
+    }
+}
@@ -0,0 +1,126 @@
+// compiles programs with --target=c and a C compiler, the executables have to behave exactly like the native ones
+// (the tests are skipped if there is no cc, unless CI is set)
+
+mod common;
+
+use std::fs;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn temp_dir() -> PathBuf {
+    common::temp_dir("c")
+}
+
+fn hj(args: &[&str], source_path: &Path, output_path: &Path) -> Output {
+    let compile = common::compile(source_path, args, output_path);
+    assert!(compile.status.success(), "compilation of {} failed:\n{}", source_path.display(), String::from_utf8_lossy(&compile.stdout));
+    compile
+}
+
+// compiles the file natively and through C and runs both executables, None if there is no C compiler
+fn run_both(source_path: &Path, level: &str) -> Option<(Output, Output)> {
+    let name = format!("{}{}", source_path.file_stem().unwrap().to_string_lossy(), level);
+    let dir = temp_dir();
+    let (native_path, c_path, c_exe_path) = (dir.join(&name), dir.join(format!("{}.c", name)), dir.join(format!("{}-c", name)));
+    hj(&[level], source_path, &native_path);
+    hj(&[level, "--target", "c"], source_path, &c_path);
+
+    // the output has to be valid C99 without any warnings
+    let cc = common::run_tool(Command::new("cc").args(["-std=c99", "-pedantic", "-Wall", "-Wextra", "-Werror"]).arg(&c_path).arg("-o").arg(&c_exe_path).arg("-lm"))?;
+    assert!(cc.status.success(), "{} isn't valid C:\n{}", c_path.display(), String::from_utf8_lossy(&cc.stderr));
+    Some((Command::new(&native_path).output().unwrap(), Command::new(&c_exe_path).output().unwrap()))
+}
+
+fn run_source_both(name: &str, source: &str, level: &str) -> Option<(Output, Output)> {
+    let source_path = temp_dir().join(format!("{}.hj", name));
+    fs::write(&source_path, source).unwrap();
+    run_both(&source_path, level)
+}
+
+fn assert_same(native: &Output, c: &Output, name: &str) {
+    assert_eq!(String::from_utf8_lossy(&native.stdout), String::from_utf8_lossy(&c.stdout), "different output for {}", name);
+    assert_eq!(native.status.code(), c.status.code(), "different exit code for {}", name);
+    assert_eq!(native.status.signal(), c.status.signal(), "different signal for {}", name);
+}
+
+#[test]
+fn matches_native_programs() {
+    let programs = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("programs");
+    for entry in fs::read_dir(programs).unwrap() {
+        let path = entry.unwrap().path();
+        for level in ["-O0", "-O2"] {
+            let Some((native, c)) = run_both(&path, level) else { return };
+            assert_same(&native, &c, &format!("{} with {}", path.display(), level));
+        }
+    }
+}
+
+// constants that can't be written in C the way the IR writes them, and names that are C keywords
+#[test]
+fn translates_special_values() {
+    let source = "
+        fn double(int int_value) -> int { return int_value * 2; }
+        let min = -9223372036854775807 - 1;
+        print(min);
+        print(-min);
+        print(min - 1);
+        print(double(min + 5));
+        let zero = 0.0;
+        print(1.0 / zero);
+        print(-1.0 / zero);
+        print(zero / zero);
+        print(-(-2.5));
+        print(-zero);
+        let big = 100000000000000000000.0;
+        print(big * 12345.678);
+        print(big * big * big * big * big * big * big * big * big * big * big * big * big * big * big * big);
+        print(0.1 + 0.2);
+        print('~');
+        print('\\\\');
+        print('\\'');
+        print('ü');
+        print('😀');
+        print(' ');
+        print(\"a \\\" ??= ü 😀 %d \\\\ \\0 \\n\");
+        print(9223372036854775807 + 1);
+        exit(300);
+    ";
+    for level in ["-O0", "-O2"] {
+        let Some((native, c)) = run_source_both("special_values", source, level) else { return };
+        assert_same(&native, &c, &format!("special values with {}", level));
+    }
+}
+
+#[test]
+fn traps_like_native_code() {
+    for (name, source) in [("division_by_zero", "let zero = 0; print(1); print(5 / zero);"), ("remainder_overflow", "let min = -9223372036854775807 - 1; let x = -1; print(2); print(min % x);"), ("unsigned_division_by_zero", "let u8 zero = 0; print(3); print(7u8 / zero);")] {
+        let Some((native, c)) = run_source_both(name, source, "-O0") else { return };
+        assert_same(&native, &c, name);
+        assert!(c.status.signal().is_some(), "{} didn't crash", name);
+    }
+}
+
+#[test]
+fn writes_readable_c() {
+    let dir = temp_dir();
+    let source_path = dir.join("readable.hj");
+    let c_path = dir.join("readable.c");
+    fs::write(&source_path, "fn greet(str name, int times) -> float { let i = 0; while i < times { print(name); i += 1; } return i * 0.5; } print(greet(\"hj\", 3));").unwrap();
+    hj(&["-O1", "--target", "c"], &source_path, &c_path);
+    let c = fs::read_to_string(&c_path).unwrap();
+    assert!(c.contains("double fn_greet(hj_str r0, int64_t r1)"), "{}", c);
+    assert!(c.contains("hj_print_str(r0);"), "{}", c);
+    assert!(c.contains("hj_print_float(r"), "{}", c);
+    assert!(c.contains("int main(void)"), "{}", c);
+}
+
+#[test]
+fn rejects_assembly_for_c() {
+    let dir = temp_dir();
+    let source_path = dir.join("no_assembly.hj");
+    fs::write(&source_path, "print(1);").unwrap();
+    let compile = Command::new(env!("CARGO_BIN_EXE_hj")).arg(&source_path).args(["--target", "c", "--emit", "asm"]).output().unwrap();
+    assert!(!compile.status.success());
+    assert!(String::from_utf8_lossy(&compile.stdout).contains("There is no assembly for the C target"));
+}
@@ -0,0 +1,96 @@
+// checks which casts with "as" the type checker accepts, and what the cast values are when the program runs
+
+mod common;
+
+use common::{check, parse};
+use hj::interpreter::Interpreter;
+use hj::nodes::*;
+use hj::variable_traverser::VariableTraverser;
+
+// the type of the expression of the last variable definition
+fn checked_type(source: &str) -> Option<Type> {
+    let mut scope = parse(source);
+    VariableTraverser::new().traverse(&mut scope).expect("type checking failed");
+    match scope.commands.pop() {
+        Some(CommandNode::VariableDefinitionNode(node)) => node.expression.unwrap().t,
+        _ => panic!("expected a variable definition at the end"),
+    }
+}
+
+// what the interpreter prints for the value of the expression
+fn printed(expression: &str) -> String {
+    let mut scope = parse(&format!("print({});", expression));
+    VariableTraverser::new().traverse(&mut scope).expect("type checking failed");
+    let mut output = vec!();
+    Interpreter::new(&mut output).run(&scope).expect("running failed");
+    String::from_utf8(output).unwrap().trim_end().to_string()
+}
+
+#[test]
+fn casts_have_the_target_type() {
+    assert_eq!(checked_type("let x = 1.5 as int;"), Some(Type::Int));
+    assert_eq!(checked_type("let x = 300 as u8;"), Some(Type::U8));
+    assert_eq!(checked_type("let u8 a = 1; let x = a as float;"), Some(Type::Float));
+    assert_eq!(checked_type("let x = 'a' as u32;"), Some(Type::U32));
+    assert_eq!(checked_type("let x = 97 as char;"), Some(Type::Char));
+    assert_eq!(checked_type("let x = true as i16;"), Some(Type::I16));
+    assert_eq!(checked_type("let x = \"s\" as str;"), Some(Type::Str));
+    // the value of a cast doesn't adapt to the expected type like a literal
+    assert_eq!(check("let int k = (1.0 * 2) as int; let i8 small = k as i8; let float f = small as float;"), Vec::<&str>::new());
+    assert_eq!(check("let u8 x = 5 as u16;"), vec!("E0009"));
+}
+
+#[test]
+fn casts_between_unrelated_types_are_errors() {
+    // E0036: invalid cast
+    assert_eq!(check("let x = \"5\" as int;"), vec!("E0036"));
+    assert_eq!(check("let x = 5 as str;"), vec!("E0036"));
+    assert_eq!(check("let x = 1 as bool;"), vec!("E0036"));
+    assert_eq!(check("let x = 1.5 as bool;"), vec!("E0036"));
+    assert_eq!(check("let x = 2.5 as char;"), vec!("E0036"));
+    assert_eq!(check("let x = 'a' as float;"), vec!("E0036"));
+    assert_eq!(check("let x = 'a' as bool;"), vec!("E0036"));
+    assert_eq!(check("let x = true as float;"), vec!("E0036"));
+    assert_eq!(check("let x = true as char;"), vec!("E0036"));
+    // the cast has the error type afterwards, so there are no further errors
+    assert_eq!(check("let x = \"5\" as int + 1;"), vec!("E0036"));
+    // an error inside of the cast isn't reported again
+    assert_eq!(check("let x = y as int;"), vec!("E0012"));
+}
+
+#[test]
+fn ints_wrap_around() {
+    assert_eq!(printed("300 as u8"), "44");
+    assert_eq!(printed("-1 as uint"), "18446744073709551615");
+    assert_eq!(printed("200u8 as i8"), "-56");
+    assert_eq!(printed("18446744073709551615u64 as i32"), "-1");
+    assert_eq!(printed("-1 as u8 as int"), "255");
+}
+
+#[test]
+fn floats_are_truncated_and_saturated() {
+    assert_eq!(printed("2.9 as int"), "2");
+    assert_eq!(printed("-2.9 as int"), "-2");
+    assert_eq!(printed("1e30 as int"), "9223372036854775807");
+    assert_eq!(printed("-1e30 as int"), "-9223372036854775808");
+    assert_eq!(printed("300.5 as u8"), "255");
+    assert_eq!(printed("-0.5 as u8"), "0");
+    assert_eq!(printed("-5.0 as uint"), "0");
+    assert_eq!(printed("1e19 as uint"), "10000000000000000000");
+    assert_eq!(printed("(0.0 / 0.0) as i32"), "0");
+    assert_eq!(printed("7 as float / 2.0"), "3.500000");
+}
+
+#[test]
+fn chars_are_code_points() {
+    assert_eq!(printed("'A' as int"), "65");
+    assert_eq!(printed("'€' as int"), "8364");
+    assert_eq!(printed("'€' as u8"), "172");
+    assert_eq!(printed("65 as char"), "A");
+    assert_eq!(printed("8364 as char"), "€");
+    // values that aren't code points become U+FFFD
+    assert_eq!(printed("-1 as char"), "\u{FFFD}");
+    assert_eq!(printed("0xD800 as char"), "\u{FFFD}");
+    assert_eq!(printed("0x110000 as char"), "\u{FFFD}");
+    assert_eq!(printed("true as int + false as int"), "1");
+}
@@ -0,0 +1,74 @@
+// helpers shared by the integration tests, every test file only uses some of them
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use hj::errors::TypeError;
+use hj::ir::Module;
+use hj::lexer::create_tokens;
+use hj::lowering::lower;
+use hj::nodes::ScopeNode;
+use hj::optimizer::PassManager;
+use hj::parser::Parser;
+use hj::variable_traverser::VariableTraverser;
+
+pub fn parse(source: &str) -> ScopeNode {
+    let tokens = create_tokens(source, 0).expect("lexing failed");
+    Parser::new(tokens, 0).parse().expect("parsing failed")
+}
+
+// the IR of a valid program, before any optimization
+pub fn lower_source(source: &str) -> Module {
+    let mut scope = parse(source);
+    VariableTraverser::new().traverse(&mut scope).expect("type checking failed");
+    lower(&scope)
+}
+
+// the IR of a valid program after the passes of the optimization level
+pub fn optimize(source: &str, level: u8) -> Module {
+    let mut module = lower_source(source);
+    PassManager::new(level).run(&mut module);
+    module
+}
+
+// the error codes of all semantic errors the traverser finds in the source, in the order they were found
+pub fn error_codes(traverser: &mut VariableTraverser, source: &str) -> Vec<&'static str> {
+    let mut scope = parse(source);
+    match traverser.traverse(&mut scope) {
+        Ok(()) => vec!(),
+        Err(errors) => errors.iter().map(TypeError::code).collect(),
+    }
+}
+
+// the error codes of all semantic errors in the source, in the order they were found
+pub fn check(source: &str) -> Vec<&'static str> {
+    error_codes(&mut VariableTraverser::new(), source)
+}
+
+// a directory for the files of one test file, e.g. /tmp/hj-native-1234
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("hj-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// runs hj on the source file with the arguments and writes the result to the output path
+pub fn compile(source_path: &Path, args: &[&str], output_path: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hj")).arg(source_path).args(args).arg("-o").arg(output_path).output().unwrap()
+}
+
+// runs an external tool like cc, None if it isn't installed so the test can be skipped, but with CI set a missing tool
+// fails the test, so the tests can't pass there without running
+pub fn run_tool(command: &mut Command) -> Option<Output> {
+    match command.output() {
+        Ok(output) => Some(output),
+        Err(e) if env::var_os("CI").is_some() => panic!("{:?} can't be run on CI: {}", command.get_program(), e),
+        Err(_) => {
+            println!("{:?} isn't installed, skipping", command.get_program());
+            None
+        }
+    }
+}
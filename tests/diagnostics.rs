@@ -0,0 +1,115 @@
+// checks how diagnostics are rendered: the snippet of the source with the labels under it, notes and help
+
+use hj::output::{render_diagnostic, Diagnostic, Level, SourceFile};
+use hj::span::Span;
+
+// the span of the first occurrence of the text in the source
+fn find(source: &str, text: &str) -> Span {
+    let start = source.find(text).expect("text not in source");
+    let before = &source[..start];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap().chars().count() + 1;
+    Span::new(start, start + text.len(), line, column)
+}
+
+fn render(diagnostic: &Diagnostic, source: &str) -> String {
+    colored::control::set_override(false);
+    render_diagnostic(diagnostic, &SourceFile::new("test.hj", source))
+}
+
+#[test]
+fn primary_label_is_underlined_with_carets() {
+    let source = "let x = 5;\nlet y = x + true;\n";
+    let diagnostic = Diagnostic::error(String::from("Invalid types in binary operation")).with_code("E0017").with_span(find(source, "true"), "has type bool");
+    let expected = "\
+Error[E0017]: Invalid types in binary operation
+ --> test.hj:2:13
+  |
+2 | let y = x + true;
+  |             ^^^^ has type bool
+  |
+  = note: run \"hj --explain E0017\" for more information
+";
+    assert_eq!(render(&diagnostic, source), expected);
+}
+
+#[test]
+fn secondary_labels_are_underlined_with_dashes() {
+    let source = "let x = 5;\nprint(1);\nprint(2);\nx = true;\n";
+    let diagnostic = Diagnostic::error(String::from("Mismatched types"))
+        .with_span(find(source, "true"), "expected int")
+        .with_label(find(source, "x = 5"), "defined as int here");
+    // the labels are sorted by line, lines in between are left out
+    let expected = "\
+Error: Mismatched types
+ --> test.hj:4:5
+  |
+1 | let x = 5;
+  |     ----- defined as int here
+...
+4 | x = true;
+  |     ^^^^ expected int
+  |
+";
+    assert_eq!(render(&diagnostic, source), expected);
+
+    // both labels on the same line share it
+    let source = "let y = x;";
+    let diagnostic = Diagnostic::error(String::from("Undefined variable")).with_span(find(source, "x"), "not defined").with_label(find(source, "y"), "in here");
+    assert_eq!(render(&diagnostic, source), "Error: Undefined variable\n --> test.hj:1:9\n  |\n1 | let y = x;\n  |     - in here\n  |         ^ not defined\n  |\n");
+}
+
+#[test]
+fn notes_and_help_come_after_the_snippet() {
+    let source = "let f = 1.5 + 2;";
+    let diagnostic = Diagnostic::error(String::from("Implicit conversion"))
+        .with_level(Level::Warning)
+        .with_span(find(source, "2"), "")
+        .with_note("ints above 2^53 are rounded")
+        .with_help("write 2.0");
+    let expected = "\
+Warning: Implicit conversion
+ --> test.hj:1:15
+  |
+1 | let f = 1.5 + 2;
+  |               ^
+  |
+  = note: ints above 2^53 are rounded
+  = help: write 2.0
+";
+    assert_eq!(render(&diagnostic, source), expected);
+
+    // without labels there is no snippet
+    let diagnostic = Diagnostic::error(String::from("No such file")).with_note("check the path");
+    assert_eq!(render(&diagnostic, source), "Error: No such file\n --> test.hj\n  = note: check the path\n");
+    assert_eq!(render(&Diagnostic::error(String::from("No such file")), source), "Error: No such file\n");
+}
+
+#[test]
+fn columns_count_characters() {
+    // ü and € take several bytes, but only one column each
+    let source = "let s = \"ü€\"; let t = s + 'ö';";
+    let diagnostic = Diagnostic::error(String::from("Invalid types")).with_span(find(source, "'ö'"), "has type char").with_label(find(source, "\"ü€\""), "has type str");
+    let expected = "\
+Error: Invalid types
+ --> test.hj:1:27
+  |
+1 | let s = \"ü€\"; let t = s + 'ö';
+  |         ---- has type str
+  |                           ^^^ has type char
+  |
+";
+    assert_eq!(render(&diagnostic, source), expected);
+
+    // tabs in front of the label are kept, so the carets line up with the code
+    let source = "if true {\n\tlet x = y;\n}";
+    let diagnostic = Diagnostic::error(String::from("Undefined variable")).with_span(find(source, "y"), "not defined");
+    assert_eq!(render(&diagnostic, source), "Error: Undefined variable\n --> test.hj:2:10\n  |\n2 | \tlet x = y;\n  | \t        ^ not defined\n  |\n");
+}
+
+#[test]
+fn wide_line_numbers_widen_the_gutter() {
+    let source = format!("{}let x = y;", "\n".repeat(11));
+    let diagnostic = Diagnostic::error(String::from("Undefined variable")).with_span(find(&source, "y"), "not defined");
+    assert_eq!(render(&diagnostic, &source), "Error: Undefined variable\n  --> test.hj:12:9\n   |\n12 | let x = y;\n   |         ^ not defined\n   |\n");
+}
@@ -0,0 +1,150 @@
+// checks the error codes: every kind of error has its own stable code, and every code can be looked up with --explain
+
+mod common;
+
+use std::collections::HashSet;
+
+use hj::errors::{explain, LexError, ParseError, RuntimeError, TypeError};
+use hj::interpreter::Interpreter;
+use hj::lexer::create_tokens;
+use hj::nodes::{Operator, Type};
+use hj::parser::Parser;
+use hj::span::Span;
+use hj::variable_traverser::VariableTraverser;
+
+// one error of every kind, the matches have no wildcard, so a new kind of error doesn't compile until it's added here
+fn lex_errors() -> Vec<LexError> {
+    let span = Span::default();
+    let errors = vec!(
+        LexError::UnterminatedChar { span },
+        LexError::UnterminatedString { span },
+        LexError::InvalidNumber { span, found: String::from("1.2.3") },
+        LexError::UnexpectedCharacter { span, found: '$' },
+        LexError::InvalidEscape { span, found: String::from("\\q") },
+        LexError::InvalidCharLiteral { span, found: String::from("'ab'"), length: 2 },
+    );
+    for error in &errors {
+        match error {
+            LexError::UnterminatedChar { .. } | LexError::UnterminatedString { .. } | LexError::InvalidNumber { .. }
+            | LexError::UnexpectedCharacter { .. } | LexError::InvalidEscape { .. } | LexError::InvalidCharLiteral { .. } => (),
+        }
+    }
+    errors
+}
+
+fn parse_errors() -> Vec<ParseError> {
+    let span = Span::default();
+    let errors = vec!(
+        ParseError::UnexpectedEof { span: None, context: "while parsing", expected: "value" },
+        ParseError::UnexpectedToken { span, found: String::from(")"), context: "while parsing", expected: "value" },
+        ParseError::InvalidUnaryOperator { span, operator: String::from("*") },
+        ParseError::SpecialAssignmentInDefinition { span, operator: String::from("+=") },
+        ParseError::NumberOutOfRange { span, found: String::from("300u8"), t: Type::U8 },
+    );
+    for error in &errors {
+        match error {
+            ParseError::UnexpectedEof { .. } | ParseError::UnexpectedToken { .. } | ParseError::InvalidUnaryOperator { .. }
+            | ParseError::SpecialAssignmentInDefinition { .. } | ParseError::NumberOutOfRange { .. } => (),
+        }
+    }
+    errors
+}
+
+fn type_errors() -> Vec<TypeError> {
+    let span = Span::default();
+    let name = || String::from("f");
+    let errors = vec!(
+        TypeError::MismatchedDefinitionType { span, declared_span: span, declared: Type::Int, found: Type::Str },
+        TypeError::UninferableType { span },
+        TypeError::AssignmentToUndefined { span, name: name() },
+        TypeError::UndefinedVariable { span, name: name() },
+        TypeError::MismatchedAssignmentType { span, definition_span: span, expected: Type::Int, found: Type::Str },
+        TypeError::UndefinedFunction { span, name: name() },
+        TypeError::WrongArgumentCount { span, function: name(), expected: 1, found: 2 },
+        TypeError::InvalidUnaryOperand { span, operator: Operator::Not, found: Type::Int },
+        TypeError::InvalidBinaryOperands { span, operator: Operator::Plus, left_span: span, left: Type::Int, right_span: span, right: Type::Str },
+        TypeError::NonBoolCondition { span, statement: "if", found: Type::Int },
+        TypeError::OutsideOfLoop { span, keyword: "break" },
+        TypeError::DuplicateFunction { span, name: name(), previous_span: None },
+        TypeError::MismatchedArgumentType { span, function: name(), parameter_span: None, expected: Type::Int, found: Type::Str },
+        TypeError::VoidFunctionInExpression { span, name: name() },
+        TypeError::ReturnOutsideFunction { span },
+        TypeError::MismatchedReturnType { span, function: name(), expected: Type::Int, found: Type::Str },
+        TypeError::MissingReturnValue { span, function: name(), expected: Type::Int },
+        TypeError::UnexpectedReturnValue { span, function: name() },
+        TypeError::MissingReturn { span, function: name(), expected: Type::Int },
+        TypeError::NestedFunctionDefinition { span, name: name() },
+        TypeError::InvalidCast { span, expression_span: span, found: Type::Str, target: Type::Int },
+        TypeError::ImplicitFloatConversion { span, operator: Operator::Plus, found: Type::Int, float_span: span },
+        TypeError::DuplicateParameter { span, function: name(), name: String::from("a"), previous_span: span },
+    );
+    for error in &errors {
+        match error {
+            TypeError::MismatchedDefinitionType { .. } | TypeError::UninferableType { .. } | TypeError::AssignmentToUndefined { .. }
+            | TypeError::UndefinedVariable { .. } | TypeError::MismatchedAssignmentType { .. } | TypeError::UndefinedFunction { .. }
+            | TypeError::WrongArgumentCount { .. } | TypeError::InvalidUnaryOperand { .. } | TypeError::InvalidBinaryOperands { .. }
+            | TypeError::NonBoolCondition { .. } | TypeError::OutsideOfLoop { .. } | TypeError::DuplicateFunction { .. }
+            | TypeError::MismatchedArgumentType { .. } | TypeError::VoidFunctionInExpression { .. } | TypeError::ReturnOutsideFunction { .. }
+            | TypeError::MismatchedReturnType { .. } | TypeError::MissingReturnValue { .. } | TypeError::UnexpectedReturnValue { .. }
+            | TypeError::MissingReturn { .. } | TypeError::NestedFunctionDefinition { .. } | TypeError::InvalidCast { .. }
+            | TypeError::ImplicitFloatConversion { .. } | TypeError::DuplicateParameter { .. } => (),
+        }
+    }
+    errors
+}
+
+fn runtime_errors() -> Vec<RuntimeError> {
+    let span = Span::default();
+    let errors = vec!(RuntimeError::DivisionByZero { span }, RuntimeError::DivisionOverflow { span }, RuntimeError::StackOverflow { span, function: String::from("f") });
+    for error in &errors {
+        match error {
+            RuntimeError::DivisionByZero { .. } | RuntimeError::DivisionOverflow { .. } | RuntimeError::StackOverflow { .. } => (),
+        }
+    }
+    errors
+}
+
+fn all_codes() -> Vec<&'static str> {
+    let mut codes: Vec<&'static str> = lex_errors().iter().map(LexError::code).collect();
+    codes.extend(parse_errors().iter().map(ParseError::code));
+    codes.extend(type_errors().iter().map(TypeError::code));
+    codes.extend(runtime_errors().iter().map(RuntimeError::code));
+    codes
+}
+
+#[test]
+fn every_stage_reports_its_codes() {
+    let lexed: Vec<&str> = create_tokens("let x = $;", 0).err().unwrap().iter().map(LexError::code).collect();
+    assert_eq!(lexed, vec!("E0004"));
+
+    let tokens = create_tokens("let = 5;", 0).unwrap();
+    let parsed: Vec<&str> = Parser::new(tokens, 0).parse().err().unwrap().iter().map(ParseError::code).collect();
+    assert_eq!(parsed, vec!("E0006"));
+
+    assert_eq!(common::check("print(y);"), vec!("E0012"));
+
+    let mut scope = common::parse("let zero = 0; print(1 / zero);");
+    VariableTraverser::new().traverse(&mut scope).expect("type checking failed");
+    let mut output = vec!();
+    assert_eq!(Interpreter::new(&mut output).run(&scope).unwrap_err().code(), "E0031");
+}
+
+#[test]
+fn codes_are_unique() {
+    let codes = all_codes();
+    let mut seen = HashSet::new();
+    for code in &codes {
+        assert!(code.len() == 5 && code.starts_with('E') && code[1..].chars().all(|c| c.is_ascii_digit()), "{} isn't a valid code", code);
+        assert!(seen.insert(code), "{} is used for two kinds of errors", code);
+    }
+}
+
+#[test]
+fn every_code_has_an_explanation() {
+    for code in all_codes() {
+        assert!(explain(code).is_some(), "{} has no explanation for --explain", code);
+        assert_eq!(explain(&code.to_lowercase()), explain(code));
+    }
+    assert_eq!(explain("E9999"), None);
+    assert_eq!(explain("0001"), None);
+}
@@ -0,0 +1,132 @@
+// checks the shape of the AST the expression parser produces for tricky operator combinations
+
+use hj::lexer::create_tokens;
+use hj::nodes::*;
+use hj::parser::Parser;
+
+// writes an expression as an s-expression, e.g. "(- (- a b) c)"
+fn to_sexpr(expression: &ExpressionNode) -> String {
+    match expression {
+        ExpressionNode::BinaryOperationNode(node) => format!("({} {} {})", node.operator.to_str(), to_sexpr(&node.left_expr.node), to_sexpr(&node.right_expr.node)),
+        ExpressionNode::UnaryOperationNode(node) => format!("({}{})", node.operator.to_str(), to_sexpr(&node.expression.node)),
+        ExpressionNode::VariableNode(node) => node.name.clone(),
+        ExpressionNode::IntLiteralNode(node) => node.value.to_string(),
+        ExpressionNode::FloatLiteralNode(node) => node.value.to_string(),
+        ExpressionNode::BoolLiteralNode(node) => node.value.to_string(),
+        ExpressionNode::StringLiteralNode(node) => format!("{:?}", node.value),
+        ExpressionNode::CharLiteralNode(node) => format!("{:?}", node.value),
+        ExpressionNode::FunctionCallNode(node) => {
+            let args: Vec<String> = node.args.iter().map(|arg| to_sexpr(&arg.node)).collect();
+            format!("{}({})", node.function.name, args.join(", "))
+        }
+        // the parser doesn't produce conversions, only the type checker
+        ExpressionNode::ConversionNode(node) => to_sexpr(&node.expression.node),
+        ExpressionNode::CastNode(node) => format!("(as {} {})", to_sexpr(&node.expression.node), node.target),
+    }
+}
+
+fn parse_expression(source: &str) -> String {
+    let tokens = create_tokens(&format!("let x = {};", source), 0).expect("lexing failed");
+    let scope = Parser::new(tokens, 0).parse().expect("parsing failed");
+    match &scope.commands[0] {
+        CommandNode::VariableDefinitionNode(node) => to_sexpr(&node.expression.as_ref().unwrap().node),
+        _ => panic!("expected a variable definition"),
+    }
+}
+
+// the source code the spans of the expression and of its operands cover
+fn span_texts(source: &str) -> Vec<String> {
+    let source = format!("let x = {};", source);
+    let tokens = create_tokens(&source, 0).expect("lexing failed");
+    let scope = Parser::new(tokens, 0).parse().expect("parsing failed");
+    let expression = match &scope.commands[0] {
+        CommandNode::VariableDefinitionNode(node) => &node.expression.as_ref().unwrap().node,
+        _ => panic!("expected a variable definition"),
+    };
+    let mut spans = vec!(expression.span());
+    match expression {
+        ExpressionNode::BinaryOperationNode(node) => spans.extend([node.left_expr.node.span(), node.right_expr.node.span()]),
+        ExpressionNode::UnaryOperationNode(node) => spans.push(node.expression.node.span()),
+        ExpressionNode::CastNode(node) => spans.push(node.expression.node.span()),
+        _ => (),
+    }
+    spans.iter().map(|span| source[span.start..span.end].to_string()).collect()
+}
+
+#[test]
+fn arithmetic_is_left_associative() {
+    assert_eq!(parse_expression("a - b - c"), "(- (- a b) c)");
+    assert_eq!(parse_expression("a / b / c"), "(/ (/ a b) c)");
+    assert_eq!(parse_expression("a % b * c"), "(* (% a b) c)");
+    assert_eq!(parse_expression("a * b % c / d"), "(/ (% (* a b) c) d)");
+    assert_eq!(parse_expression("a - b + c - d"), "(- (+ (- a b) c) d)");
+}
+
+#[test]
+fn multiplication_binds_more_tightly_than_addition() {
+    assert_eq!(parse_expression("a + b * c"), "(+ a (* b c))");
+    assert_eq!(parse_expression("a * b + c"), "(+ (* a b) c)");
+    assert_eq!(parse_expression("a - b * c - d"), "(- (- a (* b c)) d)");
+    assert_eq!(parse_expression("(a + b) * c"), "(* (+ a b) c)");
+    assert_eq!(parse_expression("a * (b - c) % d"), "(% (* a (- b c)) d)");
+}
+
+#[test]
+fn prefix_operators_bind_most_tightly() {
+    assert_eq!(parse_expression("-a * b"), "(* (-a) b)");
+    assert_eq!(parse_expression("-a - -b"), "(- (-a) (-b))");
+    assert_eq!(parse_expression("- -a"), "(-(-a))");
+    assert_eq!(parse_expression("!a && b"), "(&& (!a) b)");
+    assert_eq!(parse_expression("-(a + b) * c"), "(* (-(+ a b)) c)");
+}
+
+#[test]
+fn comparison_and_logical_precedence() {
+    assert_eq!(parse_expression("a + b < c * d"), "(< (+ a b) (* c d))");
+    assert_eq!(parse_expression("a < b == c > d"), "(== (< a b) (> c d))");
+    assert_eq!(parse_expression("a == b != c"), "(!= (== a b) c)");
+    assert_eq!(parse_expression("a || b && c"), "(|| a (&& b c))");
+    assert_eq!(parse_expression("a && b || c && d"), "(|| (&& a b) (&& c d))");
+    assert_eq!(parse_expression("a || b || c"), "(|| (|| a b) c)");
+    assert_eq!(parse_expression("a && b && c"), "(&& (&& a b) c)");
+    assert_eq!(parse_expression("a < b < c"), "(< (< a b) c)");
+    assert_eq!(parse_expression("a >= 1 && a <= 10 || !b"), "(|| (&& (>= a 1) (<= a 10)) (!b))");
+}
+
+#[test]
+fn shifts_bind_between_comparisons_and_addition() {
+    assert_eq!(parse_expression("a << b + c"), "(<< a (+ b c))");
+    assert_eq!(parse_expression("a + b >> c"), "(>> (+ a b) c)");
+    assert_eq!(parse_expression("a << b < c >> d"), "(< (<< a b) (>> c d))");
+    assert_eq!(parse_expression("a >> b << c"), "(<< (>> a b) c)");
+    assert_eq!(parse_expression("a * b << -c"), "(<< (* a b) (-c))");
+}
+
+#[test]
+fn casts_bind_between_binary_and_prefix_operators() {
+    assert_eq!(parse_expression("a * b as float"), "(* a (as b float))");
+    assert_eq!(parse_expression("a as float * b"), "(* (as a float) b)");
+    assert_eq!(parse_expression("-a as u8"), "(as (-a) u8)");
+    assert_eq!(parse_expression("!a as int + 1"), "(+ (as (!a) int) 1)");
+    assert_eq!(parse_expression("a as u8 as char"), "(as (as a u8) char)");
+    assert_eq!(parse_expression("(a + b) as i16 << c as u8"), "(<< (as (+ a b) i16) (as c u8))");
+    assert_eq!(parse_expression("f(a as int, b) as float"), "(as f((as a int), b) float)");
+}
+
+#[test]
+fn spans_include_parentheses() {
+    assert_eq!(span_texts("a + b * c"), vec!("a + b * c", "a", "b * c"));
+    assert_eq!(span_texts("(a + b) * c"), vec!("(a + b) * c", "(a + b)", "c"));
+    assert_eq!(span_texts("a * (b - c)"), vec!("a * (b - c)", "a", "(b - c)"));
+    assert_eq!(span_texts("-(a)"), vec!("-(a)", "(a)"));
+    assert_eq!(span_texts("((a))"), vec!("((a))"));
+    assert_eq!(span_texts("(f(a)) as u8"), vec!("(f(a)) as u8", "(f(a))"));
+}
+
+#[test]
+fn invalid_operator_positions_are_errors() {
+    for source in ["let x = a ! b;", "let x = * a;", "let x = a +;", "let x = a + b", "let x = a as;", "let x = a as b;", "let x = as int;", "let x = a as 5;"] {
+        let tokens = create_tokens(source, 0).expect("lexing failed");
+        assert!(Parser::new(tokens, 0).parse().is_err(), "\"{}\" should not parse", source);
+    }
+}
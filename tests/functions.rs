@@ -0,0 +1,96 @@
+// checks the errors in function definitions and calls: arguments, return values and where functions can be defined
+
+mod common;
+
+use common::check;
+
+#[test]
+fn calls_need_the_right_number_of_arguments() {
+    assert_eq!(check("fn add(int a, int b) -> int { return a + b; } let x = add(1, 2); print(x);"), Vec::<&str>::new());
+    // E0015: wrong argument count
+    assert_eq!(check("fn add(int a, int b) -> int { return a + b; } let x = add(1);"), vec!("E0015"));
+    assert_eq!(check("fn add(int a, int b) -> int { return a + b; } let x = add(1, 2, 3);"), vec!("E0015"));
+    assert_eq!(check("fn nothing() { } nothing(1);"), vec!("E0015"));
+    assert_eq!(check("print();"), vec!("E0015"));
+}
+
+#[test]
+fn arguments_need_the_parameter_types() {
+    assert_eq!(check("fn f(int a, float b, bool c, str d, char e) { } f(1, 2.5, true, \"s\", 'c');"), Vec::<&str>::new());
+    // E0022: mismatched argument type, every wrong argument is reported
+    assert_eq!(check("fn f(int a, bool b) { } f(true, 1);"), vec!("E0022", "E0022"));
+    assert_eq!(check("fn f(str s) { } f('c');"), vec!("E0022"));
+    // arguments are converted like in a definition: ints widen and literals adapt, but ints don't become floats
+    assert_eq!(check("fn g(int y, u16 z) { } let i8 a = 3; let u8 b = 4; g(a, b); g(1, 60000);"), Vec::<&str>::new());
+    assert_eq!(check("fn g(u8 y) { } let i8 a = 3; g(a); g(300);"), vec!("E0022", "E0022"));
+    assert_eq!(check("fn half(float x) { } half(3);"), vec!("E0022"));
+    // E0023: a function without a return type has no value that could be passed on
+    assert_eq!(check("fn nothing() { } fn f(int a) { } f(nothing());"), vec!("E0023"));
+}
+
+#[test]
+fn return_values_need_the_return_type() {
+    assert_eq!(check("fn f(int a) -> bool { if a > 0 { return true; } return false; } let bool b = f(1);"), Vec::<&str>::new());
+    // E0025: mismatched return type, E0026: missing return value, E0027: unexpected return value
+    assert_eq!(check("fn f() -> int { return true; }"), vec!("E0025"));
+    assert_eq!(check("fn f() -> str { return 'c'; }"), vec!("E0025"));
+    assert_eq!(check("fn f() -> int { return; }"), vec!("E0026"));
+    assert_eq!(check("fn f() { return 1; }"), vec!("E0027"));
+    // E0009: the result has the return type
+    assert_eq!(check("fn f() -> int { return 1; } let bool b = f();"), vec!("E0009"));
+}
+
+#[test]
+fn every_path_has_to_return() {
+    assert_eq!(check("fn sign(int x) -> int { if x < 0 { return -1; } else if x > 0 { return 1; } else { return 0; } }"), Vec::<&str>::new());
+    assert_eq!(check("fn f() -> int { while true { } return 1; }"), Vec::<&str>::new());
+    // E0028: missing return
+    assert_eq!(check("fn f() -> int { }"), vec!("E0028"));
+    assert_eq!(check("fn sign(int x) -> int { if x < 0 { return -1; } }"), vec!("E0028"));
+    assert_eq!(check("fn sign(int x) -> int { if x < 0 { return -1; } else if x > 0 { return 1; } }"), vec!("E0028"));
+    // a return inside of a loop doesn't count, the loop might not run at all
+    assert_eq!(check("fn f(int x) -> int { while x > 0 { return x; } }"), vec!("E0028"));
+    // functions without a return type don't need a return
+    assert_eq!(check("fn f(int x) { if x > 0 { return; } }"), Vec::<&str>::new());
+}
+
+#[test]
+fn functions_can_be_called_before_their_definition() {
+    assert_eq!(check("let x = double(2); print(x); fn double(int a) -> int { return a * 2; }"), Vec::<&str>::new());
+    assert_eq!(check("fn even(int n) -> bool { if n == 0 { return true; } return odd(n - 1); } fn odd(int n) -> bool { if n == 0 { return false; } return even(n - 1); }"), Vec::<&str>::new());
+    // the arguments are checked against the later definition as well
+    assert_eq!(check("double(true); fn double(int a) -> int { return a * 2; }"), vec!("E0022"));
+}
+
+#[test]
+fn functions_can_only_be_defined_at_the_top_level() {
+    // E0029: nested function definition, the body of the nested function isn't checked
+    assert_eq!(check("fn outer() { fn inner() { } }"), vec!("E0029"));
+    assert_eq!(check("if true { fn f() -> int { return true; } }"), vec!("E0029"));
+    assert_eq!(check("while true { fn f() { } }"), vec!("E0029"));
+    // E0014: the nested function isn't defined anywhere
+    assert_eq!(check("fn outer() { fn inner() { } inner(); }"), vec!("E0029", "E0014"));
+}
+
+#[test]
+fn return_only_works_in_functions() {
+    // E0024: return outside of a function
+    assert_eq!(check("return;"), vec!("E0024"));
+    assert_eq!(check("if true { return 1; }"), vec!("E0024"));
+    assert_eq!(check("while true { return; }"), vec!("E0024"));
+    // the function ends with its body
+    assert_eq!(check("fn f() { return; } return;"), vec!("E0024"));
+}
+
+#[test]
+fn names_have_to_be_unique() {
+    // E0021: duplicate function, E0038: duplicate parameter
+    assert_eq!(check("fn f() { } fn f(int a) { }"), vec!("E0021"));
+    assert_eq!(check("fn print(int a) { }"), vec!("E0021"));
+    assert_eq!(check("fn add(int a, int a) -> int { return a + a; }"), vec!("E0038"));
+    assert_eq!(check("fn f(int a, float b, bool a, str b) { }"), vec!("E0038", "E0038"));
+    // the body sees the first parameter with the name
+    assert_eq!(check("fn f(int a, bool a) { let int x = a; }"), vec!("E0038"));
+    // a parameter can have the name of its function, and the body can shadow a parameter
+    assert_eq!(check("fn f(int f) { let f = 1.5; print(f); }"), Vec::<&str>::new());
+}
@@ -0,0 +1,119 @@
+// checks the typing of the sized and unsigned int types: which implicit conversions exist and which mixes are errors
+
+mod common;
+
+use common::{check, parse};
+use hj::nodes::*;
+use hj::variable_traverser::VariableTraverser;
+
+// the expression of the last variable definition after type checking
+fn checked_expression(source: &str) -> TExpressionNode {
+    let mut scope = parse(source);
+    VariableTraverser::new().traverse(&mut scope).expect("type checking failed");
+    match scope.commands.pop() {
+        Some(CommandNode::VariableDefinitionNode(node)) => *node.expression.unwrap(),
+        _ => panic!("expected a variable definition at the end"),
+    }
+}
+
+// the first note of the first error in the source
+fn first_note(source: &str) -> String {
+    let mut scope = parse(source);
+    let errors = VariableTraverser::new().traverse(&mut scope).expect_err("no errors");
+    errors[0].to_diagnostic().notes[0].clone()
+}
+
+#[test]
+fn literals_take_the_expected_type() {
+    assert_eq!(check("let u8 x = 255; let i8 y = -128; let u16 z = 2 * 1000 + 5; let uint w = 0;"), Vec::<&str>::new());
+    assert_eq!(check("let u8 x = 1; x += 2; x = x * 3 - 1; let bool small = x < 200;"), Vec::<&str>::new());
+    assert_eq!(check("fn f(u32 a) -> u16 { return 7; } let u16 x = f(4000000000);"), Vec::<&str>::new());
+    // E0009: mismatched definition type, E0013: mismatched assignment type, E0022: mismatched argument type
+    assert_eq!(check("let u8 x = 256;"), vec!("E0009"));
+    assert_eq!(check("let i8 x = -129;"), vec!("E0009"));
+    assert_eq!(check("let u8 x = -1;"), vec!("E0009"));
+    assert_eq!(check("let u8 x = 1; x = 300;"), vec!("E0013"));
+    assert_eq!(check("fn f(i16 a) { } f(40000);"), vec!("E0022"));
+    // every literal of the expression has to fit, not only the result
+    assert_eq!(check("let u8 x = 300 - 100;"), vec!("E0009"));
+
+    // the literal itself becomes a u8, there is no conversion
+    let expression = checked_expression("let u8 x = 200;");
+    assert_eq!(expression.t, Some(Type::U8));
+    assert!(matches!(expression.node, ExpressionNode::IntLiteralNode(_)));
+}
+
+#[test]
+fn suffixes_fix_the_type_of_literals() {
+    assert_eq!(check("let x = 5u8; let u8 y = x; let i16 z = -3i16;"), Vec::<&str>::new());
+    assert_eq!(checked_expression("let x = 18446744073709551615u64;").t, Some(Type::UInt));
+    assert_eq!(checked_expression("let x = 7i64;").t, Some(Type::Int));
+    // a suffixed literal doesn't adapt to another type
+    assert_eq!(check("let i8 x = 5u8;"), vec!("E0009"));
+    assert_eq!(check("let int x = 5u64;"), vec!("E0009"));
+}
+
+#[test]
+fn ints_widen_implicitly() {
+    assert_eq!(check("let i8 a = 1; let i16 b = a; let int c = b; let i32 d = a;"), Vec::<&str>::new());
+    assert_eq!(check("let u8 a = 1; let u32 b = a; let uint c = b; let i16 d = a; let int e = b;"), Vec::<&str>::new());
+    assert_eq!(check("fn f(int a) -> uint { let u16 x = 1; return x; } let u8 y = 2; f(y);"), Vec::<&str>::new());
+
+    // the widened value gets a conversion around it
+    let expression = checked_expression("let i8 a = 1; let int b = a;");
+    assert_eq!(expression.t, Some(Type::Int));
+    match &expression.node {
+        ExpressionNode::ConversionNode(node) => assert_eq!(node.expression.t, Some(Type::I8)),
+        _ => panic!("expected a conversion"),
+    }
+}
+
+#[test]
+fn narrowing_and_signedness_changes_are_errors() {
+    assert_eq!(check("let int a = 1; let i32 b = a;"), vec!("E0009"));
+    assert_eq!(check("let i8 a = 1; let u8 b = a;"), vec!("E0009"));
+    assert_eq!(check("let i8 a = 1; let uint b = a;"), vec!("E0009"));
+    assert_eq!(check("let u32 a = 1; let i32 b = a;"), vec!("E0009"));
+    assert_eq!(check("let uint a = 1; let int b = a;"), vec!("E0009"));
+    // E0025: mismatched return type
+    assert_eq!(check("fn f(u16 a) -> u8 { return a; }"), vec!("E0025"));
+    // ints don't become floats in definitions and assignments
+    assert_eq!(check("let u8 a = 1; let float b = a;"), vec!("E0009"));
+}
+
+#[test]
+fn binary_operations_use_the_wider_type() {
+    assert_eq!(checked_expression("let i8 a = 1; let i32 b = 2; let c = a + b;").t, Some(Type::I32));
+    assert_eq!(checked_expression("let u8 a = 1; let i16 b = 2; let c = a * b;").t, Some(Type::I16));
+    assert_eq!(checked_expression("let u16 a = 1; let c = a + 1;").t, Some(Type::U16));
+    assert_eq!(checked_expression("let u16 a = 1; let c = 70000 + a;").t, Some(Type::Int));
+    assert_eq!(checked_expression("let uint a = 1; let c = a - 1;").t, Some(Type::UInt));
+    assert_eq!(checked_expression("let u8 a = 1; let c = a < 200;").t, Some(Type::Bool));
+    assert_eq!(checked_expression("let u32 a = 1; let c = a / 2.0;").t, Some(Type::Float));
+    // E0017: invalid binary operands, there is no type that can hold both sides
+    assert_eq!(check("let i8 a = 1; let u8 b = 2; let c = a + b;"), vec!("E0017"));
+    assert_eq!(check("let int a = 1; let uint b = 2; let c = a == b;"), vec!("E0017"));
+    assert_eq!(check("let uint a = 1; let c = a < -1;"), vec!("E0017"));
+    assert_eq!(check("let u8 a = 1; let c = a + 5u16 + true;"), vec!("E0017"));
+    // the note only talks about mixing int types if the operator works on ints
+    assert!(first_note("let i8 a = 1; let u8 b = 2; let c = a == b;").starts_with("two different int types"));
+    assert_eq!(first_note("let c = 1 && 2;"), "logical operations are only defined for bool");
+}
+
+#[test]
+fn shifts_keep_the_type_of_the_shifted_value() {
+    assert_eq!(checked_expression("let u8 a = 1; let int n = 3; let c = a << n;").t, Some(Type::U8));
+    assert_eq!(checked_expression("let i16 a = 1; let uint n = 3; let c = a >> n;").t, Some(Type::I16));
+    assert_eq!(checked_expression("let c = 1 << 3u8;").t, Some(Type::Int));
+    assert_eq!(check("let u8 a = 1; a <<= 2; a >>= a;"), Vec::<&str>::new());
+    assert_eq!(check("let a = 1.5 << 2;"), vec!("E0017"));
+    assert_eq!(check("let a = 1 >> 'a';"), vec!("E0017"));
+}
+
+#[test]
+fn unsigned_ints_cant_be_negated() {
+    // E0016: invalid unary operand
+    assert_eq!(check("let uint a = 1; let b = -a;"), vec!("E0016"));
+    assert_eq!(check("let b = -5u8;"), vec!("E0016"));
+    assert_eq!(check("let i8 a = 1; let b = -a; let u8 c = 5; let d = +c;"), Vec::<&str>::new());
+}
@@ -0,0 +1,138 @@
+// runs programs with "hj run", and compares the interpreter with the compiled executables
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn temp_dir() -> PathBuf {
+    common::temp_dir("interpreter")
+}
+
+fn interpret_file(path: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hj")).arg("run").arg(path).output().unwrap()
+}
+
+fn interpret(name: &str, source: &str) -> Output {
+    let path = temp_dir().join(format!("{}.hj", name));
+    fs::write(&path, source).unwrap();
+    interpret_file(&path)
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn prints_every_type() {
+    let output = interpret("prints_every_type", "print(1); print(-2.5); print(true); print('€'); print(\"text\");");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "1\n-2.500000\ntrue\n€\ntext\n");
+}
+
+#[test]
+fn calls_functions() {
+    let source = "
+        fn fib(int n) -> int {
+            if n < 2 {
+                return n;
+            }
+            return fib(n - 1) + fib(n - 2);
+        }
+        fn greet(str name) {
+            print(name);
+            return;
+            print(\"unreachable\");
+        }
+        fn first_even(int limit) -> int {
+            let i = 1;
+            while i < limit {
+                if i % 2 == 0 {
+                    return i;
+                }
+                i += 1;
+            }
+            return -1;
+        }
+        print(fib(20));
+        greet(\"hj\");
+        print(first_even(10) * 1.5);
+    ";
+    let output = interpret("calls_functions", source);
+    assert_eq!(stdout(&output), "6765\nhj\n3.000000\n");
+}
+
+#[test]
+fn exits_with_status_code() {
+    let output = interpret("exits_with_status_code", "fn stop() { exit(42); } print(1); stop(); print(2);");
+    assert_eq!(output.status.code(), Some(42));
+    assert_eq!(stdout(&output), "1\n");
+}
+
+#[test]
+fn recursion_goes_as_deep_as_in_compiled_programs() {
+    let source = "fn depth(int n) -> int { if n == 0 { return 0; } return depth(n - 1) + 1; } print(depth(100000));";
+    let output = interpret("deep_recursion", source);
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "100000\n");
+}
+
+#[test]
+fn reports_endless_recursion() {
+    let output = interpret("endless_recursion", "fn forever(int n) -> int { return forever(n + 1); } print(1); print(forever(0));");
+    assert!(!output.status.success());
+    let text = stdout(&output);
+    assert!(text.starts_with("1\n"));
+    assert!(text.contains("E0039"));
+}
+
+#[test]
+fn reports_division_by_zero() {
+    let output = interpret("reports_division_by_zero", "let zero = 0; print(1); print(5 % zero);");
+    assert!(!output.status.success());
+    let text = stdout(&output);
+    assert!(text.starts_with("1\n"));
+    assert!(text.contains("E0031"));
+}
+
+// the right side of && and || prints its name when it's evaluated
+#[test]
+fn logical_operators_short_circuit() {
+    let output = interpret_file(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs/short_circuit.hj"));
+    assert_eq!(stdout(&output), "false\ntrue\nand\nfalse\nor\ntrue\nleft\nright\ntrue\nleft\nright\nfalse\nfalse\na\ntrue\nloop\nloop\nloop\n3\n");
+}
+
+#[test]
+fn only_prints_type_errors_for_invalid_programs() {
+    let output = interpret("only_prints_type_errors", "print(1); print(1 + true);");
+    assert!(!output.status.success());
+    assert!(!stdout(&output).starts_with("1\n"));
+    assert!(stdout(&output).contains("E0017"));
+}
+
+// every program in tests/programs has to produce the same output when it's interpreted and when it's compiled (at every optimization level)
+#[test]
+fn matches_compiled_programs() {
+    let programs = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("programs");
+    let mut count = 0;
+    for entry in fs::read_dir(programs).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+
+        let interpreted = interpret_file(&path);
+        assert!(interpreted.status.success(), "{} failed in the interpreter:\n{}", name, stdout(&interpreted));
+
+        for level in ["-O0", "-O1", "-O2"] {
+            let exe_path = temp_dir().join(format!("{}{}", name, level));
+            let compile = common::compile(&path, &[level], &exe_path);
+            assert!(compile.status.success(), "{} failed to compile with {}:\n{}", name, level, stdout(&compile));
+            let compiled = Command::new(&exe_path).output().unwrap();
+
+            assert_eq!(stdout(&interpreted), stdout(&compiled), "different output for {} with {}", name, level);
+            assert_eq!(interpreted.status.code(), compiled.status.code(), "different exit code for {} with {}", name, level);
+        }
+        count += 1;
+    }
+    assert!(count > 0);
+}
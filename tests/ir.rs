@@ -0,0 +1,99 @@
+// checks the IR the type-checked AST gets lowered to
+
+mod common;
+
+use common::lower_source;
+use hj::ir::*;
+
+#[test]
+fn straight_line_code_is_one_block() {
+    let module = lower_source("let x = 1; x += 2 * x; let y = -x; print(y);");
+    let expected = "\
+fn <main>() {
+bb0:
+    %0: int = copy 1
+    %1: int = mul 2, %0
+    %2: int = add %0, %1
+    %0: int = copy %2
+    %3: int = neg %0
+    %4: int = copy %3
+    call print(%4)
+    return
+}
+";
+    assert_eq!(module.to_text(), expected);
+}
+
+#[test]
+fn ints_are_converted_when_used_with_floats() {
+    let module = lower_source("let x = 2; let y = x * 1.5; y /= x;");
+    let expected = "\
+fn <main>() {
+bb0:
+    %0: int = copy 2
+    %1: float = convert %0
+    %2: float = mul %1, 1.5
+    %3: float = copy %2
+    %4: float = convert %0
+    %5: float = div %3, %4
+    %3: float = copy %5
+    return
+}
+";
+    assert_eq!(module.to_text(), expected);
+}
+
+#[test]
+fn loops_jump_back_to_their_condition() {
+    let module = lower_source("let i = 0; while i < 10 { i += 1; if i == 5 { continue; } if i == 7 { break; } } print(i);");
+    let main = &module.main;
+    let predecessors = main.predecessors();
+    // the condition block is reached from the entry, the end of the body and the continue
+    let cond_block = match main.blocks[0].terminator {
+        Terminator::Jump(target) => target,
+        _ => panic!("expected a jump into the loop"),
+    };
+    assert_eq!(predecessors[cond_block.0].len(), 3);
+    let end_block = match &main.block(cond_block).terminator {
+        Terminator::Branch { else_block, .. } => *else_block,
+        _ => panic!("expected a branch on the loop condition"),
+    };
+    // the end of the loop is reached when the condition is false and from the break
+    assert_eq!(predecessors[end_block.0].len(), 2);
+    assert!(matches!(&main.block(end_block).insts[0], Inst::Call { function, .. } if function == "print"));
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    let module = lower_source("fn f() -> bool { print(1); return true; } let a = false && f(); let b = true || f();");
+    let main = &module.main;
+    // the calls are in their own blocks, which are only reached through a branch
+    let predecessors = main.predecessors();
+    let call_blocks: Vec<usize> = (0..main.blocks.len())
+        .filter(|i| main.blocks[*i].insts.iter().any(|inst| matches!(inst, Inst::Call { .. })))
+        .collect();
+    assert_eq!(call_blocks.len(), 2);
+    for block in call_blocks {
+        let pred = predecessors[block][0];
+        assert!(matches!(main.block(pred).terminator, Terminator::Branch { .. }));
+    }
+}
+
+#[test]
+fn code_after_return_is_removed() {
+    let module = lower_source("fn f(int n) -> int { if n > 0 { return 1; } else { return 2; } print(n); } print(f(3));");
+    let f = module.function("f").unwrap();
+    let expected = "\
+fn f(%0: int) -> int {
+bb0:
+    %1: bool = gt %0, 0
+    branch %1, bb1, bb2
+bb1: ; preds: bb0
+    return 1
+bb2: ; preds: bb0
+    return 2
+}
+";
+    assert_eq!(f.to_text(), expected);
+    assert_eq!(module.main.blocks.len(), 1);
+}
@@ -0,0 +1,196 @@
+// checks how the lexer and the parser read literals, and the errors for invalid ones
+
+use hj::errors::LexError;
+use hj::lexer::create_tokens;
+use hj::nodes::*;
+use hj::parser::Parser;
+
+// parses the literal as the expression of a variable definition
+fn parse_literal(source: &str) -> ScopeNode {
+    let tokens = create_tokens(&format!("let x = {};", source), 0).expect("lexing failed");
+    Parser::new(tokens, 0).parse().expect("parsing failed")
+}
+
+fn expression(scope: &ScopeNode) -> &ExpressionNode {
+    match &scope.commands[0] {
+        CommandNode::VariableDefinitionNode(node) => &node.expression.as_ref().unwrap().node,
+        _ => panic!("expected a variable definition"),
+    }
+}
+
+fn string_value(source: &str) -> String {
+    match expression(&parse_literal(source)) {
+        ExpressionNode::StringLiteralNode(node) => node.value.clone(),
+        _ => panic!("{} isn't a string literal", source),
+    }
+}
+
+fn char_value(source: &str) -> char {
+    match expression(&parse_literal(source)) {
+        ExpressionNode::CharLiteralNode(node) => node.value,
+        _ => panic!("{} isn't a char literal", source),
+    }
+}
+
+// the codes of the lexing errors and the source text they point to
+fn lex_errors(source: &str) -> Vec<(&'static str, String)> {
+    match create_tokens(source, 0) {
+        Ok(_) => vec!(),
+        Err(errors) => errors.iter().map(|e: &LexError| (e.code(), source[e.span().start..e.span().end].to_string())).collect(),
+    }
+}
+
+#[test]
+fn escape_sequences_are_decoded() {
+    assert_eq!(string_value(r#""say \"hi\"""#), "say \"hi\"");
+    assert_eq!(string_value(r#""a\tb\r\n\\\0""#), "a\tb\r\n\\\0");
+    assert_eq!(string_value(r#""it's \'quoted\'""#), "it's 'quoted'");
+    assert_eq!(string_value(r#""\x41\x7f\x0a""#), "A\x7f\n");
+    assert_eq!(string_value(r#""\u{41}\u{e9}\u{1F600}\u{10FFFF}""#), "Aé😀\u{10FFFF}");
+    assert_eq!(string_value(r#""""#), "");
+    assert_eq!(string_value(r#""\\""#), "\\");
+
+    assert_eq!(char_value(r"'\n'"), '\n');
+    assert_eq!(char_value(r"'\''"), '\'');
+    assert_eq!(char_value(r#"'"'"#), '"');
+    assert_eq!(char_value(r"'\\'"), '\\');
+    assert_eq!(char_value(r"'\0'"), '\0');
+    assert_eq!(char_value(r"'\u{20AC}'"), '€');
+    assert_eq!(char_value("'ü'"), 'ü');
+}
+
+#[test]
+fn invalid_escape_sequences_are_errors() {
+    // E0033: invalid escape sequence, the span only covers the escape sequence
+    assert_eq!(lex_errors(r#"print("C:\Users");"#), vec!(("E0033", r"\U".to_string())));
+    assert_eq!(lex_errors(r#"print("\x8");"#), vec!(("E0033", r"\x8".to_string())));
+    assert_eq!(lex_errors(r#"print("\x80");"#), vec!(("E0033", r"\x80".to_string())));
+    assert_eq!(lex_errors(r#"print("\u{110000}");"#), vec!(("E0033", r"\u{110000}".to_string())));
+    assert_eq!(lex_errors(r#"print("\u{D800}");"#), vec!(("E0033", r"\u{D800}".to_string())));
+    assert_eq!(lex_errors(r#"print("\u{}");"#), vec!(("E0033", r"\u{}".to_string())));
+    assert_eq!(lex_errors(r#"print("\u41");"#), vec!(("E0033", r"\u".to_string())));
+    assert_eq!(lex_errors(r"print('\q');"), vec!(("E0033", r"\q".to_string())));
+    // the rest of the literal is skipped, so the lexer continues after it
+    assert_eq!(lex_errors(r#"print("ü\a\b"); print("\c");"#), vec!(("E0033", r"\a".to_string()), ("E0033", r"\c".to_string())));
+}
+
+#[test]
+fn escaped_quotes_dont_end_literals() {
+    // E0002: unterminated string, E0001: unterminated char
+    assert_eq!(lex_errors(r#"print("abc\");"#), vec!(("E0002", "\"".to_string())));
+    assert_eq!(lex_errors(r"print('\');"), vec!(("E0001", "'".to_string())));
+}
+
+#[test]
+fn char_literals_contain_one_character() {
+    // E0034: invalid char literal, the span covers the whole literal
+    assert_eq!(lex_errors("let c = 'ab';"), vec!(("E0034", "'ab'".to_string())));
+    assert_eq!(lex_errors("let c = '';"), vec!(("E0034", "''".to_string())));
+    assert_eq!(lex_errors(r"let c = '\n\t';"), vec!(("E0034", r"'\n\t'".to_string())));
+    assert_eq!(lex_errors(r"let c = '\u{1F600}'; let d = 'e'; let f = 'gh';"), vec!(("E0034", "'gh'".to_string())));
+}
+
+fn int_value(source: &str) -> i64 {
+    match expression(&parse_literal(source)) {
+        ExpressionNode::IntLiteralNode(node) => node.value,
+        _ => panic!("{} isn't an int literal", source),
+    }
+}
+
+fn float_value(source: &str) -> f64 {
+    match expression(&parse_literal(source)) {
+        ExpressionNode::FloatLiteralNode(node) => node.value,
+        _ => panic!("{} isn't a float literal", source),
+    }
+}
+
+// the codes of the parsing errors
+fn parse_errors(source: &str) -> Vec<&'static str> {
+    let tokens = create_tokens(source, 0).expect("lexing failed");
+    match Parser::new(tokens, 0).parse() {
+        Ok(_) => vec!(),
+        Err(errors) => errors.iter().map(|e| e.code()).collect(),
+    }
+}
+
+#[test]
+fn numbers_in_other_bases() {
+    assert_eq!(int_value("0x1F"), 31);
+    assert_eq!(int_value("0xdead_BEEF"), 0xdeadbeef);
+    assert_eq!(int_value("0b1010"), 10);
+    assert_eq!(int_value("0o755"), 493);
+    assert_eq!(int_value("0"), 0);
+    assert_eq!(int_value("007"), 7);
+    assert_eq!(int_value("1_000_000"), 1000000);
+    assert_eq!(int_value("0x7FFF_FFFF_FFFF_FFFF"), i64::MAX);
+    assert_eq!(int_value("-9223372036854775808"), i64::MIN);
+    assert_eq!(int_value("-0x8000000000000000"), i64::MIN);
+}
+
+#[test]
+fn floats_with_exponents() {
+    assert_eq!(float_value("1.5e-3"), 0.0015);
+    assert_eq!(float_value("2e10"), 2e10);
+    assert_eq!(float_value("2E+10"), 2e10);
+    assert_eq!(float_value("1_000.000_1"), 1000.0001);
+    assert_eq!(float_value("1.7976931348623157e308"), f64::MAX);
+    assert_eq!(float_value("1e-400"), 0.0);
+}
+
+#[test]
+fn invalid_numbers_are_errors() {
+    // E0003: invalid number
+    for number in ["1.2.3", "5.", "1._5", "1_", "1__", "0x", "0x_", "0b102", "0o8", "0xG", "12abc", "1e", "1e+", "1.5e3.0", "0X1F"] {
+        assert_eq!(lex_errors(&format!("let x = {};", number)), vec!(("E0003", number.to_string())), "{}", number);
+    }
+    // underscores at the start are names, not numbers
+    assert_eq!(lex_errors("let x = _1;"), vec!(("E0004", "_".to_string())));
+}
+
+#[test]
+fn numbers_out_of_range_are_errors() {
+    // E0035: number out of range
+    assert_eq!(parse_errors("let x = 9223372036854775808;"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = 0xFFFF_FFFF_FFFF_FFFF;"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = 0b1_0000000000000000000000000000000000000000000000000000000000000000;"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = 99999999999999999999999;"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = 1e309;"), vec!("E0035"));
+    // only a minus directly in front makes the lowest int valid
+    assert_eq!(parse_errors("let x = -(9223372036854775808);"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = 1 - 9223372036854775808;"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = -9223372036854775808 * 2 - -9223372036854775808;"), Vec::<&str>::new());
+    assert_eq!(parse_errors("let x = -9223372036854775809;"), vec!("E0035"));
+}
+
+fn int_suffix(source: &str) -> Option<Type> {
+    match expression(&parse_literal(source)) {
+        ExpressionNode::IntLiteralNode(node) => node.suffix,
+        _ => panic!("{} isn't an int literal", source),
+    }
+}
+
+#[test]
+fn int_suffixes() {
+    assert_eq!((int_value("10u8"), int_suffix("10u8")), (10, Some(Type::U8)));
+    assert_eq!((int_value("0xFFi16"), int_suffix("0xFFi16")), (255, Some(Type::I16)));
+    assert_eq!((int_value("1_000u32"), int_suffix("1_000u32")), (1000, Some(Type::U32)));
+    assert_eq!(int_suffix("5i64"), Some(Type::Int));
+    assert_eq!(int_suffix("5"), None);
+    // uints above the highest int are stored with the same bits as in a register
+    assert_eq!(int_value("18446744073709551615u64"), -1);
+    assert_eq!(int_value("0xFFFF_FFFF_FFFF_FFFFu64"), -1);
+    assert_eq!(int_value("-128i8"), -128);
+    assert_eq!(int_value("-32768i16"), -32768);
+
+    // E0003: suffixes only exist for ints (and underscores are still only allowed between digits)
+    for number in ["1.5u8", "1e3i32", "5u7", "5i128", "5u", "0xFFu", "1_u8"] {
+        assert_eq!(lex_errors(&format!("let x = {};", number)), vec!(("E0003", number.to_string())), "{}", number);
+    }
+    // E0035: the value has to fit into the type of the suffix
+    assert_eq!(parse_errors("let x = 256u8;"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = 128i8;"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = -(128i8);"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = 0x1_0000_0000u32;"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = 18446744073709551616u64;"), vec!("E0035"));
+    assert_eq!(parse_errors("let x = 255u8 + 65535u16 + 4294967295u32;"), Vec::<&str>::new());
+}
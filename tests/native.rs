@@ -0,0 +1,259 @@
+// compiles programs into executables with hj itself (no assembler or linker) and runs them
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// compiles the source and runs the program
+fn run_program(name: &str, source: &str) -> Output {
+    let dir = common::temp_dir("native");
+    let source_path = dir.join(format!("{}.hj", name));
+    let exe_path: PathBuf = dir.join(name);
+    fs::write(&source_path, source).unwrap();
+
+    let compile = common::compile(&source_path, &[], &exe_path);
+    assert!(exe_path.exists(), "compilation failed:\n{}", String::from_utf8_lossy(&compile.stdout));
+
+    Command::new(&exe_path).output().unwrap()
+}
+
+// compiles the source and returns the output of the program, which has to exit successfully
+fn compile_and_run(name: &str, source: &str) -> String {
+    let run = run_program(name, source);
+    assert!(run.status.success(), "program exited with {}", run.status);
+    String::from_utf8(run.stdout).unwrap()
+}
+
+#[test]
+fn int_arithmetic() {
+    let source = "
+        let i = (5 + 3 % 2 * -5) * 3 - 1;
+        let j = 5 + i * 2 + 4 - 2;
+        print(j);
+        j += 2;
+        j *= 3;
+        print(j);
+        print(-17 / 5);
+        print(-17 % 5);
+    ";
+    assert_eq!(compile_and_run("int_arithmetic", source), "5\n21\n-3\n-2\n");
+}
+
+#[test]
+fn int_limits() {
+    let source = "
+        let max = 9223372036854775807;
+        print(max);
+        print(-max - 1);
+        print(0);
+    ";
+    assert_eq!(compile_and_run("int_limits", source), "9223372036854775807\n-9223372036854775808\n0\n");
+}
+
+#[test]
+fn control_flow() {
+    let source = "
+        let n = 0;
+        let sum = 0;
+        while true {
+            n += 1;
+            if n % 2 == 0 {
+                continue;
+            }
+            if n > 10 {
+                break;
+            }
+            sum += n * n;
+        }
+        print(sum);
+        if n > 3 && n < 100 || false {
+            print(1);
+        } else if n == 11 {
+            print(2);
+        } else {
+            print(3);
+        }
+    ";
+    assert_eq!(compile_and_run("control_flow", source), "165\n1\n");
+}
+
+// the right side of && and || prints its name when it's evaluated
+#[test]
+fn logical_operators_short_circuit() {
+    let source = fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/programs/short_circuit.hj")).unwrap();
+    assert_eq!(compile_and_run("short_circuit", &source), "false\ntrue\nand\nfalse\nor\ntrue\nleft\nright\ntrue\nleft\nright\nfalse\nfalse\na\ntrue\nloop\nloop\nloop\n3\n");
+}
+
+#[test]
+fn scopes_and_shadowing() {
+    let source = "
+        let x = 1;
+        if true {
+            let x = 2;
+            print(x);
+            let y = x * 10;
+            print(y);
+        }
+        print(x);
+        let x = x + 40;
+        print(x);
+    ";
+    assert_eq!(compile_and_run("scopes_and_shadowing", source), "2\n20\n1\n41\n");
+}
+
+#[test]
+fn print_every_type() {
+    let source = "
+        print(42);
+        print(-7);
+        print(1.5);
+        print(true);
+        print(false);
+        print('a');
+        print('é');
+        print('€');
+        print('😀');
+        print(\"hello, world\");
+        print(\"\");
+    ";
+    assert_eq!(compile_and_run("print_every_type", source), "42\n-7\n1.500000\ntrue\nfalse\na\né\n€\n😀\nhello, world\n\n");
+}
+
+#[test]
+fn float_arithmetic() {
+    let source = "
+        print(45.4 * 3);
+        print(0.1 + 0.2);
+        print(1 / 3.0);
+        print(-2 - 0.25);
+        print(7.5 % 2);
+        print(-7.5 % 2.0);
+        let x = 2.5;
+        x = -x * x;
+        print(x);
+        print(2.5 < 3);
+        print(1 == 1.0);
+        print(0.5 >= 0.5 && 0.25 <= 0.125);
+    ";
+    assert_eq!(compile_and_run("float_arithmetic", source), "136.200000\n0.300000\n0.333333\n-2.250000\n1.500000\n-1.500000\n-6.250000\ntrue\ntrue\nfalse\n");
+}
+
+#[test]
+fn float_formatting() {
+    let source = "
+        print(123456789012.5);
+        print(10000000000000.0);
+        print(100000000000000000000.0 * 100000000000000000000.0);
+        print(1.0 / 0.0);
+        print(-1.0 / 0.0);
+        print(0.0 / 0.0);
+        print(0.0 / 0.0 == 0.0 / 0.0);
+        print(0.0000004);
+        print(-0.0);
+    ";
+    assert_eq!(compile_and_run("float_formatting", source), "123456789012.500000\n1.000000e13\n1.000000e40\ninf\n-inf\nNaN\nfalse\n0.000000\n0.000000\n");
+}
+
+#[test]
+fn exit_with_status_code() {
+    let run = run_program("exit_with_status_code", "print(1); exit(3); print(2);");
+    assert_eq!(run.status.code(), Some(3));
+    assert_eq!(String::from_utf8(run.stdout).unwrap(), "1\n");
+}
+
+#[test]
+fn user_defined_functions() {
+    let source = "
+        fn gcd(int a, int b) -> int {
+            if b == 0 {
+                return a;
+            }
+            return gcd(b, a % b);
+        }
+        fn average(float a, int b, float c) -> float {
+            return (a + b + c) / 3;
+        }
+        fn stop(int code) {
+            print(\"stopping\");
+            exit(code);
+        }
+        print(gcd(1071, 462));
+        print(average(1.5, 2, 2.5));
+        stop(gcd(12, 18));
+        print(\"not printed\");
+    ";
+    let run = run_program("user_defined_functions", source);
+    assert_eq!(run.status.code(), Some(6));
+    assert_eq!(String::from_utf8(run.stdout).unwrap(), "21\n2.000000\nstopping\n");
+}
+
+#[test]
+fn never_overwrites_the_source() {
+    let dir = common::temp_dir("native");
+    let source = "print(7);";
+    let source_path = dir.join("no_extension");
+    fs::write(&source_path, source).unwrap();
+
+    // without -o the executable would have the name of the source, so it gets .out appended
+    let compile = Command::new(env!("CARGO_BIN_EXE_hj")).arg(&source_path).output().unwrap();
+    assert!(compile.status.success(), "compilation failed:\n{}", String::from_utf8_lossy(&compile.stdout));
+    assert_eq!(fs::read_to_string(&source_path).unwrap(), source);
+    let run = Command::new(dir.join("no_extension.out")).output().unwrap();
+    assert_eq!(String::from_utf8(run.stdout).unwrap(), "7\n");
+
+    // writing to the source file explicitly is an error
+    let compile = common::compile(&source_path, &[], &dir.join(".").join("no_extension"));
+    assert!(!compile.status.success());
+    assert!(String::from_utf8_lossy(&compile.stdout).contains("is the source file"));
+    assert_eq!(fs::read_to_string(&source_path).unwrap(), source);
+}
+
+// the functions follow the System V calling convention, so C code can call them (this needs gcc, see common::run_tool)
+#[test]
+fn links_with_c() {
+    let dir = common::temp_dir("native");
+    let source = "
+        fn scale(int a, float b, int c, int d, int e, int f, int g, int h, bool negate, float i) -> float {
+            let sum = a + c + d + e + f + g + h + i;
+            if negate {
+                return -sum * b;
+            }
+            return sum * b;
+        }
+        fn fib(int n) -> int {
+            if n < 2 { return n; }
+            return fib(n - 1) + fib(n - 2);
+        }
+        fn narrow(u8 a, i16 b, u32 c, char d, bool e) -> int {
+            if !e { return -1; }
+            return a as int + b as int + c as int + d as int;
+        }
+    ";
+    // narrow only gets the bits of the declared types right, the upper bits of its arguments are garbage
+    let c_source = "
+        #include <stdint.h>
+        #include <stdio.h>
+        double scale(long, double, long, long, long, long, long, long, _Bool, double);
+        long fib(long);
+        long narrow(uint8_t, int16_t, uint32_t, uint32_t, _Bool);
+        typedef long (*raw_narrow)(unsigned long, unsigned long, unsigned long, unsigned long, unsigned long);
+        int main(void) {
+            raw_narrow garbage = (raw_narrow)(void (*)(void))narrow;
+            printf(\"%.2f %.2f %ld\\n\", scale(1, 0.5, 2, 3, 4, 5, 6, 7, 0, 0.5), scale(1, 2.0, 1, 1, 1, 1, 1, 1, 1, 1.0), fib(20));
+            printf(\"%ld %ld\\n\", narrow(200, -3, 5, 'A', 1), garbage(0x123456c8, 0x7777fffd, 0xdeadbeef00000005, 0xffffffff00000041, 0x1201));
+            printf(\"%ld\\n\", garbage(1, 1, 1, 1, 0x100));
+            return 0;
+        }
+    ";
+    fs::write(dir.join("library.hj"), source).unwrap();
+    fs::write(dir.join("main.c"), c_source).unwrap();
+    let compile = common::compile(&dir.join("library.hj"), &["--emit", "asm", "-O2"], &dir.join("library.s"));
+    assert!(compile.status.success(), "compilation failed:\n{}", String::from_utf8_lossy(&compile.stdout));
+
+    let Some(link) = common::run_tool(Command::new("gcc").arg(dir.join("main.c")).arg(dir.join("library.s")).arg("-o").arg(dir.join("linked"))) else { return };
+    assert!(link.status.success(), "linking failed:\n{}", String::from_utf8_lossy(&link.stderr));
+    let run = Command::new(dir.join("linked")).output().unwrap();
+    assert_eq!(String::from_utf8(run.stdout).unwrap(), "14.25 -16.00 6765\n267 267\n-1\n");
+}
@@ -0,0 +1,194 @@
+// checks the SSA form and what the optimization passes do to the IR
+
+mod common;
+
+use std::collections::HashSet;
+
+use common::{lower_source, optimize};
+use hj::ir::*;
+use hj::ssa;
+
+fn all_insts(function: &Function) -> Vec<&Inst> {
+    function.blocks.iter().flat_map(|block| block.insts.iter()).collect()
+}
+
+fn count_binary(function: &Function, wanted: BinaryOp) -> usize {
+    all_insts(function).iter().filter(|inst| matches!(inst, Inst::Binary { op, .. } if *op == wanted)).count()
+}
+
+#[test]
+fn every_register_is_written_once() {
+    let source = "
+        fn collatz(int n) -> int {
+            let steps = 0;
+            while n != 1 {
+                if n % 2 == 0 { n /= 2; } else { n = 3 * n + 1; }
+                steps += 1;
+            }
+            return steps;
+        }
+        let i = 1;
+        let total = 0;
+        while i < 10 { total += collatz(i); i += 1; }
+        print(total);
+    ";
+    let module = optimize(source, 1);
+    for function in module.all_functions() {
+        let mut written: HashSet<VReg> = function.params.iter().copied().collect();
+        for inst in all_insts(function) {
+            if let Some(dest) = inst.dest() {
+                assert!(written.insert(dest), "%{} is written twice in {}", dest.0, function.name);
+            }
+        }
+    }
+    // n and steps change in the loop of collatz, so the loop header chooses between their values
+    let collatz = module.function("collatz").unwrap();
+    let phi_count = all_insts(collatz).iter().filter(|inst| inst.is_phi()).count();
+    assert!(phi_count >= 2);
+}
+
+#[test]
+fn constants_are_calculated_while_compiling() {
+    let module = optimize("let x = 2 * 3 + 1; let y = x * 1.5; print(x); print(y); print(-x < 0 && !false);", 1);
+    let expected = "\
+fn <main>() {
+bb0:
+    call print(7)
+    call print(10.5)
+    call print(true)
+    return
+}
+";
+    assert_eq!(module.to_text(), expected);
+}
+
+#[test]
+fn branches_on_constants_become_jumps() {
+    let module = optimize("let debug = false; if debug { print(1); } else { print(2); } while debug { print(3); }", 1);
+    assert_eq!(module.main.blocks.len(), 1);
+    assert_eq!(module.main.to_text(), "fn <main>() {\nbb0:\n    call print(2)\n    return\n}\n");
+}
+
+#[test]
+fn failing_divisions_are_kept() {
+    // the division by zero has to fail at runtime, even though its result is never used
+    let module = optimize("let zero = 0; let x = 1 / zero; let min = -9223372036854775807 - 1; let y = min % -1; let z = 5 / 2;", 1);
+    assert_eq!(count_binary(&module.main, BinaryOp::Div), 1);
+    assert_eq!(count_binary(&module.main, BinaryOp::Rem), 1);
+}
+
+#[test]
+fn constants_wrap_at_the_width_of_their_type() {
+    let source = "let u8 x = 200; let i8 y = -128; let uint z = 0; print(x + 100); print(y / -1); print(z - 1); print(x >> 9); print(z - 1 > 5); let u16 w = x; print(w * 400);";
+    let expected = "\
+fn <main>() {
+bb0:
+    call print(44u8)
+    call print(-128i8)
+    call print(18446744073709551615u64)
+    call print(100u8)
+    call print(true)
+    call print(14464u16)
+    return
+}
+";
+    assert_eq!(optimize(source, 1).to_text(), expected);
+    // only dividing an int by -1 can fail, the narrower types just wrap around
+    let module = optimize("fn f(i8 x, int y) { let a = x / -1; let b = y / -1; let c = x / 0; }", 1);
+    assert_eq!(count_binary(module.function("f").unwrap(), BinaryOp::Div), 2);
+}
+
+#[test]
+fn casts_are_folded() {
+    let source = "let f = -2.75; let zero = 0.0; print(f as int); print(f as uint); print(1e10 as i16); print((zero / zero) as u8); print('€' as u8); print(8364 as char); print(-1 as char); print(true as u16); print(f as float);";
+    let expected = "\
+fn <main>() {
+bb0:
+    call print(-2)
+    call print(0u64)
+    call print(32767i16)
+    call print(0u8)
+    call print(172u8)
+    call print('€')
+    call print('\u{FFFD}')
+    call print(1u16)
+    call print(-2.75)
+    return
+}
+";
+    assert_eq!(optimize(source, 1).to_text(), expected);
+}
+
+#[test]
+fn copies_are_propagated() {
+    let module = optimize("fn f(int x) -> int { let a = x; let b = a; return b; } print(f(1));", 1);
+    let expected = "\
+fn f(%0: int) -> int {
+bb0:
+    return %0
+}
+";
+    assert_eq!(module.function("f").unwrap().to_text(), expected);
+}
+
+#[test]
+fn unused_values_are_removed_but_calls_stay() {
+    let module = optimize("fn f(int x) -> int { print(x); return x; } fn g(float x) { let unused = x * 2.0 / x; let also_unused = f(1); }", 1);
+    let expected = "\
+fn g(%0: float) {
+bb0:
+    call f(1)
+    return
+}
+";
+    assert_eq!(module.function("g").unwrap().to_text(), expected);
+}
+
+#[test]
+fn common_subexpressions_are_calculated_once_with_o2() {
+    let source = "
+        fn f(int x, int y) -> int {
+            let a = x * y + 1;
+            if a > 10 {
+                let b = y * x + 1;
+                return a + b;
+            }
+            return -x * -x;
+        }
+        print(f(2, 3));
+    ";
+    let o1 = optimize(source, 1);
+    let o2 = optimize(source, 2);
+    assert_eq!(count_binary(o1.function("f").unwrap(), BinaryOp::Mul), 3);
+    assert_eq!(count_binary(o2.function("f").unwrap(), BinaryOp::Mul), 2);
+    assert_eq!(count_binary(o2.function("f").unwrap(), BinaryOp::Add), 2);
+}
+
+#[test]
+fn o0_keeps_the_ir_unchanged() {
+    let source = "let x = 1 + 2; x += 1; print(x);";
+    assert_eq!(optimize(source, 0), lower_source(source));
+}
+
+#[test]
+fn phis_of_a_block_that_jumps_to_itself_get_their_copies() {
+    let source = "fn check(int x) { if x == 5 { exit(0); } } let x = 0; while true { x = x + 1; print(x); check(x); }";
+    let mut function = optimize(source, 1).main;
+    ssa::destruct(&mut function);
+    // bb1 is its own predecessor, so the copy for the next round is at its end
+    let expected = "\
+fn <main>() {
+bb0:
+    %2: int = copy 0
+    jump bb1
+bb1: ; preds: bb0, bb1
+    %0: int = copy %2
+    %1: int = add %0, 1
+    call print(%1)
+    call check(%1)
+    %2: int = copy %1
+    jump bb1
+}
+";
+    assert_eq!(function.to_text(), expected);
+}
@@ -0,0 +1,108 @@
+// checks that the lexer and the parser recover from errors, so every error in a file is reported in one run
+
+mod common;
+
+use std::fs;
+use std::process::Output;
+
+use hj::errors::{LexError, ParseError};
+use hj::lexer::{create_tokens, tokenize};
+use hj::parser::Parser;
+
+// the codes of the parse errors and the lines they're in
+fn parse_errors(source: &str, error_limit: usize) -> Vec<(&'static str, usize)> {
+    let tokens = create_tokens(source, 0).expect("lexing failed");
+    match Parser::new(tokens, error_limit).parse() {
+        Ok(_) => vec!(),
+        Err(errors) => errors.iter().map(|e: &ParseError| (e.code(), e.span().map_or(0, |span| span.line))).collect(),
+    }
+}
+
+fn lex_errors(source: &str, error_limit: usize) -> Vec<(&'static str, usize)> {
+    tokenize(source, error_limit).1.iter().map(|e: &LexError| (e.code(), e.span().line)).collect()
+}
+
+fn compile(name: &str, source: &str, args: &[&str]) -> (Output, Vec<String>) {
+    let dir = common::temp_dir("recovery");
+    let path = dir.join(format!("{}.hj", name));
+    fs::write(&path, source).unwrap();
+    let output = common::compile(&path, args, &dir.join(name));
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let codes = stdout.lines().filter_map(|line| line.strip_prefix("Error[")).map(|rest| rest[..5].to_string()).collect();
+    (output, codes)
+}
+
+#[test]
+fn parsing_goes_on_after_the_next_semicolon() {
+    // E0006: unexpected token, E0007: invalid unary operator
+    let source = "let = 5;\nlet x = 1;\nlet y = * 2;\nprint(x);\nx 5;\n";
+    assert_eq!(parse_errors(source, 0), vec!(("E0006", 1), ("E0007", 3), ("E0006", 5)));
+    // the rest of the statement is skipped, even if it contains more errors
+    assert_eq!(parse_errors("let = = = ;\nlet y = 2;", 0), vec!(("E0006", 1)));
+    // E0005: unexpected end of file
+    assert_eq!(parse_errors("let x = 1;\nlet y =", 0), vec!(("E0005", 2)));
+}
+
+#[test]
+fn parsing_goes_on_after_the_end_of_a_block() {
+    // the closing brace isn't skipped, so the block still ends at the right place
+    let source = "if true {\n    let = 1\n}\nlet y = ;\nwhile true { print(1); }\n";
+    assert_eq!(parse_errors(source, 0), vec!(("E0006", 2), ("E0006", 4)));
+    let source = "fn f() {\n    let x = 1 +;\n    if x { let = 2; }\n}\nf(;\n";
+    assert_eq!(parse_errors(source, 0), vec!(("E0006", 2), ("E0006", 3), ("E0006", 5)));
+    // a closing brace without a block is an error, but doesn't stop the parser
+    assert_eq!(parse_errors("}\nlet = 1;", 0), vec!(("E0006", 1), ("E0006", 2)));
+}
+
+#[test]
+fn parse_errors_stop_at_the_limit() {
+    let source = "let = 1;\n".repeat(10);
+    assert_eq!(parse_errors(&source, 3).len(), 3);
+    assert_eq!(parse_errors(&source, 0).len(), 10);
+}
+
+#[test]
+fn lexing_goes_on_after_invalid_characters() {
+    // E0004: unexpected character, E0003: invalid number, E0033: invalid escape sequence, E0002: unterminated string
+    let source = "let x = $;\nlet y = 1.2.3;\nlet s = \"\\q\";\nlet z = 1 @ 2;\nlet t = \"open";
+    assert_eq!(lex_errors(source, 0), vec!(("E0004", 1), ("E0003", 2), ("E0033", 3), ("E0004", 4), ("E0002", 5)));
+    assert_eq!(lex_errors(source, 2), vec!(("E0004", 1), ("E0003", 2)));
+}
+
+#[test]
+fn lex_and_parse_errors_are_reported_together() {
+    let (output, codes) = compile("together", "let x = $;\nlet = 5;\nlet y = 1 @ 2;\nprint(;\n", &[]);
+    assert!(!output.status.success());
+    // the statements with invalid characters aren't reported a second time by the parser
+    assert_eq!(codes, vec!("E0004", "E0006", "E0004", "E0006"));
+
+    // an unterminated literal hides the rest of the file, but the errors before it are still reported
+    let (_, codes) = compile("unterminated", "let = 1;\nlet s = \"text;\nlet = 2;\n", &[]);
+    assert_eq!(codes, vec!("E0006", "E0002"));
+}
+
+#[test]
+fn the_limit_counts_all_errors() {
+    let source = "let a = $;\nlet = 1;\nlet b = $;\nlet = 2;\nlet c = $;\n";
+    let (output, codes) = compile("limit", source, &["--error-limit", "3"]);
+    assert_eq!(codes, vec!("E0004", "E0006", "E0004"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Stopping after 3 errors"));
+
+    let (_, codes) = compile("lex_limit", "let a = $;\nlet b = $;\nlet = 1;\n", &["--error-limit", "2"]);
+    assert_eq!(codes, vec!("E0004", "E0004"));
+
+    // exactly as many errors as the limit allows, so nothing was left out
+    let (output, codes) = compile("at_limit", "let a = $;\nlet = 1;\nlet b = $;\n", &["--error-limit", "3"]);
+    assert_eq!(codes, vec!("E0004", "E0006", "E0004"));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Stopping"));
+    let (output, codes) = compile("type_limit", "let a = 1 + true;\nlet b = 2 + \"s\";\n", &["--error-limit", "2"]);
+    assert_eq!(codes, vec!("E0017", "E0017"));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Stopping"));
+    let (output, codes) = compile("type_over_limit", "let a = 1 + true;\nlet b = 2 + \"s\";\nlet c = d;\n", &["--error-limit", "2"]);
+    assert_eq!(codes, vec!("E0017", "E0017"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Stopping after 2 errors"));
+
+    let (output, codes) = compile("no_limit", &source.repeat(10), &["--error-limit", "0"]);
+    assert_eq!(codes.len(), 50);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Stopping"));
+}
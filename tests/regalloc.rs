@@ -0,0 +1,126 @@
+// checks where the register allocator puts values, and measures the instruction counts on the programs in tests/programs
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::optimize;
+use hj::codegen::CodeGenerator;
+use hj::ir::*;
+use hj::regalloc::{allocate, Allocation, Location, CALLEE_SAVED};
+use hj::ssa;
+use hj::x86::{Reg, Xmm};
+
+// the main function without phis (like the code generator sees it) and its allocation
+fn allocate_main(source: &str) -> (Function, Allocation) {
+    let mut function = optimize(source, 2).main;
+    ssa::destruct(&mut function);
+    let allocation = allocate(&function);
+    (function, allocation)
+}
+
+fn print_args(function: &Function) -> Vec<VReg> {
+    let mut args = vec!();
+    for block in &function.blocks {
+        for inst in &block.insts {
+            if let Inst::Call { args: call_args, .. } = inst {
+                if let Some(Operand::Reg(reg)) = call_args.first() {
+                    args.push(*reg);
+                }
+            }
+        }
+    }
+    args
+}
+
+#[test]
+fn floats_get_sse_registers() {
+    let (function, allocation) = allocate_main("let x = 2.0; let i = 0; while i < 10 { x = x * 1.5 - 0.25; i += 1; } print(x);");
+    for (i, t) in function.reg_types.iter().enumerate() {
+        match allocation.locations[i] {
+            Location::Xmm(_) => assert_eq!(*t, hj::nodes::Type::Float, "%{} isn't a float", i),
+            Location::Reg(_) => assert_ne!(*t, hj::nodes::Type::Float, "%{} is a float", i),
+            location => assert_eq!(location, Location::Nowhere, "%{} was spilled", i),
+        }
+    }
+    assert_eq!(allocation.slot_count, 0);
+}
+
+#[test]
+fn values_needed_after_calls_are_callee_saved() {
+    // i is printed and then still needed for the next iteration
+    let (function, allocation) = allocate_main("let i = 0; while i < 10 { print(i); i += 1; }");
+    let args = print_args(&function);
+    assert_eq!(args.len(), 1);
+    match allocation.locations[args[0].0] {
+        Location::Reg(reg) => assert!(CALLEE_SAVED.contains(&reg), "{:?} isn't callee-saved", reg),
+        location => panic!("i is at {:?}", location),
+    }
+    assert!(!allocation.callee_saved.is_empty());
+
+    // there are no callee-saved SSE registers, so a float has to go to the stack
+    let (function, allocation) = allocate_main("let x = 0.5; while x < 10.0 { print(x); x += 1.0; }");
+    let args = print_args(&function);
+    assert!(matches!(allocation.locations[args[0].0], Location::Stack(_)));
+}
+
+#[test]
+fn values_are_only_spilled_under_pressure() {
+    let programs = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("programs");
+    let (_, allocation) = allocate_main(&fs::read_to_string(programs.join("primes.hj")).unwrap());
+    assert_eq!(allocation.slot_count, 0);
+    let (_, allocation) = allocate_main(&fs::read_to_string(programs.join("pressure.hj")).unwrap());
+    assert!(allocation.slot_count > 0);
+}
+
+#[test]
+fn copies_between_phis_disappear() {
+    // the values of the loop variables flow around the loop without being moved between registers
+    let (function, allocation) = allocate_main("let i = 0; let sum = 0; while i < 100 { sum += i; i += 1; } print(sum);");
+    for block in &function.blocks {
+        for inst in &block.insts {
+            if let Inst::Copy { dest, src: Operand::Reg(src) } = inst {
+                assert_eq!(allocation.locations[dest.0], allocation.locations[src.0], "{}", inst.to_text(&function));
+            }
+        }
+    }
+}
+
+// the benchmark: with the same optimized IR, no program may need more instructions with registers than with a stack slot
+// for every value (some of them are only prints of constants after optimizing), run with --nocapture to see the numbers
+#[test]
+fn registers_need_fewer_instructions() {
+    let programs = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("programs");
+    let runtime_size = CodeGenerator::new(false).generate(&optimize("", 0)).instruction_count();
+    let count = |module: &Module, allocate_registers: bool| CodeGenerator::new(allocate_registers).generate(module).instruction_count() - runtime_size;
+    let mut paths: Vec<_> = fs::read_dir(programs).unwrap().map(|entry| entry.unwrap().path()).collect();
+    paths.sort();
+
+    let (mut total_stack, mut total_registers) = (0, 0);
+    println!("{:<16} {:>6} {:>12} {:>16}", "program", "-O0", "-O2 (stack)", "-O2 (registers)");
+    for path in paths {
+        let source = fs::read_to_string(&path).unwrap();
+        let unoptimized = count(&optimize(&source, 0), false);
+        let module = optimize(&source, 2);
+        let (stack, registers) = (count(&module, false), count(&module, true));
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        println!("{:<16} {:>6} {:>12} {:>16}", name, unoptimized, stack, registers);
+        assert!(registers <= stack, "{} needs {} instructions with registers and {} without", name, registers, stack);
+        total_stack += stack;
+        total_registers += registers;
+    }
+    println!("{:<16} {:>6} {:>12} {:>16}", "total", "", total_stack, total_registers);
+    // not exact, so small changes to the code generator don't break it
+    assert!(total_registers * 4 < total_stack * 3);
+}
+
+#[test]
+fn parameters_stay_in_argument_registers() {
+    let module = optimize("fn f(int a, float b, int c) -> float { return a * b - c; } print(f(1, 2.0, 3));", 2);
+    let mut function = module.function("f").unwrap().clone();
+    ssa::destruct(&mut function);
+    let allocation = allocate(&function);
+    let locations: Vec<Location> = function.params.iter().map(|param| allocation.locations[param.0]).collect();
+    assert_eq!(locations, vec!(Location::Reg(Reg::Rdi), Location::Xmm(Xmm::Xmm0), Location::Reg(Reg::Rsi)));
+}
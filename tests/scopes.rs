@@ -0,0 +1,44 @@
+// checks which variable a name refers to in nested scopes and after shadowing
+
+mod common;
+
+use common::check;
+
+#[test]
+fn inner_scopes_can_use_outer_variables() {
+    assert_eq!(check("let x = 1; if true { x += 1; let y = x * 2; print(y); }"), Vec::<&str>::new());
+    assert_eq!(check("let x = 1; while x < 10 { if x > 5 { x = x + 2; } else { x += 1; } }"), Vec::<&str>::new());
+}
+
+#[test]
+fn variables_go_out_of_scope_at_the_end_of_their_block() {
+    // E0012: undefined variable, E0011: assignment to undefined variable
+    assert_eq!(check("if true { let y = 1; } print(y);"), vec!("E0012"));
+    assert_eq!(check("while false { let y = 1; } y = 2;"), vec!("E0011"));
+    assert_eq!(check("if true { } else { let y = 1; } print(y);"), vec!("E0012"));
+    assert_eq!(check("if true { if true { let y = 1; } print(y); }"), vec!("E0012"));
+}
+
+#[test]
+fn redefinition_in_the_same_scope_shadows() {
+    // the new variable can have a different type, and later uses see the new type
+    assert_eq!(check("let j = 5; let float j = 45.4 * 3; j = 1.5;"), Vec::<&str>::new());
+    // E0013: mismatched assignment type, the old int j isn't visible anymore
+    assert_eq!(check("let j = 5; let j = 1.5; j = 1;"), vec!("E0013"));
+    // the expression of the new definition still refers to the old variable
+    assert_eq!(check("let x = 1; let x = x < 2; if x { }"), Vec::<&str>::new());
+}
+
+#[test]
+fn shadowing_in_an_inner_scope_ends_with_the_scope() {
+    assert_eq!(check("let x = 1; if true { let x = \"s\"; print(x); } x = 2;"), Vec::<&str>::new());
+    // E0013: inside of the block x is a str
+    assert_eq!(check("let x = 1; if true { let x = \"s\"; x = 2; }"), vec!("E0013"));
+}
+
+#[test]
+fn functions_only_see_their_parameters() {
+    assert_eq!(check("let x = 1; fn f() { print(x); }"), vec!("E0012"));
+    assert_eq!(check("fn f(int x) { let x = 1.5; x = 2.5; }"), Vec::<&str>::new());
+    assert_eq!(check("fn f(int x) { } print(x);"), vec!("E0012"));
+}
@@ -0,0 +1,98 @@
+// checks the shape of the AST the parser produces for statements with blocks
+
+mod common;
+
+use common::parse;
+use hj::lexer::create_tokens;
+use hj::nodes::*;
+use hj::parser::Parser;
+
+fn expression(expression: &TExpressionNode) -> String {
+    match &expression.node {
+        ExpressionNode::VariableNode(node) => node.name.clone(),
+        ExpressionNode::BoolLiteralNode(node) => node.value.to_string(),
+        ExpressionNode::IntLiteralNode(node) => node.value.to_string(),
+        _ => String::from("expr"),
+    }
+}
+
+// writes the commands of the scope in a compact form, e.g. "(if a {x=} else {(if b {})})"
+fn shape(scope: &ScopeNode) -> String {
+    let commands: Vec<String> = scope.commands.iter().map(|command| match command {
+        CommandNode::VariableDefinitionNode(node) => format!("let {}", node.variable.name),
+        CommandNode::VariableAssignmentNode(node) => format!("{}=", node.variable.name),
+        CommandNode::FunctionCallNode(node) => format!("{}()", node.function.name),
+        CommandNode::IfNode(node) => match &node.else_body {
+            Some(else_body) => format!("(if {} {{{}}} else {{{}}})", expression(&node.condition), shape(&node.body), shape(else_body)),
+            None => format!("(if {} {{{}}})", expression(&node.condition), shape(&node.body)),
+        },
+        CommandNode::WhileNode(node) => format!("(while {} {{{}}})", expression(&node.condition), shape(&node.body)),
+        CommandNode::BreakNode(_) => String::from("break"),
+        CommandNode::ContinueNode(_) => String::from("continue"),
+        CommandNode::FunctionDefinitionNode(node) => format!("(fn {} {{{}}})", node.function.name, shape(&node.body)),
+        CommandNode::ReturnNode(node) => match &node.expression {
+            Some(value) => format!("return {}", expression(value)),
+            None => String::from("return"),
+        },
+    }).collect();
+    commands.join(" ")
+}
+
+fn parse_shape(source: &str) -> String {
+    shape(&parse(source))
+}
+
+// the codes of the parse errors
+fn parse_errors(source: &str) -> Vec<&'static str> {
+    let tokens = create_tokens(source, 0).expect("lexing failed");
+    match Parser::new(tokens, 0).parse() {
+        Ok(_) => vec!(),
+        Err(errors) => errors.iter().map(|e| e.code()).collect(),
+    }
+}
+
+#[test]
+fn if_statements_have_blocks() {
+    assert_eq!(parse_shape("if a { x = 1; print(x); }"), "(if a {x= print()})");
+    assert_eq!(parse_shape("if a { } else { let y = 2; }"), "(if a {} else {let y})");
+    assert_eq!(parse_shape("if a { if b { } } print(1);"), "(if a {(if b {})}) print()");
+}
+
+#[test]
+fn else_if_is_an_if_in_the_else_block() {
+    assert_eq!(parse_shape("if a { } else if b { x = 1; }"), "(if a {} else {(if b {x=})})");
+    assert_eq!(parse_shape("if a { } else if b { } else { x = 1; }"), "(if a {} else {(if b {} else {x=})})");
+    // every further else if is nested one level deeper
+    assert_eq!(parse_shape("if a { } else if b { } else if c { } else { }"), "(if a {} else {(if b {} else {(if c {} else {})})})");
+}
+
+#[test]
+fn else_belongs_to_the_block_it_follows() {
+    // the braces are required, so there is no dangling else: it always belongs to the if whose block it follows
+    assert_eq!(parse_shape("if a { if b { x = 1; } else { x = 2; } }"), "(if a {(if b {x=} else {x=})})");
+    assert_eq!(parse_shape("if a { if b { x = 1; } } else { x = 2; }"), "(if a {(if b {x=})} else {x=})");
+    assert_eq!(parse_shape("if a { } if b { } else { }"), "(if a {}) (if b {} else {})");
+}
+
+#[test]
+fn invalid_if_statements_are_errors() {
+    // E0006: unexpected token, E0005: unexpected end of file
+    assert_eq!(parse_errors("if a print(1);"), vec!("E0006"));
+    assert_eq!(parse_errors("if a { } else print(1);"), vec!("E0006"));
+    assert_eq!(parse_errors("else { }"), vec!("E0006"));
+    assert_eq!(parse_errors("if { }"), vec!("E0006"));
+    assert_eq!(parse_errors("if a { print(1);"), vec!("E0005"));
+    assert_eq!(parse_errors("if a { } else"), vec!("E0005"));
+}
+
+#[test]
+fn while_loops_have_blocks() {
+    assert_eq!(parse_shape("while a { x = 1; if b { break; } continue; }"), "(while a {x= (if b {break}) continue})");
+    assert_eq!(parse_shape("while a { while b { } break; }"), "(while a {(while b {}) break})");
+    // break and continue are parsed anywhere, the type checker reports them outside of loops
+    assert_eq!(parse_shape("break; continue;"), "break continue");
+    // E0006: unexpected token
+    assert_eq!(parse_errors("while a print(1);"), vec!("E0006"));
+    assert_eq!(parse_errors("while a { break }"), vec!("E0006"));
+    assert_eq!(parse_errors("while a { continue 1; }"), vec!("E0006"));
+}
@@ -0,0 +1,135 @@
+// checks that ints used together with floats are reported: as warnings by default and as errors with --strict-numeric
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use hj::errors::TypeError;
+use hj::nodes::Operator;
+use hj::variable_traverser::VariableTraverser;
+
+// the codes of the errors and of the warnings of the source
+fn check(source: &str, strict_numeric: bool) -> (Vec<&'static str>, Vec<&'static str>) {
+    let mut traverser = VariableTraverser::new();
+    traverser.strict_numeric = strict_numeric;
+    let errors = common::error_codes(&mut traverser, source);
+    (errors, traverser.warnings.iter().map(TypeError::code).collect())
+}
+
+// whether the binary operator works on an int and a float (by converting the int), None for operators that are only unary
+// (the match has no wildcard, so a new operator has to be sorted in here)
+fn mixes_ints_and_floats(operator: Operator) -> Option<bool> {
+    match operator {
+        Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Modulo => Some(true),
+        Operator::Equal | Operator::NotEqual => Some(true),
+        Operator::Less | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual => Some(true),
+        // E0017: shifts only work on ints, && and || only on bools
+        Operator::ShiftLeft | Operator::ShiftRight | Operator::And | Operator::Or => Some(false),
+        Operator::Not => None,
+    }
+}
+
+#[test]
+fn all_contains_every_operator_once() {
+    for (i, operator) in Operator::ALL.iter().enumerate() {
+        assert_eq!(Operator::from(&operator.to_str()), *operator);
+        assert!(!Operator::ALL[..i].contains(operator), "{} is in Operator::ALL twice", operator.to_str());
+    }
+}
+
+#[test]
+fn every_operator_with_an_int_and_a_float() {
+    for operator in Operator::ALL {
+        let Some(mixes) = mixes_ints_and_floats(operator) else {
+            // a unary operator only has one operand, so it never converts anything
+            let source = format!("let float f = 1.5; let b = true; let x = {}f;", operator.to_str());
+            let errors = if operator == Operator::Not { vec!("E0016") } else { vec!() };
+            assert_eq!(check(&source, false), (errors.clone(), vec!()), "{}", source);
+            assert_eq!(check(&source, true), (errors, vec!()), "{}", source);
+            continue;
+        };
+        for (left, right) in [("i", "f"), ("f", "i"), ("f", "2"), ("5u8", "f")] {
+            let source = format!("let i = 3; let f = 1.5; let x = {} {} {};", left, operator.to_str(), right);
+            if mixes {
+                // E0037: implicit conversion from an int to a float
+                assert_eq!(check(&source, false), (vec!(), vec!("E0037")), "{}", source);
+                assert_eq!(check(&source, true), (vec!("E0037"), vec!()), "{}", source);
+            } else {
+                assert_eq!(check(&source, false), (vec!("E0017"), vec!()), "{}", source);
+                assert_eq!(check(&source, true), (vec!("E0017"), vec!()), "{}", source);
+            }
+        }
+    }
+}
+
+#[test]
+fn every_operator_without_mixing() {
+    for operator in Operator::ALL {
+        if mixes_ints_and_floats(operator) != Some(true) {
+            continue;
+        }
+        // two values of the same type, or an explicit cast
+        for (left, right) in [("i", "j"), ("f", "g"), ("i as float", "f"), ("f", "(i + j) as float"), ("f as int", "i")] {
+            let source = format!("let i = 3; let j = 4; let f = 1.5; let g = 2.5; let x = {} {} {};", left, operator.to_str(), right);
+            assert_eq!(check(&source, false), (vec!(), vec!()), "{}", source);
+            assert_eq!(check(&source, true), (vec!(), vec!()), "{}", source);
+        }
+    }
+}
+
+#[test]
+fn every_conversion_is_reported() {
+    // both sides of the comparison, and the int inside of the nested operation
+    let source = "let i = 3; let f = 1.5; let x = i * f < (i + 1) / f;";
+    assert_eq!(check(source, false), (vec!(), vec!("E0037", "E0037")));
+    assert_eq!(check(source, true), (vec!("E0037", "E0037"), vec!()));
+    // compound assignments are binary operations as well
+    assert_eq!(check("let f = 1.5; f *= 2;", true), (vec!("E0037"), vec!()));
+    assert_eq!(check("let f = 1.5; f *= 2.0;", true), (vec!(), vec!()));
+    // ints don't become floats anywhere else
+    assert_eq!(check("fn half(float x) -> float { return x / 2.0; } let float f = 1; half(2);", false), (vec!("E0009", "E0022"), vec!()));
+}
+
+fn temp_dir() -> PathBuf {
+    common::temp_dir("strict")
+}
+
+fn hj(name: &str, source: &str, args: &[&str]) -> Output {
+    let path = temp_dir().join(format!("{}.hj", name));
+    fs::write(&path, source).unwrap();
+    common::compile(&path, args, &temp_dir().join(name))
+}
+
+#[test]
+fn the_option_turns_warnings_into_errors() {
+    let source = "let count = 4; print(10.0 / count);";
+    let compiled = hj("warning", source, &[]);
+    assert!(compiled.status.success());
+    assert!(String::from_utf8_lossy(&compiled.stdout).contains("Warning[E0037]"));
+
+    let strict = hj("strict", source, &["--strict-numeric"]);
+    assert!(!strict.status.success());
+    let text = String::from_utf8_lossy(&strict.stdout).to_string();
+    assert!(text.contains("Error[E0037]"), "{}", text);
+    assert!(!temp_dir().join("strict").exists());
+
+    let cast = hj("cast", "let count = 4; print(10.0 / count as float);", &["--strict-numeric"]);
+    assert!(cast.status.success());
+    assert!(!String::from_utf8_lossy(&cast.stdout).contains("Warning"));
+}
+
+#[test]
+fn the_interpreter_writes_warnings_to_stderr() {
+    let path = temp_dir().join("run.hj");
+    fs::write(&path, "let count = 4; print(10.0 / count);").unwrap();
+    let run = Command::new(env!("CARGO_BIN_EXE_hj")).arg("run").arg(&path).output().unwrap();
+    assert!(run.status.success());
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "2.500000\n");
+    assert!(String::from_utf8_lossy(&run.stderr).contains("E0037"));
+
+    let strict = Command::new(env!("CARGO_BIN_EXE_hj")).arg("run").arg("--strict-numeric").arg(&path).output().unwrap();
+    assert!(!strict.status.success());
+    assert!(String::from_utf8_lossy(&strict.stdout).contains("Error[E0037]"));
+}
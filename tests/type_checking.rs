@@ -0,0 +1,138 @@
+// checks the errors of the type checker: which programs are rejected, and that one mistake is only reported once
+
+mod common;
+
+use common::check;
+use hj::variable_traverser::VariableTraverser;
+
+#[test]
+fn errors_dont_cascade() {
+    // E0012: undefined variable, x gets the error type, so using it isn't reported again
+    assert_eq!(check("let x = y + 1; let z = x * 2; print(z); x = 5; let bool b = x;"), vec!("E0012"));
+    // E0017: invalid binary operands, the operation has the error type as well
+    assert_eq!(check("let x = 1 + true; let y = x * 2.5 - x; print(-y);"), vec!("E0017"));
+    assert_eq!(check("let s = \"a\" - 1; if s { } while s < 3 { }"), vec!("E0017"));
+    // E0014: undefined function, its result can be used anywhere (but every call is reported)
+    assert_eq!(check("let x = nothing(1) + 2; let str s = x; print(x && s);"), vec!("E0014"));
+    assert_eq!(check("nothing(); nothing();"), vec!("E0014", "E0014"));
+    // the arguments of an undefined function are still checked
+    assert_eq!(check("nothing(y);"), vec!("E0012", "E0014"));
+    // E0016: invalid unary operand
+    assert_eq!(check("let x = -true; let y = !x; let z = x + 1;"), vec!("E0016"));
+}
+
+#[test]
+fn a_wrong_declared_type_is_reported_once() {
+    // E0009: the variable has the declared type afterwards, so the later uses are fine
+    assert_eq!(check("let int x = true; let y = x + 1; x = 3;"), vec!("E0009"));
+    // E0013: the assignment doesn't change the type of the variable
+    assert_eq!(check("let x = 1; x = \"s\"; let y = x * 2;"), vec!("E0013"));
+}
+
+#[test]
+fn unrelated_errors_are_all_reported() {
+    let source = "
+        let a = b;
+        let int c = 1.5;
+        c = true;
+        let d = 1 + \"s\";
+        print(e);
+        if 1 { }
+        break;
+        f();
+    ";
+    // E0012, E0009, E0013, E0017, E0012, E0019: non-bool condition, E0020: break outside of a loop, E0014
+    assert_eq!(check(source), vec!("E0012", "E0009", "E0013", "E0017", "E0012", "E0019", "E0020", "E0014"));
+    // errors in both operands of one operation
+    assert_eq!(check("let x = (a + 1) * (true - 1);"), vec!("E0012", "E0017"));
+}
+
+#[test]
+fn if_conditions_have_to_be_bools() {
+    assert_eq!(check("let x = 1; if x < 2 && true { } else if !(x == 3) { }"), Vec::<&str>::new());
+    // E0019: non-bool condition
+    assert_eq!(check("if 1 { }"), vec!("E0019"));
+    assert_eq!(check("let s = \"yes\"; if s { }"), vec!("E0019"));
+    assert_eq!(check("if 1.5 { } else if 'c' { } else if true { }"), vec!("E0019", "E0019"));
+    // the blocks are checked even if the condition is wrong
+    assert_eq!(check("if 1 { let x = y; }"), vec!("E0019", "E0012"));
+}
+
+#[test]
+fn branches_have_their_own_scopes() {
+    assert_eq!(check("let x = 1; if x > 0 { x = 2; let y = x; } else { x = 3; let y = 1.5; y = 2.5; }"), Vec::<&str>::new());
+    // E0012: the variable of one branch isn't visible in the other one, or after the if
+    assert_eq!(check("if true { let y = 1; } else { print(y); }"), vec!("E0012"));
+    assert_eq!(check("if true { let y = 1; } else if y > 0 { }"), vec!("E0012"));
+    assert_eq!(check("if true { } else if true { let y = 1; } else { y = 2; }"), vec!("E0011"));
+    // shadowing in a branch doesn't change the outer variable, E0013: x is still an int after the if
+    assert_eq!(check("let x = 1; if true { let x = \"s\"; } x = \"t\";"), vec!("E0013"));
+}
+
+#[test]
+fn while_conditions_have_to_be_bools() {
+    assert_eq!(check("let i = 0; while i < 10 && i != 5 { i += 1; }"), Vec::<&str>::new());
+    // E0019: non-bool condition
+    assert_eq!(check("let i = 10; while i { i -= 1; }"), vec!("E0019"));
+    assert_eq!(check("while \"forever\" { }"), vec!("E0019"));
+    // the body is checked even if the condition is wrong, and its variables end with it
+    assert_eq!(check("while 1 { let x = true + 1; } print(x);"), vec!("E0019", "E0017", "E0012"));
+}
+
+#[test]
+fn break_and_continue_only_work_in_loops() {
+    assert_eq!(check("while true { if true { break; } else { continue; } while false { break; } continue; }"), Vec::<&str>::new());
+    // E0020: break or continue outside of a loop
+    assert_eq!(check("break;"), vec!("E0020"));
+    assert_eq!(check("continue;"), vec!("E0020"));
+    assert_eq!(check("if true { break; } else { continue; }"), vec!("E0020", "E0020"));
+    // the loop ends with its block
+    assert_eq!(check("while true { } break;"), vec!("E0020"));
+    // a function can't break out of the loop it's called in
+    assert_eq!(check("fn stop() { break; } while true { stop(); }"), vec!("E0020"));
+    assert_eq!(check("fn skip() { if true { continue; } }"), vec!("E0020"));
+    // E0029: functions can't be defined inside of loops (the body of the nested function isn't checked)
+    assert_eq!(check("while true { fn stop() { break; } }"), vec!("E0029"));
+}
+
+// the notes of the first error in the source
+fn notes(source: &str) -> Vec<String> {
+    let mut scope = common::parse(source);
+    let errors = VariableTraverser::new().traverse(&mut scope).expect_err("no errors");
+    errors[0].to_diagnostic().notes
+}
+
+#[test]
+fn comparisons_need_numbers_or_chars() {
+    assert_eq!(check("let bool a = 1 < 2; let bool b = 'a' <= 'b'; let bool c = 2.5 > 1.5; let bool d = 5u8 >= 3;"), Vec::<&str>::new());
+    // E0017: invalid binary operands
+    assert_eq!(check("let b = \"a\" < \"b\";"), vec!("E0017"));
+    assert_eq!(check("let b = true > false;"), vec!("E0017"));
+    assert_eq!(check("let b = 'a' < 98;"), vec!("E0017"));
+    // E0009: the result of a comparison is a bool
+    assert_eq!(check("let int x = 1 < 2;"), vec!("E0009"));
+    assert_eq!(notes("let b = \"a\" < \"b\";"), vec!("comparisons are only defined for two numbers (int or float) or two chars"));
+}
+
+#[test]
+fn equality_needs_the_same_types() {
+    assert_eq!(check("let bool a = 1 == 2; let bool b = 'a' != 'b'; let bool c = true == false; let bool d = 1.5 != 2.5;"), Vec::<&str>::new());
+    // E0017: strings can't be compared, and different types can't be mixed
+    assert_eq!(check("let b = \"a\" == \"a\";"), vec!("E0017"));
+    assert_eq!(check("let b = 1 == true;"), vec!("E0017"));
+    assert_eq!(check("let b = 'a' != \"a\";"), vec!("E0017"));
+    assert_eq!(notes("let b = 1 == true;"), vec!("only values of the same type can be compared for equality (int and float can be mixed), strings can't be compared"));
+}
+
+#[test]
+fn logical_operators_need_bools() {
+    assert_eq!(check("let x = 1; let bool b = x > 0 && x < 10 || !(x == 5);"), Vec::<&str>::new());
+    // E0017: invalid binary operands, E0016: invalid unary operand
+    assert_eq!(check("let b = 1 && true;"), vec!("E0017"));
+    assert_eq!(check("let b = true || \"yes\";"), vec!("E0017"));
+    assert_eq!(check("let b = !1;"), vec!("E0016"));
+    assert_eq!(notes("let b = 1 || 0;"), vec!("logical operations are only defined for bool"));
+    // the other notes of E0017
+    assert_eq!(notes("let b = true + 1;"), vec!("arithmetic operations are only defined for numbers (ints and floats)"));
+    assert_eq!(notes("let b = 1.5 << 1;"), vec!("shifts are only defined for ints"));
+}